@@ -8,7 +8,7 @@ use {
             NewConnectionConfig,
         },
     },
-    solana_quic_client::{QuicConfig, QuicConnectionManager, QuicPool},
+    solana_quic_client::{quic_client::close_pools, QuicConfig, QuicConnectionManager, QuicPool},
     solana_sdk::{
         pubkey::Pubkey, quic::NotifyKeyUpdate, signature::Keypair,
         transport::Result as TransportResult,
@@ -19,6 +19,7 @@ use {
         error::Error,
         net::{IpAddr, Ipv4Addr, SocketAddr},
         sync::{Arc, RwLock},
+        time::Duration,
     },
 };
 
@@ -142,6 +143,16 @@ impl ConnectionCache {
         Self::Udp(Arc::new(cache))
     }
 
+    /// Closes all of the connections gracefully, e.g. on validator shutdown, waiting up to
+    /// `drain_timeout` for their in-flight streams. Returns whether all of them have been finished
+    /// in time. Nothing is done for udp, which has no connections to close.
+    pub fn close(&self, drain_timeout: Duration) -> bool {
+        match self {
+            Self::Quic(cache) => close_pools(&cache.pools(), drain_timeout),
+            Self::Udp(_) => true,
+        }
+    }
+
     pub fn use_quic(&self) -> bool {
         matches!(self, Self::Quic(_))
     }
@@ -298,4 +309,42 @@ mod tests {
         response_recv_exit.store(true, Ordering::Relaxed);
         response_recv_thread.join().unwrap();
     }
+
+    #[test]
+    fn test_close() {
+        let (server_socket, exit, keypair) = server_args();
+        let (sender, _receiver) = unbounded();
+        let server_addr = server_socket.local_addr().unwrap();
+        let SpawnServerResult {
+            endpoint: _,
+            thread: server_thread,
+            key_updater: _,
+        } = solana_streamer::quic::spawn_server(
+            "solQuicTest",
+            "quic_streamer_test",
+            server_socket,
+            &keypair,
+            sender,
+            exit.clone(),
+            1,
+            Arc::new(RwLock::new(StakedNodes::default())),
+            10,
+            10,
+            DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
+            DEFAULT_TPU_COALESCE,
+        )
+        .unwrap();
+
+        let connection_cache = ConnectionCache::new_quic("connection_cache_test", 1);
+        let conn = connection_cache.get_connection(&server_addr);
+        conn.send_data(&[0u8; 8]).unwrap();
+
+        assert!(connection_cache.close(Duration::from_secs(1)));
+        // the pooled connection doesn't accept any send once closed
+        let conn = connection_cache.get_connection(&server_addr);
+        assert!(conn.send_data(&[0u8; 8]).is_err());
+
+        exit.store(true, Ordering::Relaxed);
+        server_thread.join().unwrap();
+    }
 }
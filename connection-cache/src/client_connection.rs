@@ -28,6 +28,10 @@ pub struct ClientStats {
     pub prepare_connection_us: AtomicU64,
     /// Count of packets successfully sent
     pub successful_packets: AtomicU64,
+    /// Count of packets resent over a re-established connection after it was lost mid-batch
+    pub batch_retried_packets: AtomicU64,
+    /// Count of packets given up on after exhausting the mid-batch retries
+    pub batch_lost_packets: AtomicU64,
 }
 
 pub trait ClientConnection: Sync + Send {
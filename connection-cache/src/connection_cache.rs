@@ -143,6 +143,16 @@ where
         map.clear();
         self.connection_manager.update_key(key)
     }
+
+    /// Returns the pools of all of the remote addresses, e.g. to close their connections on
+    /// shutdown.
+    pub fn pools(&self) -> Vec<P>
+    where
+        P: Clone,
+    {
+        self.map.read().unwrap().values().cloned().collect()
+    }
+
     /// Create a lazy connection object under the exclusive lock of the cache map if there is not
     /// enough used connections in the connection pool for the specified address.
     /// Returns CreateConnectionResult.
@@ -1,8 +1,75 @@
 use {
     crate::client_connection::ClientStats,
-    std::sync::atomic::{AtomicU64, Ordering},
+    solana_sdk::pubkey::Pubkey,
+    std::{
+        collections::HashMap,
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, RwLock,
+        },
+    },
 };
 
+/// Identifies a peer in the per-peer stats: by the identity presented by its certificate once
+/// known from a handshake, so that the stats of all of its addresses are merged, and by its
+/// address otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PeerKey {
+    Identity(Pubkey),
+    Addr(SocketAddr),
+}
+
+/// Stats attributed to a single peer, so that a peer with packet loss can be told apart from the
+/// others, which is impossible with the aggregated stats.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PeerStats {
+    pub sent_packets: u64,
+    pub sent_bytes: u64,
+    pub send_errors: u64,
+    pub handshakes: u64,
+    pub handshake_us: u64,
+}
+
+/// The counters of [`PeerStats`], which clients retain once obtained with
+/// [`ConnectionCacheStats::peer_stats`], so that sends don't look up the peer.
+#[derive(Debug, Default)]
+pub struct PeerStatsCounters {
+    sent_packets: AtomicU64,
+    sent_bytes: AtomicU64,
+    send_errors: AtomicU64,
+    handshakes: AtomicU64,
+    handshake_us: AtomicU64,
+}
+
+impl PeerStatsCounters {
+    pub fn add_handshake(&self, handshake_us: u64) {
+        self.handshakes.fetch_add(1, Ordering::Relaxed);
+        self.handshake_us.fetch_add(handshake_us, Ordering::Relaxed);
+    }
+
+    pub fn add_send(&self, num_packets: usize, num_bytes: usize, is_success: bool) {
+        if is_success {
+            self.sent_packets
+                .fetch_add(num_packets as u64, Ordering::Relaxed);
+            self.sent_bytes
+                .fetch_add(num_bytes as u64, Ordering::Relaxed);
+        } else {
+            self.send_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn load(&self) -> PeerStats {
+        PeerStats {
+            sent_packets: self.sent_packets.load(Ordering::Relaxed),
+            sent_bytes: self.sent_bytes.load(Ordering::Relaxed),
+            send_errors: self.send_errors.load(Ordering::Relaxed),
+            handshakes: self.handshakes.load(Ordering::Relaxed),
+            handshake_us: self.handshake_us.load(Ordering::Relaxed),
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct ConnectionCacheStats {
     pub cache_hits: AtomicU64,
@@ -21,6 +88,10 @@ pub struct ConnectionCacheStats {
     // Need to track these separately per-connection
     // because we need to track the base stat value from quinn
     pub total_client_stats: ClientStats,
+
+    // Only written when a client connects to a peer for the first time, and pruned of the peers
+    // which no client refers to anymore in report(), as leaders rotate.
+    per_peer_stats: RwLock<HashMap<PeerKey, Arc<PeerStatsCounters>>>,
 }
 
 pub const CONNECTION_STAT_SUBMISSION_INTERVAL: u64 = 2000;
@@ -72,6 +143,14 @@ impl ConnectionCacheStats {
             client_stats.prepare_connection_us.load(Ordering::Relaxed),
             Ordering::Relaxed,
         );
+        self.total_client_stats.batch_retried_packets.fetch_add(
+            client_stats.batch_retried_packets.load(Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
+        self.total_client_stats.batch_lost_packets.fetch_add(
+            client_stats.batch_lost_packets.load(Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
         self.sent_packets
             .fetch_add(num_packets as u64, Ordering::Relaxed);
         self.total_batches.fetch_add(1, Ordering::Relaxed);
@@ -82,6 +161,30 @@ impl ConnectionCacheStats {
         }
     }
 
+    /// Returns the counters of the given peer, which the caller should retain for its sends to
+    /// the peer.
+    pub fn peer_stats(&self, peer_key: PeerKey) -> Arc<PeerStatsCounters> {
+        if let Some(peer_stats) = self.per_peer_stats.read().unwrap().get(&peer_key) {
+            return peer_stats.clone();
+        }
+        self.per_peer_stats
+            .write()
+            .unwrap()
+            .entry(peer_key)
+            .or_default()
+            .clone()
+    }
+
+    /// Returns a snapshot of the stats accumulated for each peer so far.
+    pub fn per_peer_summary(&self) -> HashMap<PeerKey, PeerStats> {
+        self.per_peer_stats
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(peer_key, peer_stats)| (*peer_key, peer_stats.load()))
+            .collect()
+    }
+
     pub(super) fn report(&self, name: &'static str) {
         let successful_packets = self
             .total_client_stats
@@ -218,6 +321,20 @@ impl ConnectionCacheStats {
                 self.batch_failure.swap(0, Ordering::Relaxed),
                 i64
             ),
+            (
+                "batch_retried_packets",
+                self.total_client_stats
+                    .batch_retried_packets
+                    .swap(0, Ordering::Relaxed),
+                i64
+            ),
+            (
+                "batch_lost_packets",
+                self.total_client_stats
+                    .batch_lost_packets
+                    .swap(0, Ordering::Relaxed),
+                i64
+            ),
             (
                 "send_timeout",
                 self.total_client_stats
@@ -233,5 +350,66 @@ impl ConnectionCacheStats {
                 i64
             ),
         );
+
+        // the peers of dropped clients (e.g. evicted as the leaders rotated) are done with
+        self.per_peer_stats
+            .write()
+            .unwrap()
+            .retain(|_peer_key, peer_stats| Arc::strong_count(peer_stats) > 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::net::{IpAddr, Ipv4Addr},
+    };
+
+    #[test]
+    fn test_per_peer_summary() {
+        let stats = ConnectionCacheStats::default();
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 8002);
+        let identity = Pubkey::new_unique();
+
+        // the addresses of the same identity share the stats
+        let peer_stats1 = stats.peer_stats(PeerKey::Identity(identity));
+        let peer_stats2 = stats.peer_stats(PeerKey::Identity(identity));
+        assert!(Arc::ptr_eq(&peer_stats1, &peer_stats2));
+        peer_stats1.add_handshake(10);
+        peer_stats2.add_handshake(20);
+        peer_stats1.add_send(3, 300, true);
+        peer_stats2.add_send(2, 200, false);
+        let peer_stats3 = stats.peer_stats(PeerKey::Addr(addr));
+        peer_stats3.add_send(1, 100, true);
+
+        let summary = stats.per_peer_summary();
+        assert_eq!(summary.len(), 2);
+        assert_eq!(
+            summary[&PeerKey::Identity(identity)],
+            PeerStats {
+                sent_packets: 3,
+                sent_bytes: 300,
+                send_errors: 1,
+                handshakes: 2,
+                handshake_us: 30,
+            }
+        );
+        assert_eq!(
+            summary[&PeerKey::Addr(addr)],
+            PeerStats {
+                sent_packets: 1,
+                sent_bytes: 100,
+                ..PeerStats::default()
+            }
+        );
+
+        // the peers which are no longer referred to are pruned upon reporting
+        drop((peer_stats1, peer_stats2));
+        stats.report("test_per_peer_summary");
+        assert_eq!(
+            stats.per_peer_summary().into_keys().collect::<Vec<_>>(),
+            vec![PeerKey::Addr(addr)]
+        );
     }
 }
@@ -2970,6 +2970,8 @@ impl ReplayStage {
                             ancestor_hashes_replay_update_sender,
                             purge_repair_slot_counter,
                         );
+                        // the already-scheduled transactions are pointless to execute anymore
+                        bank.abort_scheduler();
                         // don't try to run the below logic to check if the bank is completed
                         continue;
                     }
@@ -2985,24 +2987,50 @@ impl ReplayStage {
 
                 let replay_stats = bank_progress.replay_stats.clone();
 
-                if let Some((result, completed_execute_timings)) =
-                    bank.wait_for_completed_scheduler()
+                if let Some((result_with_timings, summary)) =
+                    bank.wait_for_completed_scheduler_with_summary()
                 {
-                    let metrics = ExecuteBatchesInternalMetrics::new_with_timings_from_all_threads(
-                        completed_execute_timings,
+                    // measured by the scheduler, separately from the rest of the replay
+                    datapoint_info!(
+                        "replay-slot-scheduler_session",
+                        ("slot", bank_slot, i64),
+                        (
+                            "first_dispatch_us",
+                            summary.first_dispatch.map(|d| d.as_micros() as i64),
+                            Option<i64>
+                        ),
+                        (
+                            "last_commit_us",
+                            summary.last_commit.map(|d| d.as_micros() as i64),
+                            Option<i64>
+                        ),
                     );
-                    replay_stats
-                        .write()
-                        .unwrap()
-                        .batch_execute
-                        .accumulate(metrics);
+                    let result = match result_with_timings {
+                        Ok((result, completed_execute_timings)) => {
+                            let metrics =
+                                ExecuteBatchesInternalMetrics::new_with_timings_from_all_threads(
+                                    completed_execute_timings,
+                                );
+                            replay_stats
+                                .write()
+                                .unwrap()
+                                .batch_execute
+                                .accumulate(metrics);
+                            result.map_err(BlockstoreProcessorError::InvalidTransaction)
+                        }
+                        // some of the transactions may not have been executed, so the bank can't
+                        // be frozen. but this is a local failure, which says nothing about the
+                        // validity of the block itself; marking the slot dead would make this
+                        // node permanently reject a valid block and fork off the cluster.
+                        Err(err) => panic!("slot {bank_slot}: the scheduler has failed: {err}"),
+                    };
 
                     if let Err(err) = result {
                         Self::mark_dead_slot(
                             blockstore,
                             bank,
                             bank_forks.read().unwrap().root(),
-                            &BlockstoreProcessorError::InvalidTransaction(err),
+                            &err,
                             rpc_subscriptions,
                             duplicate_slots_tracker,
                             duplicate_confirmed_slots,
@@ -4359,10 +4387,13 @@ pub(crate) mod tests {
             poh_config::PohConfig,
             signature::{Keypair, Signer},
             system_transaction,
-            transaction::TransactionError,
+            transaction::{self, SanitizedTransaction, TransactionError},
         },
         solana_streamer::socket::SocketAddrSpace,
         solana_transaction_status::VersionedTransactionWithStatusMeta,
+        solana_unified_scheduler_pool::{
+            HandlerContext, PooledScheduler, SchedulerPool, TaskHandler,
+        },
         solana_vote_program::{
             vote_state::{self, VoteStateVersions},
             vote_transaction,
@@ -5110,6 +5141,116 @@ pub(crate) mod tests {
         res
     }
 
+    #[test]
+    fn test_scheduler_failure_doesnt_mark_slot_dead() {
+        solana_logger::setup();
+
+        #[derive(Debug)]
+        struct PanickingTaskHandler;
+        impl TaskHandler for PanickingTaskHandler {
+            fn handle(
+                _result: &mut transaction::Result<()>,
+                _timings: &mut ExecuteTimings,
+                _bank: &Arc<Bank>,
+                _transaction: &SanitizedTransaction,
+                index: usize,
+                _handler_context: &HandlerContext,
+            ) {
+                panic!("task {index} panicked");
+            }
+        }
+
+        let ReplayBlockstoreComponents {
+            blockstore,
+            vote_simulator,
+            ..
+        } = replay_blockstore_components(Some(tr(0)), 1, None);
+        let VoteSimulator {
+            mut progress,
+            bank_forks,
+            mut heaviest_subtree_fork_choice,
+            validator_keypairs,
+            ..
+        } = vote_simulator;
+        let pool = SchedulerPool::<PooledScheduler<PanickingTaskHandler>, _>::new(
+            None,
+            None,
+            None,
+            None,
+            Arc::new(PrioritizationFeeCache::new(0u64)),
+        );
+        bank_forks.write().unwrap().install_scheduler_pool(pool);
+
+        let bank0 = bank_forks.read().unwrap().get(0).unwrap();
+        let bank1 = Bank::new_from_parent(bank0.clone(), &Pubkey::default(), 1);
+        bank_forks.write().unwrap().insert(bank1);
+        let bank1 = bank_forks.read().unwrap().get_with_scheduler(1).unwrap();
+        progress
+            .entry(bank1.slot())
+            .or_insert_with(|| ForkProgress::new(bank1.last_blockhash(), None, None, 0, 0));
+
+        // the block itself is perfectly valid, only the local handler is broken
+        let tx = SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+            &validator_keypairs.values().next().unwrap().node_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            2,
+            bank0.last_blockhash(),
+        ));
+        bank1.schedule_transaction_executions([(&tx, &0)].into_iter());
+        bank1.fill_bank_with_ticks_for_tests();
+        assert!(bank1.is_complete());
+
+        let exit = Arc::new(AtomicBool::new(false));
+        let rpc_subscriptions = Arc::new(RpcSubscriptions::new_for_tests(
+            exit,
+            Arc::new(AtomicU64::default()),
+            Arc::new(AtomicU64::default()),
+            bank_forks.clone(),
+            Arc::new(RwLock::new(BlockCommitmentCache::default())),
+            OptimisticallyConfirmedBank::locked_from_bank_forks_root(&bank_forks),
+        ));
+        let (cluster_slots_update_sender, _cluster_slots_update_receiver) = unbounded();
+        let (cost_update_sender, _cost_update_receiver) = unbounded();
+        let (ancestor_hashes_replay_update_sender, _ancestor_hashes_replay_update_receiver) =
+            unbounded();
+        let replay_result_vec = [ReplaySlotFromBlockstore {
+            is_slot_dead: false,
+            bank_slot: bank1.slot(),
+            replay_result: Some(Ok(1)),
+        }];
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            ReplayStage::process_replay_results(
+                &blockstore,
+                &bank_forks,
+                &mut progress,
+                None,
+                None,
+                &mut heaviest_subtree_fork_choice,
+                &None,
+                &None,
+                &rpc_subscriptions,
+                &mut DuplicateSlotsTracker::default(),
+                &DuplicateConfirmedSlots::new(),
+                &mut EpochSlotsFrozenSlots::default(),
+                &mut UnfrozenGossipVerifiedVoteHashes::default(),
+                &mut LatestValidatorVotesForFrozenBanks::default(),
+                &cluster_slots_update_sender,
+                &cost_update_sender,
+                &mut DuplicateSlotsToRepair::default(),
+                &ancestor_hashes_replay_update_sender,
+                None,
+                &replay_result_vec,
+                &mut PurgeRepairSlotCounter::default(),
+            )
+        }));
+
+        // the local failure brings down replay, instead of rejecting the block forever
+        assert!(result.is_err());
+        assert!(!bank1.is_frozen());
+        assert!(!progress.get(&bank1.slot()).unwrap().is_dead);
+        assert!(!blockstore.is_dead(bank1.slot()));
+    }
+
     #[test]
     fn test_replay_commitment_cache() {
         fn leader_vote(vote_slot: Slot, bank: &Bank, pubkey: &Pubkey) {
@@ -137,6 +137,8 @@ use {
 };
 
 const MAX_COMPLETED_DATA_SETS_IN_CHANNEL: usize = 100_000;
+// How long the in-flight streams of the tpu connections are waited for on shutdown
+const CONNECTION_CACHE_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
 const WAIT_FOR_SUPERMAJORITY_THRESHOLD_PERCENT: u64 = 80;
 // Right now since we reuse the wait for supermajority code, the
 // following threshold should always greater than or equal to
@@ -480,6 +482,8 @@ pub struct Validator {
     repair_quic_endpoint: Option<Endpoint>,
     repair_quic_endpoint_runtime: Option<TokioRuntime>,
     repair_quic_endpoint_join_handle: Option<repair::quic_endpoint::AsyncTryJoinHandle>,
+    scheduler_pool: Option<Arc<DefaultSchedulerPool>>,
+    connection_cache: Arc<ConnectionCache>,
 }
 
 impl Validator {
@@ -817,7 +821,7 @@ impl Validator {
         // (by both replay stage and banking stage)
         let prioritization_fee_cache = Arc::new(PrioritizationFeeCache::default());
 
-        match &config.block_verification_method {
+        let scheduler_pool = match &config.block_verification_method {
             BlockVerificationMethod::BlockstoreProcessor => {
                 info!("no scheduler pool is installed for block verification...");
                 if let Some(count) = config.unified_scheduler_handler_threads {
@@ -826,21 +830,27 @@ impl Validator {
                          scheduler isn't enabled"
                     );
                 }
+                None
             }
             BlockVerificationMethod::UnifiedScheduler => {
-                let scheduler_pool = DefaultSchedulerPool::new_dyn(
+                let scheduler_pool = DefaultSchedulerPool::new(
                     config.unified_scheduler_handler_threads,
                     config.runtime_config.log_messages_bytes_limit,
                     transaction_status_sender.clone(),
                     Some(replay_vote_sender.clone()),
                     prioritization_fee_cache.clone(),
                 );
+                let self_test_duration = scheduler_pool
+                    .self_test()
+                    .map_err(|err| format!("unified scheduler self-test failed: {err}"))?;
+                info!("unified scheduler self-test passed in {self_test_duration:?}");
                 bank_forks
                     .write()
                     .unwrap()
-                    .install_scheduler_pool(scheduler_pool);
+                    .install_scheduler_pool(scheduler_pool.clone());
+                Some(scheduler_pool)
             }
-        }
+        };
 
         let leader_schedule_cache = Arc::new(leader_schedule_cache);
         let entry_notification_sender = entry_notifier_service
@@ -1395,7 +1405,7 @@ impl Validator {
         );
 
         *start_progress.write().unwrap() = ValidatorStartProgress::Running;
-        key_notifies.push(connection_cache);
+        key_notifies.push(connection_cache.clone());
 
         *admin_rpc_service_post_init.write().unwrap() = Some(AdminRpcRequestMetadataPostInit {
             bank_forks: bank_forks.clone(),
@@ -1444,6 +1454,8 @@ impl Validator {
             repair_quic_endpoint,
             repair_quic_endpoint_runtime,
             repair_quic_endpoint_join_handle,
+            scheduler_pool,
+            connection_cache,
         })
     }
 
@@ -1582,6 +1594,14 @@ impl Validator {
         }
         self.tpu.join().expect("tpu");
         self.tvu.join().expect("tvu");
+        // no more transactions or votes are sent once tpu and tvu have stopped
+        if !self.connection_cache.close(CONNECTION_CACHE_DRAIN_TIMEOUT) {
+            warn!("Closed the tpu connections with unfinished streams");
+        }
+        if let Some(scheduler_pool) = self.scheduler_pool {
+            // replay has stopped; no scheduler is taken anymore.
+            scheduler_pool.shutdown_all();
+        }
         if let Some(turbine_quic_endpoint_join_handle) = self.turbine_quic_endpoint_join_handle {
             self.turbine_quic_endpoint_runtime
                 .map(|runtime| runtime.block_on(turbine_quic_endpoint_join_handle))
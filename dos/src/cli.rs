@@ -1,8 +1,8 @@
 use {
     clap::{crate_description, crate_name, crate_version, ArgEnum, Args, Parser},
     serde::{Deserialize, Serialize},
-    solana_sdk::pubkey::Pubkey,
-    std::{net::SocketAddr, process::exit, str::FromStr},
+    solana_sdk::{clock::Slot, pubkey::Pubkey},
+    std::{net::SocketAddr, path::PathBuf, process::exit, str::FromStr, time::Duration},
 };
 
 #[derive(Parser, Debug, PartialEq, Eq)]
@@ -67,6 +67,42 @@ pub struct DosClientParameters {
 
     #[clap(long, default_value = "16384", help = "Size of the transactions batch")]
     pub send_batch_size: usize,
+
+    #[clap(flatten)]
+    pub ledger_replay_params: LedgerReplayParams,
+
+    #[clap(flatten)]
+    pub funding_params: FundingParams,
+
+    #[clap(flatten)]
+    pub slow_stream_params: SlowStreamParams,
+
+    #[clap(
+        long,
+        help = "Sample the target's RPC (health, slot progression, performance samples) every \
+                given number of milliseconds during the attack and log the observations"
+    )]
+    pub sample_target_rpc_interval_ms: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Seed the random number generators of all threads, so that generated keypairs, \
+                payloads and permutations are the same across runs. Note that network timing, \
+                and hence the interleaving of the sent packets, still varies"
+    )]
+    pub seed: Option<u64>,
+
+    #[clap(
+        long,
+        requires("sample-target-rpc-interval-ms"),
+        multiple_occurrences(true),
+        parse(try_from_str = stop_condition_parser),
+        help = "Stop the attack once the target degrades as observed by the RPC sampling, so that \
+                the cluster is preserved for postmortem. Either `unhealthy` (RPC health check \
+                fails) or `slot-stall:<SECONDS>` (slot doesn't advance for the given duration). \
+                May be specified multiple times to stop on any of the conditions"
+    )]
+    pub stop_when: Vec<StopCondition>,
 }
 
 #[derive(Args, Clone, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
@@ -107,10 +143,130 @@ pub struct TransactionParams {
 
     #[clap(
         long,
-        required_if_eq("transaction-type", "transfer"),
-        help = "Number of instructions in transfer transaction"
+        required_if_eq_any(&[
+            ("transaction-type", "transfer"),
+            ("transaction-type", "v0-with-lookup-tables"),
+        ]),
+        help = "Number of instructions in transfer transaction, which is also the number of \
+                addresses in the lookup table for v0-with-lookup-tables"
     )]
     pub num_instructions: Option<usize>,
+
+    #[clap(
+        long,
+        requires("valid-blockhash"),
+        parse(try_from_str = blockhash_age_distribution_parser),
+        help = "Sign transactions with blockhashes of varying ages instead of the latest one, \
+                weighted as `just-valid:W,about-to-expire:W,just-expired:W` (omitted ages weigh \
+                0), to exercise the age checks and the status cache. The ages are tracked from \
+                the blockhashes observed since the start, so the older ones become available \
+                only once the attack has run for their age"
+    )]
+    pub blockhash_age_distribution: Option<BlockhashAgeDistribution>,
+}
+
+#[derive(Args, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[clap(rename_all = "kebab-case")]
+pub struct FundingParams {
+    #[clap(
+        long,
+        default_value = "1000000",
+        help = "Lamports to fund each payer with, in addition to the rent-exempt minimum, \
+                relevant only for valid-blockhash"
+    )]
+    pub lamports_per_payer: u64,
+
+    #[clap(
+        long,
+        help = "Top up the payers to lamports-per-payer by airdrop once their balances drop below \
+                the given lamports, so that long-running attacks don't run dry"
+    )]
+    pub refund_payers_below: Option<u64>,
+
+    #[clap(
+        long,
+        parse(try_from_str = pubkey_parser),
+        help = "Transfer the leftover funds of the payers to the given account (e.g. the faucet) \
+                once the attack is over"
+    )]
+    pub sweep_payers_to: Option<Pubkey>,
+}
+
+impl Default for FundingParams {
+    fn default() -> Self {
+        Self {
+            lamports_per_payer: 1_000_000,
+            refund_payers_below: None,
+            sweep_payers_to: None,
+        }
+    }
+}
+
+#[derive(Args, Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[clap(rename_all = "kebab-case")]
+pub struct SlowStreamParams {
+    #[clap(
+        long,
+        requires("tpu-use-quic"),
+        help = "Keep the given number of QUIC streams open to the TPU, writing the data into each \
+                of them slowly (slow-loris) instead of sending it at full speed, to test the \
+                stream timeouts and the resource limits of the server"
+    )]
+    pub slow_stream_count: Option<usize>,
+
+    #[clap(
+        long,
+        default_value = "1",
+        help = "Bytes per second written into each stream, relevant only for slow-stream-count"
+    )]
+    pub slow_stream_bytes_per_second: u64,
+}
+
+impl Default for SlowStreamParams {
+    fn default() -> Self {
+        Self {
+            slow_stream_count: None,
+            slow_stream_bytes_per_second: 1,
+        }
+    }
+}
+
+#[derive(Args, Clone, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[clap(rename_all = "kebab-case")]
+pub struct LedgerReplayParams {
+    #[clap(
+        long,
+        required_if_eq("data-type", "ledger-transaction"),
+        help = "Path to the ledger to read transactions from, relevant only for \
+                data-type=ledger-transaction"
+    )]
+    pub ledger_path: Option<PathBuf>,
+
+    #[clap(
+        long,
+        default_value = "0",
+        help = "First slot to replay transactions from"
+    )]
+    pub replay_start_slot: Slot,
+
+    #[clap(
+        long,
+        help = "Last slot (inclusive) to replay transactions from. [default: highest slot in \
+                the ledger]"
+    )]
+    pub replay_end_slot: Option<Slot>,
+
+    #[clap(
+        long,
+        help = "Maximum number of replayed transactions sent per second. [default: unlimited]"
+    )]
+    pub replay_rate: Option<u64>,
+
+    #[clap(
+        long,
+        help = "Shuffle replayed transactions instead of preserving the ledger order"
+    )]
+    pub replay_shuffle: bool,
 }
 
 #[derive(ArgEnum, Clone, Copy, Debug, Eq, PartialEq)]
@@ -133,12 +289,66 @@ pub enum DataType {
     GetAccountInfo,
     GetProgramAccounts,
     Transaction,
+    LedgerTransaction,
 }
 
 #[derive(ArgEnum, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum TransactionType {
     Transfer,
     AccountCreation,
+    V0WithLookupTables,
+}
+
+/// Relative weights of the ages of the blockhashes which transactions are signed with
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct BlockhashAgeDistribution {
+    /// The latest blockhash
+    pub just_valid: u32,
+    /// The oldest blockhash which is still valid
+    pub about_to_expire: u32,
+    /// The newest blockhash which has expired
+    pub just_expired: u32,
+}
+
+fn blockhash_age_distribution_parser(
+    distribution: &str,
+) -> Result<BlockhashAgeDistribution, &'static str> {
+    let mut result = BlockhashAgeDistribution::default();
+    for age_weight in distribution.split(',') {
+        let (age, weight) = age_weight
+            .split_once(':')
+            .ok_or("expected AGE:WEIGHT pairs separated by commas")?;
+        let weight = weight.parse().map_err(|_| "failed to parse weight")?;
+        match age {
+            "just-valid" => result.just_valid = weight,
+            "about-to-expire" => result.about_to_expire = weight,
+            "just-expired" => result.just_expired = weight,
+            _ => return Err("age must be one of just-valid, about-to-expire and just-expired"),
+        }
+    }
+    if result == BlockhashAgeDistribution::default() {
+        return Err("at least one age must have a positive weight");
+    }
+    Ok(result)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopCondition {
+    Unhealthy,
+    SlotStall(Duration),
+}
+
+fn stop_condition_parser(condition: &str) -> Result<StopCondition, &'static str> {
+    match condition.split_once(':') {
+        None if condition == "unhealthy" => Ok(StopCondition::Unhealthy),
+        Some(("slot-stall", seconds)) => match seconds.parse() {
+            Ok(seconds) if seconds > 0 => {
+                Ok(StopCondition::SlotStall(Duration::from_secs(seconds)))
+            }
+            _ => Err("slot-stall duration must be a positive number of seconds"),
+        },
+        _ => Err("failed to parse stop condition"),
+    }
 }
 
 fn addr_parser(addr: &str) -> Result<SocketAddr, &'static str> {
@@ -155,6 +365,9 @@ fn pubkey_parser(pubkey: &str) -> Result<Pubkey, &'static str> {
     }
 }
 
+// the number of addresses which a single lookup table can hold
+const MAX_LOOKUP_TABLE_ADDRESSES: usize = 256;
+
 /// input checks which are not covered by Clap
 fn validate_input(params: &DosClientParameters) {
     if params.mode == Mode::Rpc
@@ -172,6 +385,77 @@ fn validate_input(params: &DosClientParameters) {
             exit(1);
         }
     }
+
+    let tp = &params.transaction_params;
+    if tp.transaction_type == Some(TransactionType::V0WithLookupTables)
+        && tp.num_instructions.unwrap_or(0) > MAX_LOOKUP_TABLE_ADDRESSES
+    {
+        eprintln!(
+            "num-instructions must not exceed {MAX_LOOKUP_TABLE_ADDRESSES} for v0-with-lookup-tables"
+        );
+        exit(1);
+    }
+
+    if tp.valid_blockhash {
+        let fp = &params.funding_params;
+        if fp.refund_payers_below.unwrap_or(0) >= fp.lamports_per_payer {
+            eprintln!("refund-payers-below must be less than lamports-per-payer");
+            exit(1);
+        }
+    } else if params.funding_params != FundingParams::default() {
+        eprintln!("Funding arguments are ignored unless valid-blockhash is set");
+        exit(1);
+    }
+
+    if params.data_type == DataType::LedgerTransaction {
+        if params.mode != Mode::Tpu && params.mode != Mode::TpuForwards {
+            eprintln!(
+                "data-type ledger-transaction is supported only with mode tpu or tpu-forwards"
+            );
+            exit(1);
+        }
+        let lp = &params.ledger_replay_params;
+        if lp.replay_end_slot.unwrap_or(Slot::MAX) < lp.replay_start_slot {
+            eprintln!("replay-end-slot must not be less than replay-start-slot");
+            exit(1);
+        }
+        if lp.replay_rate == Some(0) {
+            eprintln!("replay-rate must be positive");
+            exit(1);
+        }
+    } else if params.ledger_replay_params != LedgerReplayParams::default() {
+        eprintln!("Ledger replay arguments are ignored if data-type != ledger-transaction");
+        exit(1);
+    }
+
+    let sp = &params.slow_stream_params;
+    if let Some(slow_stream_count) = sp.slow_stream_count {
+        if params.mode != Mode::Tpu && params.mode != Mode::TpuForwards {
+            eprintln!("slow-stream-count is supported only with mode tpu or tpu-forwards");
+            exit(1);
+        }
+        if params.data_type != DataType::Random && params.data_type != DataType::Transaction
+            || params.transaction_params.unique_transactions
+        {
+            eprintln!(
+                "slow-stream-count is supported only with data-type random or transaction, \
+                 without unique-transactions"
+            );
+            exit(1);
+        }
+        if slow_stream_count == 0 || sp.slow_stream_bytes_per_second == 0 {
+            eprintln!("slow-stream-count and slow-stream-bytes-per-second must be positive");
+            exit(1);
+        }
+    } else if *sp != SlowStreamParams::default() {
+        eprintln!("slow-stream-bytes-per-second is ignored unless slow-stream-count is set");
+        exit(1);
+    }
+
+    if params.sample_target_rpc_interval_ms == Some(0) {
+        eprintln!("sample-target-rpc-interval-ms must be positive");
+        exit(1);
+    }
 }
 
 pub fn build_cli_parameters() -> DosClientParameters {
@@ -230,10 +514,132 @@ mod tests {
                 tpu_use_quic: false,
                 num_gen_threads: 1,
                 send_batch_size: 16384,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
     }
 
+    #[test]
+    fn test_cli_parse_blockhash_age_distribution() {
+        assert_eq!(
+            blockhash_age_distribution_parser("just-valid:5,just-expired:1"),
+            Ok(BlockhashAgeDistribution {
+                just_valid: 5,
+                about_to_expire: 0,
+                just_expired: 1,
+            })
+        );
+        assert!(blockhash_age_distribution_parser("about-to-expire:1").is_ok());
+        assert!(blockhash_age_distribution_parser("just-valid:0").is_err());
+        assert!(blockhash_age_distribution_parser("just-valid").is_err());
+        assert!(blockhash_age_distribution_parser("stale:1").is_err());
+
+        // blockhashes are tracked only with valid-blockhash
+        assert!(DosClientParameters::try_parse_from(vec![
+            "solana-dos",
+            "--mode",
+            "tpu",
+            "--data-type",
+            "transaction",
+            "--blockhash-age-distribution",
+            "just-valid:1",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_stop_when() {
+        let args = |stop_when: &[&'static str]| {
+            let mut args = vec![
+                "solana-dos",
+                "--mode",
+                "tpu",
+                "--data-type",
+                "random",
+                "--sample-target-rpc-interval-ms",
+                "1000",
+            ];
+            for condition in stop_when {
+                args.extend(["--stop-when", condition]);
+            }
+            args
+        };
+
+        let params =
+            DosClientParameters::try_parse_from(args(&["unhealthy", "slot-stall:30"])).unwrap();
+        assert_eq!(
+            params.stop_when,
+            vec![
+                StopCondition::Unhealthy,
+                StopCondition::SlotStall(Duration::from_secs(30))
+            ]
+        );
+
+        assert!(DosClientParameters::try_parse_from(args(&["slot-stall:0"])).is_err());
+        assert!(DosClientParameters::try_parse_from(args(&["slot-stall"])).is_err());
+        assert!(DosClientParameters::try_parse_from(args(&["crashed"])).is_err());
+
+        // conditions are evaluated by the rpc sampling
+        let result = DosClientParameters::try_parse_from(vec![
+            "solana-dos",
+            "--mode",
+            "tpu",
+            "--data-type",
+            "random",
+            "--stop-when",
+            "unhealthy",
+        ]);
+        assert_eq!(
+            result.unwrap_err().kind(),
+            clap::error::ErrorKind::MissingRequiredArgument
+        );
+    }
+
+    #[test]
+    fn test_cli_parse_slow_streams() {
+        let args = |extra_args: &[&'static str]| {
+            let mut args = vec!["solana-dos", "--mode", "tpu", "--data-type", "random"];
+            args.extend(extra_args);
+            args
+        };
+
+        let params = DosClientParameters::try_parse_from(args(&[
+            "--tpu-use-quic",
+            "--slow-stream-count",
+            "100",
+            "--slow-stream-bytes-per-second",
+            "10",
+        ]))
+        .unwrap();
+        assert_eq!(
+            params.slow_stream_params,
+            SlowStreamParams {
+                slow_stream_count: Some(100),
+                slow_stream_bytes_per_second: 10,
+            }
+        );
+
+        let params = DosClientParameters::try_parse_from(args(&[
+            "--tpu-use-quic",
+            "--slow-stream-count",
+            "100",
+        ]))
+        .unwrap();
+        assert_eq!(params.slow_stream_params.slow_stream_bytes_per_second, 1);
+
+        // streams are only slowed down over quic
+        let result = DosClientParameters::try_parse_from(args(&["--slow-stream-count", "100"]));
+        assert_eq!(
+            result.unwrap_err().kind(),
+            clap::error::ErrorKind::MissingRequiredArgument
+        );
+    }
+
     #[test]
     fn test_cli_parse_dos_valid_signatures() {
         let entrypoint_addr: SocketAddr = "127.0.0.1:8001".parse().unwrap();
@@ -270,9 +676,16 @@ mod tests {
                     unique_transactions: true,
                     transaction_type: None,
                     num_instructions: None,
+                    blockhash_age_distribution: None,
                 },
                 tpu_use_quic: true,
                 send_batch_size: 1,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
     }
@@ -314,9 +727,16 @@ mod tests {
                     unique_transactions: true,
                     transaction_type: Some(TransactionType::Transfer),
                     num_instructions: Some(1),
+                    blockhash_age_distribution: None,
                 },
                 tpu_use_quic: false,
                 send_batch_size: 1,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
 
@@ -373,9 +793,16 @@ mod tests {
                     unique_transactions: true,
                     transaction_type: Some(TransactionType::Transfer),
                     num_instructions: Some(8),
+                    blockhash_age_distribution: None,
                 },
                 tpu_use_quic: false,
                 send_batch_size: 1,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
     }
@@ -415,9 +842,16 @@ mod tests {
                     unique_transactions: true,
                     transaction_type: Some(TransactionType::AccountCreation),
                     num_instructions: None,
+                    blockhash_age_distribution: None,
                 },
                 tpu_use_quic: false,
                 send_batch_size: 1,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
     }
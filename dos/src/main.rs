@@ -37,19 +37,47 @@
 //! ```bash
 //! solana-dos $COMMON --valid-blockhash --transaction-type account-creation
 //! ```
+//! 2.4 Version 0 transaction with accounts loaded from an address lookup table
+//! ```bash
+//! solana-dos $COMMON --valid-blockhash --transaction-type v0-with-lookup-tables --num-instructions 8
+//! ```
+//! 3. Replaying transactions recorded in a local ledger:
+//! ```bash
+//! solana-dos --mode tpu --data-type ledger-transaction --ledger-path ledger \
+//!     --replay-start-slot 100 --replay-end-slot 200 --replay-rate 1000
+//! ```
+//!
+//! To make the generated keypairs and payloads the same across runs, e.g. when bisecting a
+//! regression, pass `--seed <u64>` to any of the above. Network timing, and hence the order in
+//! which the packets arrive, still varies.
+//!
+//! For unattended runs, `--sample-target-rpc-interval-ms 1000 --stop-when slot-stall:30` stops the
+//! attack once the target stops producing slots, preserving it for postmortem.
+//!
+//! To test the stream timeouts and the resource limits of the TPU, the data can be written slowly
+//! into many QUIC streams at once (slow-loris) instead:
+//! ```bash
+//! solana-dos --mode tpu --data-type random --tpu-use-quic \
+//!     --slow-stream-count 512 --slow-stream-bytes-per-second 10
+//! ```
 //!
 #![allow(clippy::arithmetic_side_effects)]
 #![allow(deprecated)]
 use {
     crossbeam_channel::{select, tick, unbounded, Receiver, Sender},
+    histogram::Histogram,
     itertools::Itertools,
     log::*,
-    rand::{thread_rng, Rng},
+    rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng},
+    serde::Serialize,
     solana_bench_tps::{bench::generate_and_fund_keypairs, bench_tps_client::BenchTpsClient},
     solana_client::{
         connection_cache::ConnectionCache, tpu_client::TpuClientWrapper,
         tpu_connection::TpuConnection,
     },
+    solana_connection_cache::{
+        client_connection::ClientStats, connection_cache_stats::ConnectionCacheStats,
+    },
     solana_core::repair::serve_repair::{RepairProtocol, RepairRequestHeader, ServeRepair},
     solana_dos::cli::*,
     solana_gossip::{
@@ -57,26 +85,42 @@ use {
         gossip_service::{discover, get_client},
         legacy_contact_info::LegacyContactInfo as ContactInfo,
     },
+    solana_ledger::{
+        blockstore::Blockstore,
+        blockstore_options::{AccessType, BlockstoreOptions},
+    },
     solana_measure::measure::Measure,
+    solana_quic_client::nonblocking::quic_client::{QuicClient, QuicLazyInitializedEndpoint},
     solana_rpc_client::rpc_client::RpcClient,
     solana_sdk::{
-        hash::Hash,
+        address_lookup_table::{
+            self,
+            state::{AddressLookupTable, LOOKUP_TABLE_META_SIZE},
+            AddressLookupTableAccount,
+        },
+        clock::{Slot, MAX_PROCESSING_AGE},
+        commitment_config::CommitmentConfig,
+        hash::{hashv, Hash},
         instruction::CompiledInstruction,
-        message::Message,
+        message::{v0, Message, VersionedMessage},
         pubkey::Pubkey,
-        signature::{Keypair, Signature, Signer},
+        signature::{keypair_from_seed, Keypair, Signature, Signer},
         stake,
         system_instruction::{self, SystemInstruction},
         system_program,
         timing::timestamp,
-        transaction::Transaction,
+        transaction::{Transaction, VersionedTransaction},
     },
     solana_streamer::socket::SocketAddrSpace,
     solana_tpu_client::tpu_client::DEFAULT_TPU_CONNECTION_POOL_SIZE,
     std::{
+        collections::VecDeque,
         net::{SocketAddr, UdpSocket},
         process::exit,
-        sync::Arc,
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc,
+        },
         thread,
         time::{Duration, Instant},
     },
@@ -84,6 +128,11 @@ use {
 
 const PROGRESS_TIMEOUT_S: u64 = 120;
 const SAMPLE_PERIOD_MS: u64 = 10_000;
+const PAYER_BALANCE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+// how long expired blockhashes are kept for blockhash-age-distribution, in blocks
+const EXPIRED_BLOCKHASH_RETENTION: u64 = 10;
+// how long a slow stream waits before opening another stream after failing to open one
+const SLOW_STREAM_RETRY_DELAY: Duration = Duration::from_millis(100);
 fn compute_rate_per_second(count: usize) -> usize {
     (count * 1000) / (SAMPLE_PERIOD_MS as usize)
 }
@@ -97,22 +146,33 @@ fn compute_rate_per_second(count: usize) -> usize {
 /// 2. With blockhash (but still deliberately invalid):
 /// 2.1 Transfer from 1 payer to multiple destinations (many instructions per transaction)
 /// 2.2 Create an account
+/// 2.3 Transfer from 1 payer to multiple destinations loaded from an address lookup table
 ///
 #[derive(Clone)]
 struct TransactionGenerator {
     blockhash: Hash,
     last_generated: Instant,
     transaction_params: TransactionParams,
+    // created on-chain lazily on the first generation of V0WithLookupTables transaction
+    lookup_table: Option<AddressLookupTableAccount>,
+    // replaced for each generator thread, see create_generator_thread()
+    rng: StdRng,
+    // the blockhashes observed so far along with their last valid block heights, from the oldest
+    // to the latest. tracked only with blockhash_age_distribution.
+    recent_blockhashes: VecDeque<(Hash, u64)>,
 }
 
 impl TransactionGenerator {
-    fn new(transaction_params: TransactionParams) -> Self {
+    fn new(transaction_params: TransactionParams, rng: StdRng) -> Self {
         TransactionGenerator {
             blockhash: Hash::default(),
             last_generated: Instant::now()
                 .checked_sub(Duration::from_secs(100))
                 .unwrap(), //to force generation when generate is called
             transaction_params,
+            lookup_table: None,
+            rng,
+            recent_blockhashes: VecDeque::new(),
         }
     }
 
@@ -129,14 +189,14 @@ impl TransactionGenerator {
         payer: Option<&Keypair>,
         destinations: Option<Vec<&Keypair>>,
         client: Option<&Arc<T>>,
-    ) -> Transaction {
+    ) -> VersionedTransaction {
         if self.transaction_params.valid_blockhash {
             let client = client.as_ref().unwrap();
             let destinations = destinations.unwrap();
             let payer = payer.as_ref().unwrap();
             self.generate_with_blockhash(payer, destinations, client)
         } else {
-            self.generate_without_blockhash(destinations)
+            self.generate_without_blockhash(destinations).into()
         }
     }
 
@@ -145,27 +205,117 @@ impl TransactionGenerator {
         payer: &Keypair,
         destinations: Vec<&Keypair>,
         client: &Arc<T>,
-    ) -> Transaction {
+    ) -> VersionedTransaction {
         // generate a new blockhash every 1sec
         if self.transaction_params.valid_blockhash
             && self.last_generated.elapsed().as_millis() > 1000
         {
-            self.blockhash = client.get_latest_blockhash().unwrap();
+            if self.transaction_params.blockhash_age_distribution.is_some() {
+                // processed, so that the ages are close to the ones seen by the target
+                let (blockhash, last_valid_block_height) = client
+                    .get_latest_blockhash_with_commitment(CommitmentConfig::processed())
+                    .unwrap();
+                self.record_blockhash(blockhash, last_valid_block_height);
+            } else {
+                self.blockhash = client.get_latest_blockhash().unwrap();
+            }
             self.last_generated = Instant::now();
         }
+        if let Some(distribution) = self.transaction_params.blockhash_age_distribution {
+            self.blockhash = self.pick_blockhash(distribution);
+        }
 
         // transaction_type is known to be present because it is required by blockhash option in cli
         let transaction_type = self.transaction_params.transaction_type.as_ref().unwrap();
         match transaction_type {
-            TransactionType::Transfer => {
-                self.create_multi_transfer_transaction(payer, &destinations)
+            TransactionType::Transfer => self
+                .create_multi_transfer_transaction(payer, &destinations)
+                .into(),
+            TransactionType::AccountCreation => self
+                .create_account_transaction(payer, destinations[0])
+                .into(),
+            TransactionType::V0WithLookupTables => {
+                if self.lookup_table.is_none() {
+                    // num_instructions is known to be present because it is required by cli
+                    let num_addresses = self.transaction_params.num_instructions.unwrap();
+                    self.lookup_table = Some(create_lookup_table(payer, num_addresses, client));
+                }
+                self.create_lookup_table_transfer_transaction(payer)
             }
-            TransactionType::AccountCreation => {
-                self.create_account_transaction(payer, destinations[0])
+        }
+    }
+
+    fn record_blockhash(&mut self, blockhash: Hash, last_valid_block_height: u64) {
+        if self.recent_blockhashes.back().map(|(latest, _)| latest) != Some(&blockhash) {
+            self.recent_blockhashes
+                .push_back((blockhash, last_valid_block_height));
+        }
+        let block_height = last_valid_block_height.saturating_sub(MAX_PROCESSING_AGE as u64);
+        while let Some((_, oldest_last_valid_block_height)) = self.recent_blockhashes.front() {
+            if oldest_last_valid_block_height + EXPIRED_BLOCKHASH_RETENTION >= block_height {
+                break;
             }
+            self.recent_blockhashes.pop_front();
+        }
+    }
+
+    /// Picks the blockhash of an age drawn from the distribution. Until the attack has run long
+    /// enough for any blockhash to expire, the oldest valid one is picked instead of an expired
+    /// one.
+    fn pick_blockhash(&mut self, distribution: BlockhashAgeDistribution) -> Hash {
+        let (latest, latest_last_valid_block_height) = *self.recent_blockhashes.back().unwrap();
+        let block_height = latest_last_valid_block_height.saturating_sub(MAX_PROCESSING_AGE as u64);
+        let oldest_valid = || {
+            self.recent_blockhashes
+                .iter()
+                .find(|(_, last_valid_block_height)| *last_valid_block_height >= block_height)
+                .map(|(blockhash, _)| *blockhash)
+                .unwrap_or(latest)
+        };
+
+        let BlockhashAgeDistribution {
+            just_valid,
+            about_to_expire,
+            just_expired,
+        } = distribution;
+        let total_weight =
+            u64::from(just_valid) + u64::from(about_to_expire) + u64::from(just_expired);
+        let weight = self.rng.gen_range(0..total_weight);
+        if weight < u64::from(just_valid) {
+            latest
+        } else if weight < u64::from(just_valid) + u64::from(about_to_expire) {
+            oldest_valid()
+        } else {
+            self.recent_blockhashes
+                .iter()
+                .rev()
+                .find(|(_, last_valid_block_height)| *last_valid_block_height < block_height)
+                .map(|(blockhash, _)| *blockhash)
+                .unwrap_or_else(oldest_valid)
         }
     }
 
+    /// Create a version 0 transaction which transfers some lamports from payer to all the
+    /// destinations stored in the lookup table, so that they have to be resolved by the validator
+    fn create_lookup_table_transfer_transaction(&self, payer: &Keypair) -> VersionedTransaction {
+        let lookup_table = self.lookup_table.as_ref().unwrap();
+        let to_transfer: u64 = 500_000_000; // specify amount which will cause error
+        let to: Vec<(Pubkey, u64)> = lookup_table
+            .addresses
+            .iter()
+            .map(|to| (*to, to_transfer))
+            .collect();
+        let instructions = system_instruction::transfer_many(&payer.pubkey(), to.as_slice());
+        let message = v0::Message::try_compile(
+            &payer.pubkey(),
+            &instructions,
+            std::slice::from_ref(lookup_table),
+            self.blockhash,
+        )
+        .unwrap();
+        VersionedTransaction::try_new(VersionedMessage::V0(message), &[payer]).unwrap()
+    }
+
     /// Create a transaction which transfers some lamports from payer to several destinations
     fn create_multi_transfer_transaction(&self, payer: &Keypair, to: &[&Keypair]) -> Transaction {
         let to_transfer: u64 = 500_000_000; // specify amount which will cause error
@@ -232,12 +382,125 @@ impl TransactionGenerator {
                 instructions,
             );
             let num_signatures = self.transaction_params.num_signatures.unwrap();
-            tx.signatures = vec![Signature::new_unique(); num_signatures];
+            let mut signature = [0u8; 64];
+            self.rng.fill(&mut signature[..]);
+            tx.signatures = vec![Signature::from(signature); num_signatures];
             tx
         }
     }
 }
 
+// Sends the transaction built with a fresh blockhash by `build` until `is_done` returns true,
+// retrying a few times in case the transaction is dropped. `description` tells which transaction
+// has failed to be confirmed, if it's still not done after all.
+fn send_until<T: 'static + BenchTpsClient + Send + Sync>(
+    client: &Arc<T>,
+    description: &str,
+    build: impl Fn(Hash) -> Transaction,
+    is_done: impl Fn() -> bool,
+) {
+    const MAX_ATTEMPTS: usize = 5;
+    const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(15);
+    for _ in 0..MAX_ATTEMPTS {
+        let blockhash = client.get_latest_blockhash().unwrap();
+        if let Err(err) = client.send_transaction(build(blockhash)) {
+            warn!("Failed to send transaction: {err:?}");
+        }
+        let start = Instant::now();
+        while start.elapsed() < CONFIRMATION_TIMEOUT {
+            if is_done() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(400));
+        }
+    }
+    eprintln!("Failed to confirm {description} transaction");
+    exit(1);
+}
+
+/// Create a lookup table owned by the payer and extend it with `num_addresses` new addresses
+fn create_lookup_table<T: 'static + BenchTpsClient + Send + Sync>(
+    payer: &Keypair,
+    num_addresses: usize,
+    client: &Arc<T>,
+) -> AddressLookupTableAccount {
+    // the number of addresses which fits into a single extend instruction
+    const MAX_ADDRESSES_PER_EXTEND: usize = 20;
+
+    let get_lookup_table_addresses = |key: &Pubkey| -> Option<Vec<Pubkey>> {
+        let account = client
+            .get_account_with_commitment(key, CommitmentConfig::confirmed())
+            .ok()?;
+        AddressLookupTable::deserialize(&account.data)
+            .ok()
+            .map(|lookup_table| lookup_table.addresses.to_vec())
+    };
+
+    let recent_slot = client
+        .get_slot_with_commitment(CommitmentConfig::finalized())
+        .unwrap();
+    let (create_instruction, key) = address_lookup_table::instruction::create_lookup_table(
+        payer.pubkey(),
+        payer.pubkey(),
+        recent_slot,
+    );
+    send_until(
+        client,
+        &format!("lookup table {key} creation"),
+        |blockhash| {
+            Transaction::new_signed_with_payer(
+                &[create_instruction.clone()],
+                Some(&payer.pubkey()),
+                &[payer],
+                blockhash,
+            )
+        },
+        || get_lookup_table_addresses(&key).is_some(),
+    );
+
+    let addresses: Vec<Pubkey> = (0..num_addresses).map(|_| Pubkey::new_unique()).collect();
+    for (chunk_index, chunk) in addresses.chunks(MAX_ADDRESSES_PER_EXTEND).enumerate() {
+        let extended_len = chunk_index * MAX_ADDRESSES_PER_EXTEND + chunk.len();
+        let extend_instruction = address_lookup_table::instruction::extend_lookup_table(
+            key,
+            payer.pubkey(),
+            Some(payer.pubkey()),
+            chunk.to_vec(),
+        );
+        send_until(
+            client,
+            &format!("lookup table {key} extension up to {extended_len} addresses"),
+            |blockhash| {
+                Transaction::new_signed_with_payer(
+                    &[extend_instruction.clone()],
+                    Some(&payer.pubkey()),
+                    &[payer],
+                    blockhash,
+                )
+            },
+            || {
+                get_lookup_table_addresses(&key).map_or(0, |addresses| addresses.len())
+                    >= extended_len
+            },
+        );
+    }
+
+    // newly added addresses can be looked up only since the next slot
+    let extended_slot = client
+        .get_slot_with_commitment(CommitmentConfig::confirmed())
+        .unwrap();
+    while client
+        .get_slot_with_commitment(CommitmentConfig::confirmed())
+        .unwrap()
+        <= extended_slot
+    {
+        thread::sleep(Duration::from_millis(400));
+    }
+    info!("Created lookup table {key} with {num_addresses} addresses");
+
+    AddressLookupTableAccount { key, addresses }
+}
+
 // Multithreading-related functions
 //
 // The most computationally expensive work is signing new transactions.
@@ -248,15 +511,77 @@ struct TransactionBatchMsg {
     gen_time: u64,
 }
 
+#[derive(Debug, Default, PartialEq, Serialize)]
+struct LatencyPercentiles {
+    p50: u64,
+    p90: u64,
+    p99: u64,
+    p999: u64,
+}
+
+impl LatencyPercentiles {
+    fn new(histogram: &Histogram) -> Self {
+        // percentile() fails only if the histogram is empty
+        let percentile = |percentile| histogram.percentile(percentile).unwrap_or(0);
+        Self {
+            p50: percentile(50.0),
+            p90: percentile(90.0),
+            p99: percentile(99.0),
+            p999: percentile(99.9),
+        }
+    }
+}
+
+/// Durations of generating (i.e. signing and serializing) and sending each batch of transactions,
+/// in microseconds
+struct BatchLatencies {
+    generate_us: Histogram,
+    send_us: Histogram,
+}
+
+impl Default for BatchLatencies {
+    fn default() -> Self {
+        Self {
+            generate_us: Histogram::new(),
+            send_us: Histogram::new(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BatchLatencyReport {
+    batches: u64,
+    generate_us: LatencyPercentiles,
+    send_us: LatencyPercentiles,
+}
+
+impl BatchLatencies {
+    fn record(&mut self, generate_ns: u64, send_ns: u64) {
+        // increment() fails only for values beyond the histogram's max, which are just skipped
+        let _ = self.generate_us.increment(generate_ns / 1_000);
+        let _ = self.send_us.increment(send_ns / 1_000);
+    }
+
+    fn report(&self) -> BatchLatencyReport {
+        BatchLatencyReport {
+            batches: self.send_us.entries(),
+            generate_us: LatencyPercentiles::new(&self.generate_us),
+            send_us: LatencyPercentiles::new(&self.send_us),
+        }
+    }
+}
+
 /// Creates thread which receives batches of transactions from tx_receiver
 /// and sends them to the target.
 /// If `iterations` is 0, it works indefenetely.
 /// Otherwise, it sends at least `iterations` number of transactions
+/// In either case, it stops once `stop` is set.
 fn create_sender_thread(
     tx_receiver: Receiver<TransactionBatchMsg>,
     iterations: usize,
     target: &SocketAddr,
     tpu_use_quic: bool,
+    stop: Arc<AtomicBool>,
 ) -> thread::JoinHandle<()> {
     // ConnectionCache is used instead of client because it gives ~6% higher pps
     let connection_cache = match tpu_use_quic {
@@ -273,12 +598,14 @@ fn create_sender_thread(
     let stats_timer_receiver = tick(Duration::from_millis(SAMPLE_PERIOD_MS));
     let progress_timer_receiver = tick(Duration::from_secs(PROGRESS_TIMEOUT_S));
 
-    let mut time_send_ns = 0;
-    let mut time_generate_ns = 0;
+    // reset on every stats sample, while `total_latencies` covers the whole run
+    let mut latencies = BatchLatencies::default();
+    let mut total_latencies = BatchLatencies::default();
 
     // Sender signals to stop Generators by dropping receiver.
-    // It happens in 2 cases:
+    // It happens in 3 cases:
     // * Sender has sent at least `iterations` number of transactions
+    // * The target has degraded, as signaled by `stop` (checked along with the stats)
     // * Sender observes that there is no progress. Since there is no way to use recv_timeout with select,
     // a timer is used.
     thread::Builder::new().name("Sender".to_string()).spawn(move || {
@@ -298,8 +625,8 @@ fn create_sender_thread(
                             let res = connection.send_data_batch_async(tx_batch.batch);
 
                             measure_send_txs.stop();
-                            time_send_ns += measure_send_txs.as_ns();
-                            time_generate_ns += tx_batch.gen_time;
+                            latencies.record(tx_batch.gen_time, measure_send_txs.as_ns());
+                            total_latencies.record(tx_batch.gen_time, measure_send_txs.as_ns());
 
                             if res.is_err() {
                                 stats_error_count += len;
@@ -318,17 +645,23 @@ fn create_sender_thread(
                 },
                 recv(stats_timer_receiver) -> _ => {
                     info!("tx_receiver queue len: {}", tx_receiver.len());
-                    info!("Count: {}, error count: {}, send mean time: {}, generate mean time: {}, rps: {}",
+                    let report = latencies.report();
+                    info!("Count: {}, error count: {}, batch send time us: {:?}, batch generate time us: {:?}, rps: {}",
                         stats_count,
                         stats_error_count,
-                        time_send_ns.checked_div(stats_count as u64).unwrap_or(0),
-                        time_generate_ns.checked_div(stats_count as u64).unwrap_or(0),
+                        report.send_us,
+                        report.generate_us,
                         compute_rate_per_second(stats_count),
                     );
                     stats_count = 0;
                     stats_error_count = 0;
-                    time_send_ns = 0;
-                    time_generate_ns = 0;
+                    latencies = BatchLatencies::default();
+                    if stop.load(Ordering::Relaxed) {
+                        info!("Target has degraded, stop execution");
+                        // dropping receiver to signal generator threads to stop
+                        drop(tx_receiver);
+                        break;
+                    }
                 },
                 recv(progress_timer_receiver) -> _ => {
                     if prev_total_count - total_count == 0 {
@@ -341,6 +674,10 @@ fn create_sender_thread(
                 }
             }
         }
+        info!(
+            "Batch latency report: {}",
+            serde_json::to_string(&total_latencies.report()).unwrap()
+        );
     }).unwrap()
 }
 
@@ -350,10 +687,14 @@ fn create_generator_thread<T: 'static + BenchTpsClient + Send + Sync>(
     transaction_generator: &TransactionGenerator,
     client: Option<Arc<T>>,
     payer: Option<Keypair>,
-) -> thread::JoinHandle<()> {
+    mut rng: StdRng,
+    funding_params: FundingParams,
+) -> thread::JoinHandle<Option<Keypair>> {
     let tx_sender = tx_sender.clone();
 
     let mut transaction_generator = transaction_generator.clone();
+    // otherwise, all threads would generate the same transactions
+    transaction_generator.rng = StdRng::seed_from_u64(rng.gen());
     let transaction_params: &TransactionParams = &transaction_generator.transaction_params;
 
     // Generate n=1000 unique keypairs
@@ -371,7 +712,7 @@ fn create_generator_thread<T: 'static + BenchTpsClient + Send + Sync>(
     let generate_keypairs =
         transaction_params.valid_signatures || transaction_params.valid_blockhash;
     if generate_keypairs {
-        keypairs_flat = (0..num_keypairs).map(|_| Keypair::new()).collect();
+        keypairs_flat = (0..num_keypairs).map(|_| new_keypair(&mut rng)).collect();
     }
 
     thread::Builder::new()
@@ -379,8 +720,25 @@ fn create_generator_thread<T: 'static + BenchTpsClient + Send + Sync>(
         .spawn(move || {
             let indexes: Vec<usize> = (0..keypairs_flat.len()).collect();
             let mut it = indexes.iter().permutations(permutation_size);
+            let mut last_balance_check = Instant::now();
 
             loop {
+                if let (Some(threshold), Some(client), Some(payer)) = (
+                    funding_params.refund_payers_below,
+                    client.as_ref(),
+                    payer.as_ref(),
+                ) {
+                    if last_balance_check.elapsed() >= PAYER_BALANCE_CHECK_INTERVAL {
+                        refund_payer_if_needed(
+                            client,
+                            payer,
+                            threshold,
+                            funding_params.lamports_per_payer,
+                        );
+                        last_balance_check = Instant::now();
+                    }
+                }
+
                 let mut data = Vec::<Vec<u8>>::with_capacity(send_batch_size);
                 let mut measure_generate_txs = Measure::start("measure_generate_txs");
                 for _ in 0..send_batch_size {
@@ -388,7 +746,9 @@ fn create_generator_thread<T: 'static + BenchTpsClient + Send + Sync>(
                         let mut permutation = it.next();
                         if permutation.is_none() {
                             // if ran out of permutations, regenerate keys
-                            keypairs_flat.iter_mut().for_each(|v| *v = Keypair::new());
+                            keypairs_flat
+                                .iter_mut()
+                                .for_each(|v| *v = new_keypair(&mut rng));
                             info!("Regenerate keypairs");
                             permutation = it.next();
                         }
@@ -416,10 +776,260 @@ fn create_generator_thread<T: 'static + BenchTpsClient + Send + Sync>(
                     break;
                 }
             }
+            // returned to be swept
+            payer
         })
         .unwrap()
 }
 
+/// Reads the transactions recorded in the full slots of the ledger within
+/// the configured slot range, in ledger order unless shuffling is requested.
+fn load_ledger_transactions(params: &LedgerReplayParams, rng: &mut StdRng) -> Vec<Vec<u8>> {
+    // existence of ledger_path is checked at cli level
+    let ledger_path = params.ledger_path.as_ref().unwrap();
+    // Secondary access allows reading the ledger of a running validator
+    let blockstore = Blockstore::open_with_options(
+        ledger_path,
+        BlockstoreOptions {
+            access_type: AccessType::Secondary,
+            ..BlockstoreOptions::default()
+        },
+    )
+    .unwrap_or_else(|err| {
+        eprintln!("Failed to open ledger at {ledger_path:?}: {err:?}");
+        exit(1);
+    });
+
+    let end_slot = params.replay_end_slot.unwrap_or(Slot::MAX);
+    let mut transactions = Vec::new();
+    for (slot, meta) in blockstore
+        .slot_meta_iterator(params.replay_start_slot)
+        .expect("Failed to iterate over ledger slots")
+        .take_while(|(slot, _)| *slot <= end_slot)
+    {
+        if !meta.is_full() {
+            continue;
+        }
+        match blockstore.get_slot_entries(slot, 0) {
+            Ok(entries) => transactions.extend(
+                entries
+                    .iter()
+                    .flat_map(|entry| entry.transactions.iter())
+                    .map(|tx| bincode::serialize(tx).unwrap()),
+            ),
+            Err(err) => warn!("Failed to read entries of slot {slot}: {err:?}"),
+        }
+    }
+    if params.replay_shuffle {
+        transactions.shuffle(rng);
+    }
+    transactions
+}
+
+/// Creates thread which sends batches of the given transactions to tx_sender,
+/// limiting the throughput to `replay_rate` transactions per second if specified.
+fn create_replay_thread(
+    tx_sender: Sender<TransactionBatchMsg>,
+    transactions: Vec<Vec<u8>>,
+    send_batch_size: usize,
+    replay_rate: Option<u64>,
+) -> thread::JoinHandle<()> {
+    // keep batches small enough to not exceed rate within a single batch
+    let batch_size = replay_rate.map_or(send_batch_size, |rate| {
+        send_batch_size.min(rate as usize).max(1)
+    });
+    thread::Builder::new()
+        .name("Replayer".to_string())
+        .spawn(move || {
+            let start = Instant::now();
+            let mut sent_count: u64 = 0;
+            for chunk in transactions.chunks(batch_size) {
+                if let Some(rate) = replay_rate {
+                    let deadline = Duration::from_micros(sent_count * 1_000_000 / rate);
+                    if let Some(wait) = deadline.checked_sub(start.elapsed()) {
+                        thread::sleep(wait);
+                    }
+                }
+                let result = tx_sender.send(TransactionBatchMsg {
+                    batch: chunk.to_vec(),
+                    gen_time: 0,
+                });
+                if result.is_err() {
+                    // means that receiver has been dropped by sender thread
+                    info!("Exit replay thread");
+                    break;
+                }
+                sent_count += chunk.len() as u64;
+            }
+        })
+        .unwrap()
+}
+
+fn run_dos_ledger_replay(
+    target: SocketAddr,
+    iterations: usize,
+    ledger_replay_params: LedgerReplayParams,
+    tpu_use_quic: bool,
+    send_batch_size: usize,
+    rng: &mut StdRng,
+    stop: Arc<AtomicBool>,
+) {
+    let transactions = load_ledger_transactions(&ledger_replay_params, rng);
+    info!("Loaded {} transactions from ledger", transactions.len());
+    if transactions.is_empty() {
+        return;
+    }
+    // Each transaction is replayed once, unless fewer iterations are requested
+    let iterations = if iterations == 0 {
+        transactions.len()
+    } else {
+        iterations.min(transactions.len())
+    };
+
+    let (tx_sender, tx_receiver) = unbounded();
+    let sender_thread = create_sender_thread(tx_receiver, iterations, &target, tpu_use_quic, stop);
+    let replay_thread = create_replay_thread(
+        tx_sender,
+        transactions,
+        send_batch_size,
+        ledger_replay_params.replay_rate,
+    );
+    if let Err(err) = sender_thread.join() {
+        println!("join() failed with: {err:?}");
+    }
+    if let Err(err) = replay_thread.join() {
+        println!("join() failed with: {err:?}");
+    }
+}
+
+#[derive(Default)]
+struct SlowStreamStats {
+    opened: AtomicUsize,
+    // written as a whole and finished
+    finished: AtomicUsize,
+    // failed to open, e.g. once the server refuses the connection
+    open_errors: AtomicUsize,
+    // failed while being written, e.g. once the server times out the stream
+    write_errors: AtomicUsize,
+}
+
+impl SlowStreamStats {
+    fn report(&self) {
+        info!(
+            "Slow streams opened: {}, finished: {}, open errors: {}, write errors: {}",
+            self.opened.load(Ordering::Relaxed),
+            self.finished.load(Ordering::Relaxed),
+            self.open_errors.load(Ordering::Relaxed),
+            self.write_errors.load(Ordering::Relaxed),
+        );
+    }
+}
+
+// Writes `data` into a stream at `bytes_per_second`, opening another stream once one is finished
+// or has failed, until `remaining_streams` runs out.
+async fn write_slow_streams(
+    client: Arc<QuicClient>,
+    data: Arc<Vec<u8>>,
+    bytes_per_second: u64,
+    remaining_streams: Arc<AtomicUsize>,
+    stats: Arc<SlowStreamStats>,
+) {
+    let client_stats = ClientStats::default();
+    let connection_stats = Arc::new(ConnectionCacheStats::default());
+    // at high rates, several bytes are written at once so as not to wake up for every byte
+    let chunk_size = (bytes_per_second / 10).clamp(1, data.len().max(1) as u64) as usize;
+    let write_interval = Duration::from_secs_f64(chunk_size as f64 / bytes_per_second as f64);
+    while remaining_streams
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+        .is_ok()
+    {
+        let mut stream = match client
+            .open_uni_stream(&client_stats, connection_stats.clone())
+            .await
+        {
+            Ok(stream) => stream,
+            Err(err) => {
+                debug!("Failed to open slow stream: {:?}", err);
+                stats.open_errors.fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(SLOW_STREAM_RETRY_DELAY).await;
+                continue;
+            }
+        };
+        stats.opened.fetch_add(1, Ordering::Relaxed);
+        let result = async {
+            for chunk in data.chunks(chunk_size) {
+                tokio::time::sleep(write_interval).await;
+                stream.write_all(chunk).await?;
+            }
+            stream.finish().await
+        }
+        .await;
+        match result {
+            Ok(()) => stats.finished.fetch_add(1, Ordering::Relaxed),
+            Err(err) => {
+                debug!("Failed to write slow stream: {:?}", err);
+                stats.write_errors.fetch_add(1, Ordering::Relaxed)
+            }
+        };
+    }
+}
+
+/// Keeps `stream_count` QUIC streams open to the target, writing `data` into each of them at
+/// `bytes_per_second`.
+/// If `iterations` is 0, it works indefinitely. Otherwise, it opens `iterations` streams in total.
+/// In either case, it stops once `stop` is set.
+fn run_dos_slow_streams(
+    target: SocketAddr,
+    data: Vec<u8>,
+    stream_count: usize,
+    bytes_per_second: u64,
+    iterations: usize,
+    stop: &AtomicBool,
+) {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .thread_name("solDosSlowStrm")
+        .enable_all()
+        .build()
+        .unwrap();
+    // the server limits the concurrent streams, rather than the client
+    let client = Arc::new(QuicClient::new(
+        Arc::new(QuicLazyInitializedEndpoint::default()),
+        target,
+        stream_count,
+    ));
+    let data = Arc::new(data);
+    let remaining_streams = Arc::new(AtomicUsize::new(match iterations {
+        0 => usize::MAX,
+        iterations => iterations,
+    }));
+    let stats = Arc::new(SlowStreamStats::default());
+
+    runtime.block_on(async {
+        let tasks: Vec<_> = (0..stream_count)
+            .map(|_| {
+                tokio::spawn(write_slow_streams(
+                    client.clone(),
+                    data.clone(),
+                    bytes_per_second,
+                    remaining_streams.clone(),
+                    stats.clone(),
+                ))
+            })
+            .collect();
+        let mut last_log = Instant::now();
+        while !stop.load(Ordering::Relaxed) && !tasks.iter().all(|task| task.is_finished()) {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            if last_log.elapsed().as_millis() > SAMPLE_PERIOD_MS as u128 {
+                stats.report();
+                last_log = Instant::now();
+            }
+        }
+    });
+    stats.report();
+    // abandons the streams being written, if stopped
+    runtime.shutdown_background();
+}
+
 fn get_target(
     nodes: &[ContactInfo],
     mode: Mode,
@@ -484,7 +1094,101 @@ fn get_rpc_client(
     Err("Node with entrypoint_addr was not found")
 }
 
-fn run_dos_rpc_mode_helper<F: Fn() -> bool>(iterations: usize, rpc_client_call: F) {
+/// Returns the reason to stop the attack if the target has degraded according to any of
+/// `stop_conditions`.
+fn check_stop_conditions<E: std::fmt::Display>(
+    stop_conditions: &[StopCondition],
+    health: &Result<(), E>,
+    slot_stalled_for: Duration,
+) -> Option<String> {
+    stop_conditions
+        .iter()
+        .find_map(|stop_condition| match stop_condition {
+            StopCondition::Unhealthy => health
+                .as_ref()
+                .err()
+                .map(|err| format!("target is unhealthy: {err}")),
+            StopCondition::SlotStall(max_stall) => (slot_stalled_for >= *max_stall)
+                .then(|| format!("target's slot hasn't advanced for {slot_stalled_for:?}")),
+        })
+}
+
+/// Creates thread which periodically samples the target's RPC and logs the observations, so that
+/// they are interleaved with the sender's statistics in the log and the effect of the load on the
+/// target is captured along with the load itself.
+/// Sets `stop` and returns once any of `stop_conditions` is met.
+fn create_rpc_sampler_thread(
+    rpc_client: RpcClient,
+    interval: Duration,
+    stop_conditions: Vec<StopCondition>,
+    stop: Arc<AtomicBool>,
+    exit: Arc<AtomicBool>,
+) -> thread::JoinHandle<()> {
+    thread::Builder::new()
+        .name("RpcSampler".to_string())
+        .spawn(move || {
+            let mut prev_slot = None;
+            let mut last_sample = Instant::now();
+            // unreachable rpc counts as stalled too
+            let mut last_slot_advance = Instant::now();
+            while !exit.load(Ordering::Relaxed) {
+                let health_result = rpc_client.get_health();
+                let health = match &health_result {
+                    Ok(()) => "ok".to_string(),
+                    Err(err) => format!("{err}"),
+                };
+                let slot = rpc_client.get_slot().ok();
+                let slots_per_second = slot.zip(prev_slot).map(|(slot, prev_slot): (u64, u64)| {
+                    slot.saturating_sub(prev_slot) as f64 / last_sample.elapsed().as_secs_f64()
+                });
+                if slot > prev_slot {
+                    last_slot_advance = Instant::now();
+                }
+                last_sample = Instant::now();
+                prev_slot = slot.or(prev_slot);
+                let (tps, non_vote_tps) = rpc_client
+                    .get_recent_performance_samples(Some(1))
+                    .ok()
+                    .and_then(|samples| samples.into_iter().next())
+                    .filter(|sample| sample.sample_period_secs > 0)
+                    .map_or((None, None), |sample| {
+                        let period = u64::from(sample.sample_period_secs);
+                        (
+                            Some(sample.num_transactions / period),
+                            sample
+                                .num_non_vote_transactions
+                                .map(|num_non_vote_transactions| num_non_vote_transactions / period),
+                        )
+                    });
+                info!(
+                    "Target sample: health: {}, slot: {:?}, slots/s: {:?}, tps: {:?}, non-vote tps: {:?}",
+                    health, slot, slots_per_second, tps, non_vote_tps,
+                );
+
+                if let Some(reason) = check_stop_conditions(
+                    &stop_conditions,
+                    &health_result,
+                    last_slot_advance.elapsed(),
+                ) {
+                    warn!("Stopping the attack: {}", reason);
+                    stop.store(true, Ordering::Relaxed);
+                    break;
+                }
+
+                // wake up frequently enough to exit promptly once the attack is over
+                while !exit.load(Ordering::Relaxed) && last_sample.elapsed() < interval {
+                    thread::sleep(Duration::from_millis(100).min(interval));
+                }
+            }
+        })
+        .unwrap()
+}
+
+fn run_dos_rpc_mode_helper<F: Fn() -> bool>(
+    iterations: usize,
+    stop: &AtomicBool,
+    rpc_client_call: F,
+) {
     let mut last_log = Instant::now();
     let mut total_count: usize = 0;
     let mut count = 0;
@@ -505,7 +1209,7 @@ fn run_dos_rpc_mode_helper<F: Fn() -> bool>(iterations: usize, rpc_client_call:
             last_log = Instant::now();
             count = 0;
         }
-        if iterations != 0 && total_count >= iterations {
+        if (iterations != 0 && total_count >= iterations) || stop.load(Ordering::Relaxed) {
             break;
         }
     }
@@ -516,15 +1220,16 @@ fn run_dos_rpc_mode(
     iterations: usize,
     data_type: DataType,
     data_input: &Pubkey,
+    stop: &AtomicBool,
 ) {
     match data_type {
         DataType::GetAccountInfo => {
-            run_dos_rpc_mode_helper(iterations, || -> bool {
+            run_dos_rpc_mode_helper(iterations, stop, || -> bool {
                 rpc_client.get_account(data_input).is_ok()
             });
         }
         DataType::GetProgramAccounts => {
-            run_dos_rpc_mode_helper(iterations, || -> bool {
+            run_dos_rpc_mode_helper(iterations, stop, || -> bool {
                 rpc_client.get_program_accounts(data_input).is_ok()
             });
         }
@@ -544,21 +1249,41 @@ fn apply_permutation<'a, T>(permutation: Vec<&usize>, items: &'a [T]) -> Vec<&'a
 }
 
 fn create_payers<T: 'static + BenchTpsClient + Send + Sync>(
-    valid_blockhash: bool,
+    transaction_params: &TransactionParams,
     size: usize,
     client: Option<&Arc<T>>,
+    rng: &mut StdRng,
+    funding_params: &FundingParams,
 ) -> Vec<Option<Keypair>> {
     // Assume that if we use valid blockhash, we also have a payer
-    if valid_blockhash {
+    if transaction_params.valid_blockhash {
         // each payer is used to fund transaction
-        // transactions are built to be invalid so the the amount here is arbitrary
-        let funding_key = Keypair::new();
+        // transactions are built to be invalid so the the amount here is arbitrary,
+        // except that the lookup table must be rent-exempt under the target cluster's rent
+        let lamports_per_payer = match transaction_params.transaction_type {
+            Some(TransactionType::V0WithLookupTables) => {
+                let num_addresses = transaction_params.num_instructions.unwrap();
+                let lookup_table_rent = client
+                    .unwrap()
+                    .get_minimum_balance_for_rent_exemption(
+                        LOOKUP_TABLE_META_SIZE + num_addresses * std::mem::size_of::<Pubkey>(),
+                    )
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error could not get the rent of lookup tables: {e:?}");
+                        exit(1);
+                    });
+                funding_params.lamports_per_payer + lookup_table_rent
+            }
+            _ => funding_params.lamports_per_payer,
+        };
+        // the payers are derived from the funding key
+        let funding_key = new_keypair(rng);
         let funding_key = Arc::new(funding_key);
         let res = generate_and_fund_keypairs(
             client.unwrap().clone(),
             &funding_key,
             size,
-            1_000_000,
+            lamports_per_payer,
             false,
             false,
         )
@@ -572,6 +1297,113 @@ fn create_payers<T: 'static + BenchTpsClient + Send + Sync>(
     }
 }
 
+/// Tops up `payer` to `lamports_per_payer` by airdrop if its balance has dropped below
+/// `threshold`. Failures are only logged, because the payer might still have enough funds.
+fn refund_payer_if_needed<T: 'static + BenchTpsClient + Send + Sync>(
+    client: &Arc<T>,
+    payer: &Keypair,
+    threshold: u64,
+    lamports_per_payer: u64,
+) {
+    let balance = match client.get_balance(&payer.pubkey()) {
+        Ok(balance) => balance,
+        Err(err) => {
+            warn!(
+                "Failed to get the balance of payer {}: {}",
+                payer.pubkey(),
+                err
+            );
+            return;
+        }
+    };
+    if balance >= threshold {
+        return;
+    }
+    let lamports = lamports_per_payer.saturating_sub(balance);
+    let result = client.get_latest_blockhash().and_then(|blockhash| {
+        client.request_airdrop_with_blockhash(&payer.pubkey(), lamports, &blockhash)
+    });
+    match result {
+        Ok(_) => info!(
+            "Refunded payer {} with {} lamports",
+            payer.pubkey(),
+            lamports
+        ),
+        Err(err) => warn!("Failed to refund payer {}: {}", payer.pubkey(), err),
+    }
+}
+
+/// Transfers the leftover funds of `payers` to `destination`, leaving just enough for the fees.
+fn sweep_payers<T: 'static + BenchTpsClient + Send + Sync>(
+    client: &Arc<T>,
+    payers: &[Keypair],
+    destination: &Pubkey,
+) {
+    for payer in payers {
+        let result = client.get_latest_blockhash().and_then(|blockhash| {
+            let balance = client.get_balance(&payer.pubkey())?;
+            let message = Message::new_with_blockhash(
+                &[system_instruction::transfer(
+                    &payer.pubkey(),
+                    destination,
+                    balance,
+                )],
+                Some(&payer.pubkey()),
+                &blockhash,
+            );
+            let fee = client.get_fee_for_message(&message)?;
+            if balance <= fee {
+                return Ok(0);
+            }
+            let lamports = balance - fee;
+            let transaction = Transaction::new_signed_with_payer(
+                &[system_instruction::transfer(
+                    &payer.pubkey(),
+                    destination,
+                    lamports,
+                )],
+                Some(&payer.pubkey()),
+                &[payer],
+                blockhash,
+            );
+            client.send_transaction(transaction)?;
+            Ok(lamports)
+        });
+        match result {
+            Ok(lamports) => info!(
+                "Swept {} lamports from payer {} to {}",
+                lamports,
+                payer.pubkey(),
+                destination
+            ),
+            Err(err) => warn!("Failed to sweep payer {}: {}", payer.pubkey(), err),
+        }
+    }
+}
+
+/// Creates the random number generator for the given purpose of the given thread, which is
+/// deterministic if `seed` is specified. Every combination of `seed`, `purpose` and `thread_index`
+/// is hashed into an independent stream, so that no two generators share their output.
+fn create_rng(seed: Option<u64>, purpose: &str, thread_index: u64) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::from_seed(
+            hashv(&[
+                &seed.to_le_bytes(),
+                purpose.as_bytes(),
+                &thread_index.to_le_bytes(),
+            ])
+            .to_bytes(),
+        ),
+        None => StdRng::from_entropy(),
+    }
+}
+
+fn new_keypair(rng: &mut StdRng) -> Keypair {
+    let mut seed = [0u8; 32];
+    rng.fill(&mut seed);
+    keypair_from_seed(&seed).unwrap()
+}
+
 fn get_permutation_size(num_signatures: Option<&usize>, num_instructions: Option<&usize>) -> usize {
     if let Some(num_signatures) = num_signatures {
         *num_signatures
@@ -590,39 +1422,54 @@ fn run_dos_transactions<T: 'static + BenchTpsClient + Send + Sync>(
     tpu_use_quic: bool,
     num_gen_threads: usize,
     send_batch_size: usize,
+    seed: Option<u64>,
+    stop: Arc<AtomicBool>,
+    funding_params: FundingParams,
 ) {
+    let mut rng = create_rng(seed, "transactions", 0);
     // Number of payers is the number of generating threads
     // Later, we will create a new payer for each thread since Keypair is not clonable
     let payers: Vec<Option<Keypair>> = create_payers(
-        transaction_params.valid_blockhash,
+        &transaction_params,
         num_gen_threads,
         client.as_ref(),
+        &mut rng,
+        &funding_params,
     );
 
-    let transaction_generator = TransactionGenerator::new(transaction_params);
+    let transaction_generator =
+        TransactionGenerator::new(transaction_params, StdRng::seed_from_u64(rng.gen()));
     let (tx_sender, tx_receiver) = unbounded();
 
-    let sender_thread = create_sender_thread(tx_receiver, iterations, &target, tpu_use_quic);
+    let sender_thread = create_sender_thread(tx_receiver, iterations, &target, tpu_use_quic, stop);
     let tx_generator_threads: Vec<_> = payers
         .into_iter()
-        .map(|payer| {
+        .enumerate()
+        .map(|(thread_index, payer)| {
             create_generator_thread(
                 &tx_sender,
                 send_batch_size,
                 &transaction_generator,
                 client.clone(),
                 payer,
+                create_rng(seed, "generator", thread_index as u64),
+                funding_params.clone(),
             )
         })
         .collect();
     if let Err(err) = sender_thread.join() {
         println!("join() failed with: {err:?}");
     }
+    let mut payers = vec![];
     for t_generator in tx_generator_threads {
-        if let Err(err) = t_generator.join() {
-            println!("join() failed with: {err:?}");
+        match t_generator.join() {
+            Ok(payer) => payers.extend(payer),
+            Err(err) => println!("join() failed with: {err:?}"),
         }
     }
+    if let (Some(destination), Some(client)) = (funding_params.sweep_payers_to, client.as_ref()) {
+        sweep_payers(client, &payers, &destination);
+    }
 }
 
 fn run_dos<T: 'static + BenchTpsClient + Send + Sync>(
@@ -637,6 +1484,20 @@ fn run_dos<T: 'static + BenchTpsClient + Send + Sync>(
         params.entrypoint_addr,
         params.tpu_use_quic,
     );
+    let mut rng = create_rng(params.seed, "main", 0);
+    let exit = Arc::new(AtomicBool::new(false));
+    let stop = Arc::new(AtomicBool::new(false));
+    let rpc_sampler_thread = params.sample_target_rpc_interval_ms.map(|interval_ms| {
+        let rpc_client =
+            get_rpc_client(nodes, params.entrypoint_addr).expect("Failed to get rpc client");
+        create_rpc_sampler_thread(
+            rpc_client,
+            Duration::from_millis(interval_ms),
+            params.stop_when.clone(),
+            stop.clone(),
+            exit.clone(),
+        )
+    });
     if params.mode == Mode::Rpc {
         // creating rpc_client because get_account, get_program_accounts are not implemented for BenchTpsClient
         let rpc_client =
@@ -647,6 +1508,7 @@ fn run_dos<T: 'static + BenchTpsClient + Send + Sync>(
             iterations,
             params.data_type,
             &params.data_input.unwrap(),
+            &stop,
         );
     } else if params.data_type == DataType::Transaction
         && params.transaction_params.unique_transactions
@@ -661,6 +1523,21 @@ fn run_dos<T: 'static + BenchTpsClient + Send + Sync>(
             params.tpu_use_quic,
             params.num_gen_threads,
             params.send_batch_size,
+            params.seed,
+            stop.clone(),
+            params.funding_params,
+        );
+    } else if params.data_type == DataType::LedgerTransaction {
+        let (_, target_addr) = target.expect("should have target");
+        info!("Targeting {}", target_addr);
+        run_dos_ledger_replay(
+            target_addr,
+            iterations,
+            params.ledger_replay_params,
+            params.tpu_use_quic,
+            params.send_batch_size,
+            &mut rng,
+            stop.clone(),
         );
     } else {
         let (target_id, target_addr) = target.expect("should have target");
@@ -668,7 +1545,7 @@ fn run_dos<T: 'static + BenchTpsClient + Send + Sync>(
         let mut data = match params.data_type {
             DataType::RepairHighest => {
                 let slot = 100;
-                let keypair = Keypair::new();
+                let keypair = new_keypair(&mut rng);
                 let header = RepairRequestHeader::new(keypair.pubkey(), target_id, timestamp(), 0);
                 let req = RepairProtocol::WindowIndex {
                     header,
@@ -679,7 +1556,7 @@ fn run_dos<T: 'static + BenchTpsClient + Send + Sync>(
             }
             DataType::RepairShred => {
                 let slot = 100;
-                let keypair = Keypair::new();
+                let keypair = new_keypair(&mut rng);
                 let header = RepairRequestHeader::new(keypair.pubkey(), target_id, timestamp(), 0);
                 let req = RepairProtocol::HighestWindowIndex {
                     header,
@@ -690,7 +1567,7 @@ fn run_dos<T: 'static + BenchTpsClient + Send + Sync>(
             }
             DataType::RepairOrphan => {
                 let slot = 100;
-                let keypair = Keypair::new();
+                let keypair = new_keypair(&mut rng);
                 let header = RepairRequestHeader::new(keypair.pubkey(), target_id, timestamp(), 0);
                 let req = RepairProtocol::Orphan { header, slot };
                 ServeRepair::repair_proto_to_bytes(&req, &keypair).unwrap()
@@ -702,15 +1579,15 @@ fn run_dos<T: 'static + BenchTpsClient + Send + Sync>(
                 let tp = params.transaction_params;
                 info!("{:?}", tp);
 
-                let valid_blockhash = tp.valid_blockhash;
                 let payers: Vec<Option<Keypair>> =
-                    create_payers(valid_blockhash, 1, client.as_ref());
+                    create_payers(&tp, 1, client.as_ref(), &mut rng, &params.funding_params);
                 let payer = payers[0].as_ref();
 
                 let permutation_size =
                     get_permutation_size(tp.num_signatures.as_ref(), tp.num_instructions.as_ref());
-                let keypairs: Vec<Keypair> =
-                    (0..permutation_size).map(|_| Keypair::new()).collect();
+                let keypairs: Vec<Keypair> = (0..permutation_size)
+                    .map(|_| new_keypair(&mut rng))
+                    .collect();
                 let keypairs_chunk: Option<Vec<&Keypair>> =
                     if tp.valid_signatures || tp.valid_blockhash {
                         Some(keypairs.iter().collect())
@@ -718,7 +1595,8 @@ fn run_dos<T: 'static + BenchTpsClient + Send + Sync>(
                         None
                     };
 
-                let mut transaction_generator = TransactionGenerator::new(tp);
+                let mut transaction_generator =
+                    TransactionGenerator::new(tp, StdRng::seed_from_u64(rng.gen()));
                 let tx = transaction_generator.generate(payer, keypairs_chunk, client.as_ref());
                 info!("{:?}", tx);
                 bincode::serialize(&tx).unwrap()
@@ -726,37 +1604,57 @@ fn run_dos<T: 'static + BenchTpsClient + Send + Sync>(
             _ => panic!("Unsupported data_type detected"),
         };
 
-        let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
-        let mut last_log = Instant::now();
-        let mut total_count: usize = 0;
-        let mut count: usize = 0;
-        let mut error_count = 0;
-        loop {
+        if let Some(stream_count) = params.slow_stream_params.slow_stream_count {
             if params.data_type == DataType::Random {
-                thread_rng().fill(&mut data[..]);
-            }
-            let res = socket.send_to(&data, target_addr);
-            if res.is_err() {
-                error_count += 1;
+                rng.fill(&mut data[..]);
             }
+            run_dos_slow_streams(
+                target_addr,
+                data,
+                stream_count,
+                params.slow_stream_params.slow_stream_bytes_per_second,
+                iterations,
+                &stop,
+            );
+        } else {
+            let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+            let mut last_log = Instant::now();
+            let mut total_count: usize = 0;
+            let mut count: usize = 0;
+            let mut error_count = 0;
+            loop {
+                if params.data_type == DataType::Random {
+                    rng.fill(&mut data[..]);
+                }
+                let res = socket.send_to(&data, target_addr);
+                if res.is_err() {
+                    error_count += 1;
+                }
 
-            count += 1;
-            total_count += 1;
-            if last_log.elapsed().as_millis() > SAMPLE_PERIOD_MS as u128 {
-                info!(
-                    "count: {}, errors: {}, rps: {}",
-                    count,
-                    error_count,
-                    compute_rate_per_second(count)
-                );
-                last_log = Instant::now();
-                count = 0;
-            }
-            if iterations != 0 && total_count >= iterations {
-                break;
+                count += 1;
+                total_count += 1;
+                if last_log.elapsed().as_millis() > SAMPLE_PERIOD_MS as u128 {
+                    info!(
+                        "count: {}, errors: {}, rps: {}",
+                        count,
+                        error_count,
+                        compute_rate_per_second(count)
+                    );
+                    last_log = Instant::now();
+                    count = 0;
+                }
+                if (iterations != 0 && total_count >= iterations) || stop.load(Ordering::Relaxed) {
+                    break;
+                }
             }
         }
     }
+    exit.store(true, Ordering::Relaxed);
+    if let Some(rpc_sampler_thread) = rpc_sampler_thread {
+        if let Err(err) = rpc_sampler_thread.join() {
+            println!("join() failed with: {err:?}");
+        }
+    }
 }
 
 fn main() {
@@ -820,15 +1718,17 @@ pub mod test {
         super::*,
         solana_client::tpu_client::QuicTpuClient,
         solana_core::validator::ValidatorConfig,
+        solana_entry::entry::Entry,
         solana_faucet::faucet::run_local_faucet,
         solana_gossip::contact_info::LegacyContactInfo,
+        solana_ledger::{blockstore::entries_to_test_shreds, get_tmp_ledger_path_auto_delete},
         solana_local_cluster::{
             cluster::Cluster,
             local_cluster::{ClusterConfig, LocalCluster},
             validator_configs::make_identical_validator_configs,
         },
         solana_rpc::rpc::JsonRpcConfig,
-        solana_sdk::timing::timestamp,
+        solana_sdk::{system_transaction, timing::timestamp, transaction::VersionedTransaction},
     };
 
     const TEST_SEND_BATCH_SIZE: usize = 1;
@@ -862,6 +1762,12 @@ pub mod test {
                 transaction_params: TransactionParams::default(),
                 tpu_use_quic: false,
                 send_batch_size: TEST_SEND_BATCH_SIZE,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
 
@@ -883,6 +1789,12 @@ pub mod test {
                 transaction_params: TransactionParams::default(),
                 tpu_use_quic: false,
                 send_batch_size: TEST_SEND_BATCH_SIZE,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
 
@@ -901,6 +1813,12 @@ pub mod test {
                 transaction_params: TransactionParams::default(),
                 tpu_use_quic: false,
                 send_batch_size: TEST_SEND_BATCH_SIZE,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
 
@@ -919,6 +1837,12 @@ pub mod test {
                 transaction_params: TransactionParams::default(),
                 tpu_use_quic: false,
                 send_batch_size: TEST_SEND_BATCH_SIZE,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
     }
@@ -956,6 +1880,12 @@ pub mod test {
                 transaction_params: TransactionParams::default(),
                 tpu_use_quic: false,
                 send_batch_size: TEST_SEND_BATCH_SIZE,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
     }
@@ -1001,9 +1931,16 @@ pub mod test {
                     unique_transactions: false,
                     transaction_type: None,
                     num_instructions: None,
+                    blockhash_age_distribution: None,
                 },
                 tpu_use_quic: false,
                 send_batch_size: TEST_SEND_BATCH_SIZE,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
 
@@ -1028,9 +1965,16 @@ pub mod test {
                     unique_transactions: true,
                     transaction_type: None,
                     num_instructions: None,
+                    blockhash_age_distribution: None,
                 },
                 tpu_use_quic: false,
                 send_batch_size: TEST_SEND_BATCH_SIZE,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
 
@@ -1055,9 +1999,16 @@ pub mod test {
                     unique_transactions: true,
                     transaction_type: None,
                     num_instructions: None,
+                    blockhash_age_distribution: None,
                 },
                 tpu_use_quic: false,
                 send_batch_size: TEST_SEND_BATCH_SIZE,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
     }
@@ -1136,9 +2087,17 @@ pub mod test {
                     unique_transactions: false,
                     transaction_type: Some(TransactionType::Transfer),
                     num_instructions: Some(1),
+                    blockhash_age_distribution: None,
                 },
                 tpu_use_quic,
                 send_batch_size: TEST_SEND_BATCH_SIZE,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                // also exercises sampling the target while sending
+                sample_target_rpc_interval_ms: Some(100),
+                seed: None,
+                stop_when: vec![],
             },
         );
 
@@ -1165,9 +2124,21 @@ pub mod test {
                     unique_transactions: true,
                     transaction_type: Some(TransactionType::Transfer),
                     num_instructions: Some(1),
+                    blockhash_age_distribution: None,
                 },
                 tpu_use_quic,
                 send_batch_size: TEST_SEND_BATCH_SIZE,
+                ledger_replay_params: LedgerReplayParams::default(),
+                // also exercises refunding and sweeping the payers
+                funding_params: FundingParams {
+                    lamports_per_payer: 2_000_000,
+                    refund_payers_below: Some(1_000_000),
+                    sweep_payers_to: Some(faucet_pubkey),
+                },
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
         // creates and sends unique transactions of type Transfer
@@ -1193,9 +2164,16 @@ pub mod test {
                     unique_transactions: true,
                     transaction_type: Some(TransactionType::Transfer),
                     num_instructions: Some(8),
+                    blockhash_age_distribution: None,
                 },
                 tpu_use_quic,
                 send_batch_size: TEST_SEND_BATCH_SIZE,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
         // creates and sends unique transactions of type CreateAccount
@@ -1204,7 +2182,7 @@ pub mod test {
         run_dos(
             &nodes_slice,
             10,
-            Some(client),
+            Some(client.clone()),
             DosClientParameters {
                 entrypoint_addr: cluster.entry_point_info.gossip().unwrap(),
                 mode: Mode::Tpu,
@@ -1221,9 +2199,49 @@ pub mod test {
                     unique_transactions: true,
                     transaction_type: Some(TransactionType::AccountCreation),
                     num_instructions: None,
+                    blockhash_age_distribution: None,
                 },
                 tpu_use_quic,
                 send_batch_size: TEST_SEND_BATCH_SIZE,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
+            },
+        );
+        // creates a lookup table and sends v0 transactions transferring to the addresses from it
+        run_dos(
+            &nodes_slice,
+            10,
+            Some(client),
+            DosClientParameters {
+                entrypoint_addr: cluster.entry_point_info.gossip().unwrap(),
+                mode: Mode::Tpu,
+                data_size: 0, // irrelevant if not random
+                data_type: DataType::Transaction,
+                data_input: None,
+                skip_gossip: false,
+                allow_private_addr: false,
+                num_gen_threads: 1,
+                transaction_params: TransactionParams {
+                    num_signatures: None,
+                    valid_blockhash: true,
+                    valid_signatures: true,
+                    unique_transactions: true,
+                    transaction_type: Some(TransactionType::V0WithLookupTables),
+                    num_instructions: Some(4),
+                    blockhash_age_distribution: None,
+                },
+                tpu_use_quic,
+                send_batch_size: TEST_SEND_BATCH_SIZE,
+                ledger_replay_params: LedgerReplayParams::default(),
+                funding_params: FundingParams::default(),
+                slow_stream_params: SlowStreamParams::default(),
+                sample_target_rpc_interval_ms: None,
+                seed: None,
+                stop_when: vec![],
             },
         );
     }
@@ -1237,4 +2255,168 @@ pub mod test {
     fn test_dos_with_blockhash_and_payer_and_quic() {
         run_dos_with_blockhash_and_payer(/*tpu_use_quic*/ true)
     }
+
+    #[test]
+    fn test_check_stop_conditions() {
+        let healthy: Result<(), String> = Ok(());
+        let unhealthy: Result<(), String> = Err("node is behind".to_string());
+        let stop_conditions = [
+            StopCondition::Unhealthy,
+            StopCondition::SlotStall(Duration::from_secs(10)),
+        ];
+
+        assert_eq!(
+            check_stop_conditions(&stop_conditions, &healthy, Duration::from_secs(1)),
+            None
+        );
+        assert!(
+            check_stop_conditions(&stop_conditions, &unhealthy, Duration::from_secs(1)).is_some()
+        );
+        assert!(
+            check_stop_conditions(&stop_conditions, &healthy, Duration::from_secs(10)).is_some()
+        );
+        // without conditions, the attack never stops due to the target's state
+        assert_eq!(
+            check_stop_conditions(&[], &unhealthy, Duration::from_secs(10)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pick_blockhash() {
+        let transaction_params = TransactionParams {
+            valid_blockhash: true,
+            transaction_type: Some(TransactionType::Transfer),
+            num_instructions: Some(1),
+            blockhash_age_distribution: Some(BlockhashAgeDistribution {
+                just_valid: 1,
+                ..BlockhashAgeDistribution::default()
+            }),
+            ..TransactionParams::default()
+        };
+        let mut generator =
+            TransactionGenerator::new(transaction_params, create_rng(Some(0), "generator", 0));
+        let blockhashes: Vec<_> = (0..=20).map(|_| Hash::new_unique()).collect();
+        // a new blockhash every 10 blocks
+        for (i, blockhash) in blockhashes.iter().enumerate() {
+            let last_valid_block_height = MAX_PROCESSING_AGE as u64 + 10 * i as u64;
+            generator.record_blockhash(*blockhash, last_valid_block_height);
+        }
+        // the current block height is 200. so, the blockhashes up to the 4th one have expired,
+        // and the ones before it are dropped.
+        assert_eq!(generator.recent_blockhashes.len(), 17);
+        assert_eq!(generator.recent_blockhashes[0].0, blockhashes[4]);
+
+        let just_valid = BlockhashAgeDistribution {
+            just_valid: 1,
+            ..BlockhashAgeDistribution::default()
+        };
+        let about_to_expire = BlockhashAgeDistribution {
+            about_to_expire: 1,
+            ..BlockhashAgeDistribution::default()
+        };
+        let just_expired = BlockhashAgeDistribution {
+            just_expired: 1,
+            ..BlockhashAgeDistribution::default()
+        };
+        assert_eq!(generator.pick_blockhash(just_valid), blockhashes[20]);
+        assert_eq!(generator.pick_blockhash(about_to_expire), blockhashes[5]);
+        assert_eq!(generator.pick_blockhash(just_expired), blockhashes[4]);
+
+        // nothing has expired yet at the start of the attack
+        let mut generator = TransactionGenerator::new(
+            generator.transaction_params.clone(),
+            create_rng(Some(0), "generator", 0),
+        );
+        generator.record_blockhash(blockhashes[0], MAX_PROCESSING_AGE as u64);
+        generator.record_blockhash(blockhashes[1], MAX_PROCESSING_AGE as u64 + 1);
+        assert_eq!(generator.pick_blockhash(just_expired), blockhashes[0]);
+    }
+
+    #[test]
+    fn test_create_rng() {
+        let sample = |mut rng: StdRng| rng.gen::<[u8; 32]>();
+        // reproducible with the same seed
+        assert_eq!(
+            sample(create_rng(Some(1), "generator", 0)),
+            sample(create_rng(Some(1), "generator", 0))
+        );
+        // independent across seeds, purposes and threads, even if their sums would collide
+        let streams = [
+            sample(create_rng(Some(1), "generator", 0)),
+            sample(create_rng(Some(0), "generator", 1)),
+            sample(create_rng(Some(1), "main", 0)),
+            sample(create_rng(Some(1), "transactions", 0)),
+        ];
+        assert_eq!(streams.iter().unique().count(), streams.len());
+    }
+
+    #[test]
+    fn test_batch_latencies() {
+        let mut latencies = BatchLatencies::default();
+        let report = latencies.report();
+        assert_eq!(report.batches, 0);
+        assert_eq!(report.send_us, LatencyPercentiles::default());
+
+        // the outliers are hidden by the median, unlike by the mean
+        for _ in 0..90 {
+            latencies.record(20_000, 100_000);
+        }
+        for _ in 0..10 {
+            latencies.record(20_000, 5_000_000);
+        }
+        let report = latencies.report();
+        assert_eq!(report.batches, 100);
+        assert_eq!(report.generate_us.p50, 20);
+        assert_eq!(report.generate_us.p999, 20);
+        assert_eq!(report.send_us.p50, 100);
+        assert_eq!(report.send_us.p99, 5_000);
+    }
+
+    #[test]
+    fn test_load_ledger_transactions() {
+        let ledger_path = get_tmp_ledger_path_auto_delete!();
+        let payer = Keypair::new();
+        let transactions: Vec<Transaction> = (0..3)
+            .map(|i| {
+                system_transaction::transfer(&payer, &Pubkey::new_unique(), i + 1, Hash::default())
+            })
+            .collect();
+        {
+            let blockstore = Blockstore::open(ledger_path.path()).unwrap();
+            for (slot, tx) in (1..).zip(transactions.iter()) {
+                let entries = vec![Entry::new(&Hash::default(), 1, vec![tx.clone()])];
+                let shreds = entries_to_test_shreds(&entries, slot, slot - 1, true, 0, true);
+                blockstore.insert_shreds(shreds, None, false).unwrap();
+            }
+        }
+
+        let mut params = LedgerReplayParams {
+            ledger_path: Some(ledger_path.path().to_path_buf()),
+            replay_start_slot: 2,
+            ..LedgerReplayParams::default()
+        };
+        let expected: Vec<Vec<u8>> = transactions[1..]
+            .iter()
+            .map(|tx| bincode::serialize(&VersionedTransaction::from(tx.clone())).unwrap())
+            .collect();
+        let mut rng = create_rng(None, "main", 0);
+        assert_eq!(load_ledger_transactions(&params, &mut rng), expected);
+
+        params.replay_end_slot = Some(2);
+        assert_eq!(load_ledger_transactions(&params, &mut rng), expected[..1]);
+
+        params.replay_start_slot = 1;
+        params.replay_end_slot = None;
+        params.replay_shuffle = true;
+        let mut shuffled = load_ledger_transactions(&params, &mut rng);
+        assert_eq!(shuffled.len(), transactions.len());
+        shuffled.sort();
+        let mut all: Vec<Vec<u8>> = transactions
+            .iter()
+            .map(|tx| bincode::serialize(&VersionedTransaction::from(tx.clone())).unwrap())
+            .collect();
+        all.sort();
+        assert_eq!(shuffled, all);
+    }
 }
@@ -38,7 +38,7 @@ use {
         bank_forks::BankForks,
         bank_utils,
         commitment::VOTE_THRESHOLD_SIZE,
-        installed_scheduler_pool::BankWithScheduler,
+        installed_scheduler_pool::{BankWithScheduler, SchedulerError},
         prioritization_fee_cache::PrioritizationFeeCache,
         transaction_batch::TransactionBatch,
     },
@@ -684,6 +684,9 @@ pub enum BlockstoreProcessorError {
 
     #[error("root bank with mismatched capitalization at {0}")]
     RootBankWithMismatchedCapitalization(Slot),
+
+    #[error("scheduler failed: {0}")]
+    SchedulerFailed(#[from] SchedulerError),
 }
 
 /// Callback for accessing bank state after each slot is confirmed while
@@ -1426,7 +1429,8 @@ fn process_bank_0(
         &mut ExecuteTimings::default(),
     )
     .expect("Failed to process bank 0 from ledger. Did you forget to provide a snapshot?");
-    if let Some((result, _timings)) = bank0.wait_for_completed_scheduler() {
+    if let Some(result_with_timings) = bank0.wait_for_completed_scheduler() {
+        let (result, _timings) = result_with_timings.unwrap();
         result.unwrap();
     }
     bank0.freeze();
@@ -1831,7 +1835,8 @@ fn process_single_slot(
         err
     })?;
 
-    if let Some((result, _timings)) = bank.wait_for_completed_scheduler() {
+    if let Some(result_with_timings) = bank.wait_for_completed_scheduler() {
+        let (result, _timings) = result_with_timings?;
         result?
     }
     bank.freeze(); // all banks handled by this routine are created from complete slots
@@ -1980,7 +1985,7 @@ pub mod tests {
                 self, create_genesis_config_with_vote_accounts, ValidatorVoteKeypairs,
             },
             installed_scheduler_pool::{
-                MockInstalledScheduler, MockUninstalledScheduler, SchedulingContext,
+                MockInstalledScheduler, MockUninstalledScheduler, SchedulingContext, SessionSummary,
             },
         },
         solana_sdk::{
@@ -4617,7 +4622,8 @@ pub mod tests {
                     .in_sequence(&mut seq_cloned.lock().unwrap())
                     .returning(|| ());
                 (
-                    (Ok(()), ExecuteTimings::default()),
+                    Ok((Ok(()), ExecuteTimings::default())),
+                    SessionSummary::default(),
                     Box::new(mocked_uninstalled_scheduler),
                 )
             });
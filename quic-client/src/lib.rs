@@ -14,6 +14,7 @@ use {
         },
         quic_client::QuicClientConnection as BlockingQuicClientConnection,
     },
+    futures::future::join_all,
     quinn::Endpoint,
     solana_connection_cache::{
         connection_cache::{
@@ -32,15 +33,43 @@ use {
         tls_certificates::new_dummy_x509_certificate,
     },
     std::{
+        collections::HashSet,
         net::{IpAddr, SocketAddr},
         sync::{Arc, RwLock},
+        time::Duration,
     },
+    tokio::time::timeout,
 };
 
+#[derive(Clone)]
 pub struct QuicPool {
     connections: Vec<Arc<Quic>>,
     endpoint: Arc<QuicLazyInitializedEndpoint>,
 }
+
+impl QuicPool {
+    /// Closes all of the connections of the pool gracefully, e.g. on validator shutdown. See
+    /// [`QuicClient::close`]. The close of the connections is then awaited for the remainder of
+    /// `drain_timeout`, so that the servers are notified before the endpoint goes away. Returns
+    /// whether all of the in-flight streams have been finished in time.
+    pub async fn close(&self, drain_timeout: Duration) -> bool {
+        let deadline = tokio::time::Instant::now() + drain_timeout;
+        let drained = join_all(
+            self.connections
+                .iter()
+                .map(|connection| connection.0.close(drain_timeout)),
+        )
+        .await
+        .into_iter()
+        .all(|drained| drained);
+        let _ = timeout(
+            deadline.saturating_duration_since(tokio::time::Instant::now()),
+            self.endpoint.wait_idle(),
+        )
+        .await;
+        drained
+    }
+}
 impl ConnectionPool for QuicPool {
     type BaseClientConnection = Quic;
     type NewConnectionConfig = QuicConfig;
@@ -68,11 +97,9 @@ impl ConnectionPool for QuicPool {
         config: &Self::NewConnectionConfig,
         addr: &SocketAddr,
     ) -> Arc<Self::BaseClientConnection> {
-        Arc::new(Quic(Arc::new(QuicClient::new(
-            self.endpoint.clone(),
-            *addr,
-            config.compute_max_parallel_streams(),
-        ))))
+        Arc::new(Quic(Arc::new(
+            config.create_client(self.endpoint.clone(), *addr),
+        )))
     }
 }
 
@@ -85,6 +112,27 @@ pub struct QuicConfig {
     // The optional specified endpoint for the quic based client connections
     // If not specified, the connection cache will create as needed.
     client_endpoint: Option<Endpoint>,
+
+    // The number of times a batch send may reconnect to resend its remainder after the connection
+    // is lost in the middle of it. Zero by default.
+    batch_send_retries: usize,
+
+    // Whether to offer zstd-compressed batches to the servers, which is meant for trusted
+    // validator-to-validator forwarding. Disabled by default.
+    enable_zstd_batches: bool,
+
+    // The cap of the sending rate of each connection in bytes per second. Unlimited by default.
+    pacing_rate: Option<u64>,
+
+    // The identities which servers must have, if specified. Any server is accepted by default.
+    known_validators: Option<Arc<RwLock<HashSet<Pubkey>>>>,
+
+    // The client-side cap on the concurrent streams of each connection, on top of the stream
+    // credit granted by the server. Uncapped by default.
+    max_concurrent_streams: Option<usize>,
+
+    // The per-connection send window in bytes. quinn's default if not specified.
+    send_window: Option<u64>,
 }
 
 impl Clone for QuicConfig {
@@ -95,6 +143,12 @@ impl Clone for QuicConfig {
             maybe_staked_nodes: self.maybe_staked_nodes.clone(),
             maybe_client_pubkey: self.maybe_client_pubkey,
             client_endpoint: self.client_endpoint.clone(),
+            batch_send_retries: self.batch_send_retries,
+            enable_zstd_batches: self.enable_zstd_batches,
+            pacing_rate: self.pacing_rate,
+            known_validators: self.known_validators.clone(),
+            max_concurrent_streams: self.max_concurrent_streams,
+            send_window: self.send_window,
         }
     }
 }
@@ -110,6 +164,12 @@ impl NewConnectionConfig for QuicConfig {
             maybe_staked_nodes: None,
             maybe_client_pubkey: None,
             client_endpoint: None,
+            batch_send_retries: 0,
+            enable_zstd_batches: false,
+            pacing_rate: None,
+            known_validators: None,
+            max_concurrent_streams: None,
+            send_window: None,
         })
     }
 }
@@ -117,7 +177,29 @@ impl NewConnectionConfig for QuicConfig {
 impl QuicConfig {
     fn create_endpoint(&self) -> QuicLazyInitializedEndpoint {
         let cert_guard = self.client_certificate.read().unwrap();
-        QuicLazyInitializedEndpoint::new(cert_guard.clone(), self.client_endpoint.as_ref().cloned())
+        QuicLazyInitializedEndpoint::new_with_send_window(
+            cert_guard.clone(),
+            self.client_endpoint.as_ref().cloned(),
+            self.enable_zstd_batches,
+            self.known_validators.clone(),
+            self.send_window,
+        )
+    }
+
+    /// Creates a client to `addr` over `endpoint`, which is expected to be created by this config
+    fn create_client(
+        &self,
+        endpoint: Arc<QuicLazyInitializedEndpoint>,
+        addr: SocketAddr,
+    ) -> QuicClient {
+        QuicClient::new_with_stream_cap(
+            endpoint,
+            addr,
+            self.compute_max_parallel_streams(),
+            self.batch_send_retries,
+            self.pacing_rate,
+            self.max_concurrent_streams,
+        )
     }
 
     fn compute_max_parallel_streams(&self) -> usize {
@@ -172,6 +254,40 @@ impl QuicConfig {
     pub fn update_client_endpoint(&mut self, client_endpoint: Endpoint) {
         self.client_endpoint = Some(client_endpoint);
     }
+
+    pub fn set_batch_send_retries(&mut self, batch_send_retries: usize) {
+        self.batch_send_retries = batch_send_retries;
+    }
+
+    pub fn set_enable_zstd_batches(&mut self, enable_zstd_batches: bool) {
+        self.enable_zstd_batches = enable_zstd_batches;
+    }
+
+    /// Caps the sending rate of each connection to `pacing_rate` bytes per second, which may be
+    /// derived from the stake or configured limits, so as not to trigger throttling by servers.
+    pub fn set_pacing_rate(&mut self, pacing_rate: Option<u64>) {
+        self.pacing_rate = pacing_rate;
+    }
+
+    /// Rejects the servers whose identities aren't in `known_validators`, which can be updated
+    /// afterwards (e.g. as gossip discovers validators). Useful for private clusters and RPC
+    /// providers, which shouldn't talk to unknown peers.
+    pub fn set_known_validators(&mut self, known_validators: Option<Arc<RwLock<HashSet<Pubkey>>>>) {
+        self.known_validators = known_validators;
+    }
+
+    /// Caps the concurrent streams of each connection to `max_concurrent_streams`, on top of the
+    /// stream credit of the server, which is tracked regardless. See
+    /// [`StreamCap`](crate::nonblocking::stream_cap::StreamCap).
+    pub fn set_max_concurrent_streams(&mut self, max_concurrent_streams: Option<usize>) {
+        self.max_concurrent_streams = max_concurrent_streams;
+    }
+
+    /// Limits the unacknowledged bytes in flight on each connection to `send_window`, which is
+    /// reported by the stream limiter of the connections along with the credit of the server.
+    pub fn set_send_window(&mut self, send_window: Option<u64>) {
+        self.send_window = send_window;
+    }
 }
 
 pub struct Quic(Arc<QuicClient>);
@@ -0,0 +1,219 @@
+//! Pooling of [`QuicClient`]s keyed by the remote node's identity, rather than by its address.
+//!
+//! Leaders can change their TPU ports or be reachable over multiple addresses. Pooling by
+//! `SocketAddr` fragments the connections to a single node, while keying the pool by identity lets
+//! a new address be recognized as a migration of the same node: the connections to its least
+//! recently used addresses are retired, and the new connection shares the endpoint (i.e. the
+//! client certificate and the TLS session cache) with them. QUIC doesn't allow a connection to
+//! follow the server to a new address, so a new connection is still needed.
+//!
+//! The connections are configured by a [`QuicConfig`] just like the ones of the connection cache,
+//! including their pacing, compression, retries and server verification.
+
+use {
+    crate::{
+        nonblocking::quic_client::{QuicClient, QuicLazyInitializedEndpoint},
+        QuicConfig,
+    },
+    log::*,
+    solana_sdk::pubkey::Pubkey,
+    std::{
+        collections::HashMap,
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc, RwLock,
+        },
+    },
+};
+
+/// By default, the connection to the previous address is kept alongside the new one, so that
+/// senders still using the previous address during a leader schedule transition don't reconnect.
+pub const DEFAULT_MAX_ADDRESSES_PER_IDENTITY: usize = 2;
+
+#[derive(Default, Debug)]
+pub struct IdentityConnectionPoolStats {
+    pub hits: AtomicU64,
+    pub misses: AtomicU64,
+    /// Number of new addresses seen for already known identities
+    pub migrations: AtomicU64,
+    /// Number of connections retired to make room for new addresses
+    pub evictions: AtomicU64,
+}
+
+pub struct IdentityConnectionPool {
+    config: QuicConfig,
+    endpoint: Arc<QuicLazyInitializedEndpoint>,
+    max_addresses_per_identity: usize,
+    // connections for each identity, from the least recently used address to the most recently
+    // used one
+    connections: RwLock<HashMap<Pubkey, Vec<Arc<QuicClient>>>>,
+    stats: IdentityConnectionPoolStats,
+}
+
+impl IdentityConnectionPool {
+    pub fn new(config: QuicConfig) -> Self {
+        Self::new_with_max_addresses_per_identity(config, DEFAULT_MAX_ADDRESSES_PER_IDENTITY)
+    }
+
+    pub fn new_with_max_addresses_per_identity(
+        config: QuicConfig,
+        max_addresses_per_identity: usize,
+    ) -> Self {
+        assert!(max_addresses_per_identity > 0);
+        Self {
+            endpoint: Arc::new(config.create_endpoint()),
+            config,
+            max_addresses_per_identity,
+            connections: RwLock::default(),
+            stats: IdentityConnectionPoolStats::default(),
+        }
+    }
+
+    /// Returns the connection to `identity` at `addr`, creating one if `addr` hasn't been seen for
+    /// `identity` yet. The connections to the least recently used addresses of `identity` are
+    /// retired, once there are more than `max_addresses_per_identity` of them.
+    pub fn get_connection(&self, identity: &Pubkey, addr: SocketAddr) -> Arc<QuicClient> {
+        if let Some(client) = self.find_most_recent_connection(identity, &addr) {
+            self.stats.hits.fetch_add(1, Ordering::Relaxed);
+            return client;
+        }
+
+        let mut connections = self.connections.write().unwrap();
+        let clients = connections.entry(*identity).or_default();
+        // the address may have been used less recently than the others, or another caller might
+        // have created the connection in the meantime
+        if let Some(index) = clients
+            .iter()
+            .position(|client| *client.server_addr() == addr)
+        {
+            self.stats.hits.fetch_add(1, Ordering::Relaxed);
+            let client = clients.remove(index);
+            clients.push(client.clone());
+            return client;
+        }
+        self.stats.misses.fetch_add(1, Ordering::Relaxed);
+        if let Some(previous) = clients.last() {
+            debug!(
+                "{} has moved from {} to {}",
+                identity,
+                previous.server_addr(),
+                addr
+            );
+            self.stats.migrations.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let client = Arc::new(self.config.create_client(self.endpoint.clone(), addr));
+        clients.push(client.clone());
+        if clients.len() > self.max_addresses_per_identity {
+            let evicted = clients.len() - self.max_addresses_per_identity;
+            clients.drain(..evicted);
+            self.stats
+                .evictions
+                .fetch_add(evicted as u64, Ordering::Relaxed);
+        }
+        client
+    }
+
+    // Finds the connection without taking the write lock, as long as it's the most recently used
+    // one, which is the common case.
+    fn find_most_recent_connection(
+        &self,
+        identity: &Pubkey,
+        addr: &SocketAddr,
+    ) -> Option<Arc<QuicClient>> {
+        self.connections
+            .read()
+            .unwrap()
+            .get(identity)?
+            .last()
+            .filter(|client| client.server_addr() == addr)
+            .cloned()
+    }
+
+    /// Returns the addresses currently pooled for `identity`, from the least recently used to the
+    /// most recently used one.
+    pub fn addresses(&self, identity: &Pubkey) -> Vec<SocketAddr> {
+        self.connections
+            .read()
+            .unwrap()
+            .get(identity)
+            .map(|clients| clients.iter().map(|client| *client.server_addr()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Drops all of the connections to `identity`, e.g. once it's no longer an upcoming leader.
+    pub fn remove_identity(&self, identity: &Pubkey) {
+        self.connections.write().unwrap().remove(identity);
+    }
+
+    pub fn stats(&self) -> &IdentityConnectionPoolStats {
+        &self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*, solana_connection_cache::connection_cache::NewConnectionConfig,
+        std::net::Ipv4Addr,
+    };
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::LOCALHOST, port))
+    }
+
+    #[test]
+    fn test_identity_connection_pool() {
+        let mut config = QuicConfig::new().unwrap();
+        config.set_batch_send_retries(2);
+        config.set_pacing_rate(Some(1_000_000));
+        let pool = IdentityConnectionPool::new(config);
+        let identity = Pubkey::new_unique();
+        let other_identity = Pubkey::new_unique();
+
+        let client = pool.get_connection(&identity, addr(8000));
+        // the connections are configured like the ones of the connection cache
+        assert_eq!(client.pacing_rate(), Some(1_000_000));
+        assert!(Arc::ptr_eq(
+            &client,
+            &pool.get_connection(&identity, addr(8000))
+        ));
+        assert_eq!(pool.stats().hits.load(Ordering::Relaxed), 1);
+        assert_eq!(pool.stats().migrations.load(Ordering::Relaxed), 0);
+
+        // the same address for another identity isn't shared
+        assert!(!Arc::ptr_eq(
+            &client,
+            &pool.get_connection(&other_identity, addr(8000))
+        ));
+        assert_eq!(pool.stats().migrations.load(Ordering::Relaxed), 0);
+
+        // the previous address is kept while migrating
+        pool.get_connection(&identity, addr(8001));
+        assert_eq!(pool.addresses(&identity), vec![addr(8000), addr(8001)]);
+        assert_eq!(pool.stats().migrations.load(Ordering::Relaxed), 1);
+        // ...and using it makes it the most recently used one
+        assert!(Arc::ptr_eq(
+            &client,
+            &pool.get_connection(&identity, addr(8000))
+        ));
+        assert_eq!(pool.addresses(&identity), vec![addr(8001), addr(8000)]);
+        assert_eq!(pool.stats().hits.load(Ordering::Relaxed), 2);
+
+        // the least recently used one is retired once another address shows up
+        pool.get_connection(&identity, addr(8002));
+        assert_eq!(pool.addresses(&identity), vec![addr(8000), addr(8002)]);
+        assert_eq!(pool.stats().migrations.load(Ordering::Relaxed), 2);
+        assert_eq!(pool.stats().evictions.load(Ordering::Relaxed), 1);
+        assert_eq!(pool.stats().misses.load(Ordering::Relaxed), 4);
+        assert!(Arc::ptr_eq(
+            &client,
+            &pool.get_connection(&identity, addr(8000))
+        ));
+
+        pool.remove_identity(&identity);
+        assert!(pool.addresses(&identity).is_empty());
+        assert_eq!(pool.addresses(&other_identity), vec![addr(8000)]);
+    }
+}
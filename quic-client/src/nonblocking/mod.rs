@@ -1 +1,7 @@
+pub mod identity_connection_pool;
+pub mod named_quic_client;
+#[cfg(feature = "network-sim")]
+pub mod network_sim;
+pub mod pacing;
 pub mod quic_client;
+pub mod stream_cap;
@@ -0,0 +1,141 @@
+//! A [`QuicClient`] wrapper connecting to a server by its hostname, rather than by a fixed
+//! address.
+//!
+//! The hostname is resolved asynchronously on connection and then periodically re-resolved, if
+//! requested. Once the current address disappears from the resolved ones (e.g. when the server has
+//! been moved to another host), the client migrates to a new connection to one of the new
+//! addresses.
+
+use {
+    crate::nonblocking::quic_client::{QuicClient, QuicLazyInitializedEndpoint},
+    log::*,
+    solana_connection_cache::{
+        client_connection::ClientStats, connection_cache_stats::ConnectionCacheStats,
+    },
+    solana_rpc_client_api::client_error::ErrorKind as ClientErrorKind,
+    std::{
+        io,
+        net::SocketAddr,
+        sync::{Arc, RwLock},
+        time::Duration,
+    },
+    tokio::{net::lookup_host, task::JoinHandle},
+};
+
+pub struct NamedQuicClient {
+    endpoint: Arc<QuicLazyInitializedEndpoint>,
+    // `host:port`
+    name: String,
+    chunk_size: usize,
+    // replaced on migration. in-flight sends keep using the previous client until they finish.
+    client: RwLock<Arc<QuicClient>>,
+}
+
+impl NamedQuicClient {
+    /// Resolves `name`, which is in the form of `host:port`, and creates a client connecting to
+    /// the resolved address.
+    pub async fn connect_by_name(
+        endpoint: Arc<QuicLazyInitializedEndpoint>,
+        name: &str,
+        chunk_size: usize,
+    ) -> io::Result<Arc<Self>> {
+        let addrs = Self::resolve(name).await?;
+        let client = QuicClient::new(endpoint.clone(), addrs[0], chunk_size);
+        Ok(Arc::new(Self {
+            endpoint,
+            name: name.to_string(),
+            chunk_size,
+            client: RwLock::new(Arc::new(client)),
+        }))
+    }
+
+    // Only IPv4 addresses are usable, because client endpoints are bound to IPv4 sockets.
+    async fn resolve(name: &str) -> io::Result<Vec<SocketAddr>> {
+        let addrs: Vec<_> = lookup_host(name)
+            .await?
+            .filter(|addr| addr.is_ipv4())
+            .collect();
+        if addrs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{name} doesn't resolve to any IPv4 address"),
+            ));
+        }
+        Ok(addrs)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the address currently connected to.
+    pub fn server_addr(&self) -> SocketAddr {
+        *self.client().server_addr()
+    }
+
+    fn client(&self) -> Arc<QuicClient> {
+        self.client.read().unwrap().clone()
+    }
+
+    /// Re-resolves the hostname, migrating to a new connection if the current address isn't
+    /// resolved anymore. Returns whether the client has migrated.
+    pub async fn re_resolve(&self) -> io::Result<bool> {
+        let addrs = Self::resolve(&self.name).await?;
+        let current_addr = self.server_addr();
+        if addrs.contains(&current_addr) {
+            return Ok(false);
+        }
+        info!(
+            "{} has moved from {} to {}, migrating the connection",
+            self.name, current_addr, addrs[0]
+        );
+        let client = QuicClient::new(self.endpoint.clone(), addrs[0], self.chunk_size);
+        *self.client.write().unwrap() = Arc::new(client);
+        Ok(true)
+    }
+
+    /// Spawns a task re-resolving the hostname every `interval`, until this client is dropped.
+    pub fn spawn_re_resolution(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let client = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(client) = client.upgrade() else {
+                    break;
+                };
+                if let Err(err) = client.re_resolve().await {
+                    // keep using the current address, which might still work
+                    warn!("Failed to re-resolve {}: {}", client.name, err);
+                }
+            }
+        })
+    }
+
+    pub async fn send_buffer<T>(
+        &self,
+        data: T,
+        stats: &ClientStats,
+        connection_stats: Arc<ConnectionCacheStats>,
+    ) -> Result<(), ClientErrorKind>
+    where
+        T: AsRef<[u8]>,
+    {
+        self.client()
+            .send_buffer(data, stats, connection_stats)
+            .await
+    }
+
+    pub async fn send_batch<T>(
+        &self,
+        buffers: &[T],
+        stats: &ClientStats,
+        connection_stats: Arc<ConnectionCacheStats>,
+    ) -> Result<(), ClientErrorKind>
+    where
+        T: AsRef<[u8]>,
+    {
+        self.client()
+            .send_batch(buffers, stats, connection_stats)
+            .await
+    }
+}
@@ -0,0 +1,247 @@
+//! A test-only shim which simulates an imperfect network underneath a quinn [`Endpoint`].
+//!
+//! Datagrams sent through the endpoint can be dropped, delayed and reordered according to
+//! [`NetworkSimConfig`], so that the retry and timeout logic of the quic client and its callers
+//! can be tested without external network emulation tools. Received datagrams are passed through
+//! as is.
+//!
+//! The returned endpoint can be passed to [`QuicLazyInitializedEndpoint::new`] as the client
+//! endpoint.
+//!
+//! [`QuicLazyInitializedEndpoint::new`]: crate::nonblocking::quic_client::QuicLazyInitializedEndpoint::new
+use {
+    quinn::{
+        udp::{RecvMeta, UdpState},
+        AsyncUdpSocket, Endpoint, EndpointConfig, Runtime, TokioRuntime,
+    },
+    quinn_proto::Transmit,
+    rand::{rngs::StdRng, Rng, SeedableRng},
+    std::{
+        fmt,
+        io::{self, IoSliceMut},
+        net::{SocketAddr, UdpSocket},
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+        time::Duration,
+    },
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct NetworkSimConfig {
+    /// Probability of a sent datagram to be dropped, in the range of `0.0..=1.0`.
+    pub loss_rate: f64,
+    /// Delay added to every sent datagram.
+    pub latency: Duration,
+    /// Probability of a sent datagram to be delayed further by up to `reorder_delay`, so that it's
+    /// likely to be overtaken by the datagrams sent after it. In the range of `0.0..=1.0`.
+    pub reorder_rate: f64,
+    pub reorder_delay: Duration,
+    /// Seed of the random decisions, for reproducible tests.
+    pub seed: u64,
+}
+
+/// Creates a client endpoint over `socket`, whose outgoing datagrams are subject to `config`.
+///
+/// Must be called within a tokio runtime.
+pub fn create_simulated_endpoint(socket: UdpSocket, config: NetworkSimConfig) -> Endpoint {
+    let sender = socket
+        .try_clone()
+        .expect("create_simulated_endpoint try_clone");
+    let inner = TokioRuntime
+        .wrap_udp_socket(socket)
+        .expect("create_simulated_endpoint wrap_udp_socket");
+    let socket = SimulatedUdpSocket {
+        inner,
+        sender: Arc::new(sender),
+        rng: Mutex::new(StdRng::seed_from_u64(config.seed)),
+        config,
+    };
+    Endpoint::new_with_abstract_socket(
+        EndpointConfig::default(),
+        None,
+        socket,
+        Arc::new(TokioRuntime),
+    )
+    .expect("create_simulated_endpoint Endpoint::new_with_abstract_socket")
+}
+
+struct SimulatedUdpSocket {
+    inner: Box<dyn AsyncUdpSocket>,
+    // Delayed datagrams are sent with this socket directly from spawned tasks, because
+    // inner.poll_send() can only be driven by the endpoint.
+    sender: Arc<UdpSocket>,
+    rng: Mutex<StdRng>,
+    config: NetworkSimConfig,
+}
+
+impl fmt::Debug for SimulatedUdpSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SimulatedUdpSocket")
+            .field("inner", &self.inner)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+impl SimulatedUdpSocket {
+    // Returns the delay of the datagram, or None if it should be dropped.
+    fn simulate(&self) -> Option<Duration> {
+        let mut rng = self.rng.lock().unwrap();
+        if rng.gen_bool(self.config.loss_rate) {
+            return None;
+        }
+        let mut delay = self.config.latency;
+        if rng.gen_bool(self.config.reorder_rate) {
+            delay += self.config.reorder_delay.mul_f64(rng.gen());
+        }
+        Some(delay)
+    }
+
+    fn send_datagram(&self, datagram: &[u8], destination: SocketAddr) {
+        let Some(delay) = self.simulate() else {
+            return;
+        };
+        if delay.is_zero() {
+            // errors are ignored like the datagrams lost in the network
+            let _ = self.sender.send_to(datagram, destination);
+            return;
+        }
+        let sender = self.sender.clone();
+        let datagram = datagram.to_vec();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = sender.send_to(&datagram, destination);
+        });
+    }
+}
+
+impl AsyncUdpSocket for SimulatedUdpSocket {
+    fn poll_send(
+        &self,
+        _state: &UdpState,
+        _cx: &mut Context,
+        transmits: &[Transmit],
+    ) -> Poll<io::Result<usize>> {
+        for transmit in transmits {
+            // split segmentation offloaded transmits into the individual datagrams
+            let segment_size = transmit
+                .segment_size
+                .unwrap_or(transmit.contents.len())
+                .max(1);
+            for datagram in transmit.contents.chunks(segment_size) {
+                self.send_datagram(datagram, transmit.destination);
+            }
+        }
+        Poll::Ready(Ok(transmits.len()))
+    }
+
+    fn poll_recv(
+        &self,
+        cx: &mut Context,
+        bufs: &mut [IoSliceMut<'_>],
+        meta: &mut [RecvMeta],
+    ) -> Poll<io::Result<usize>> {
+        self.inner.poll_recv(cx, bufs, meta)
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::nonblocking::quic_client::{
+            QuicClient, QuicClientCertificate, QuicLazyInitializedEndpoint,
+        },
+        solana_connection_cache::{
+            client_connection::ClientStats, connection_cache_stats::ConnectionCacheStats,
+        },
+        solana_sdk::{net::DEFAULT_TPU_COALESCE, signature::Keypair},
+        solana_streamer::{
+            nonblocking::quic::{spawn_server, DEFAULT_WAIT_FOR_CHUNK_TIMEOUT},
+            streamer::StakedNodes,
+            tls_certificates::new_dummy_x509_certificate,
+        },
+        std::{
+            sync::{
+                atomic::{AtomicBool, Ordering},
+                RwLock,
+            },
+            time::Instant,
+        },
+    };
+
+    async fn send_with_simulation(config: NetworkSimConfig) -> (bool, Duration) {
+        let (sender, _receiver) = crossbeam_channel::unbounded();
+        let server_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let server_addr = server_socket.local_addr().unwrap();
+        let exit = Arc::new(AtomicBool::new(false));
+        let (_, _, server) = spawn_server(
+            "quic_network_sim_test",
+            server_socket,
+            &Keypair::new(),
+            sender,
+            exit.clone(),
+            1,
+            Arc::new(RwLock::new(StakedNodes::default())),
+            10,
+            10,
+            DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
+            DEFAULT_TPU_COALESCE,
+        )
+        .unwrap();
+
+        let endpoint = create_simulated_endpoint(UdpSocket::bind("127.0.0.1:0").unwrap(), config);
+        let (cert, key) = new_dummy_x509_certificate(&Keypair::new());
+        let client = QuicClient::new(
+            Arc::new(QuicLazyInitializedEndpoint::new(
+                Arc::new(QuicClientCertificate {
+                    certificate: cert,
+                    key,
+                }),
+                Some(endpoint),
+            )),
+            server_addr,
+            1,
+        );
+        let start = Instant::now();
+        let result = client
+            .send_buffer(
+                [0u8; 10],
+                &ClientStats::default(),
+                Arc::new(ConnectionCacheStats::default()),
+            )
+            .await;
+        let elapsed = start.elapsed();
+
+        exit.store(true, Ordering::Relaxed);
+        server.await.unwrap();
+        (result.is_ok(), elapsed)
+    }
+
+    #[tokio::test]
+    async fn test_network_sim_latency() {
+        let latency = Duration::from_millis(100);
+        let (is_ok, elapsed) = send_with_simulation(NetworkSimConfig {
+            latency,
+            ..NetworkSimConfig::default()
+        })
+        .await;
+        assert!(is_ok);
+        // the handshake alone takes at least a round trip
+        assert!(elapsed >= latency);
+    }
+
+    #[tokio::test]
+    async fn test_network_sim_total_loss() {
+        let (is_ok, _) = send_with_simulation(NetworkSimConfig {
+            loss_rate: 1.0,
+            ..NetworkSimConfig::default()
+        })
+        .await;
+        assert!(!is_ok);
+    }
+}
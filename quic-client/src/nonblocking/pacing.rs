@@ -0,0 +1,163 @@
+//! A congestion controller capping the sending rate of a connection.
+//!
+//! quinn paces outgoing packets to spread the congestion window over the round trip time. So,
+//! capping the window to `rate * rtt` caps the pacing rate to `rate`, allowing cooperative senders
+//! to stay under the rate which the server would throttle them at. The window is otherwise
+//! controlled by Cubic as usual, so the effective rate can be lower under congestion.
+
+use {
+    quinn::congestion::{Controller, ControllerFactory, CubicConfig},
+    quinn_proto::RttEstimator,
+    std::{
+        any::Any,
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+};
+
+/// Returns the congestion window which results in sending `pacing_rate` bytes per second at the
+/// given round trip time. At least two packets are allowed in flight so that the connection
+/// never stalls.
+pub fn pacing_rate_window(pacing_rate: u64, rtt: Duration, mtu: u16) -> u64 {
+    let window = (u128::from(pacing_rate) * rtt.as_nanos() / 1_000_000_000) as u64;
+    window.max(2 * u64::from(mtu))
+}
+
+/// Builds [`PacingRateController`]s for the connections created with the transport config it's
+/// set to.
+#[derive(Debug)]
+pub struct PacingRateControllerFactory {
+    pacing_rate: u64,
+    inner: Arc<CubicConfig>,
+}
+
+impl PacingRateControllerFactory {
+    /// `pacing_rate` is in bytes per second.
+    pub fn new(pacing_rate: u64) -> Self {
+        Self {
+            pacing_rate,
+            inner: Arc::new(CubicConfig::default()),
+        }
+    }
+}
+
+impl ControllerFactory for PacingRateControllerFactory {
+    fn build(self: Arc<Self>, now: Instant, current_mtu: u16) -> Box<dyn Controller> {
+        Box::new(PacingRateController {
+            pacing_rate: self.pacing_rate,
+            rtt: None,
+            mtu: current_mtu,
+            inner: self.inner.clone().build(now, current_mtu),
+        })
+    }
+}
+
+pub struct PacingRateController {
+    pacing_rate: u64,
+    // unknown until the first ack
+    rtt: Option<Duration>,
+    mtu: u16,
+    inner: Box<dyn Controller>,
+}
+
+impl Clone for PacingRateController {
+    fn clone(&self) -> Self {
+        Self {
+            pacing_rate: self.pacing_rate,
+            rtt: self.rtt,
+            mtu: self.mtu,
+            inner: self.inner.clone_box(),
+        }
+    }
+}
+
+impl Controller for PacingRateController {
+    fn on_sent(&mut self, now: Instant, bytes: u64, last_packet_number: u64) {
+        self.inner.on_sent(now, bytes, last_packet_number);
+    }
+
+    fn on_ack(
+        &mut self,
+        now: Instant,
+        sent: Instant,
+        bytes: u64,
+        app_limited: bool,
+        rtt: &RttEstimator,
+    ) {
+        self.rtt = Some(rtt.get());
+        self.inner.on_ack(now, sent, bytes, app_limited, rtt);
+    }
+
+    fn on_end_acks(
+        &mut self,
+        now: Instant,
+        in_flight: u64,
+        app_limited: bool,
+        largest_packet_num_acked: Option<u64>,
+    ) {
+        self.inner
+            .on_end_acks(now, in_flight, app_limited, largest_packet_num_acked);
+    }
+
+    fn on_congestion_event(
+        &mut self,
+        now: Instant,
+        sent: Instant,
+        is_persistent_congestion: bool,
+        lost_bytes: u64,
+    ) {
+        self.inner
+            .on_congestion_event(now, sent, is_persistent_congestion, lost_bytes);
+    }
+
+    fn on_mtu_update(&mut self, new_mtu: u16) {
+        self.mtu = new_mtu;
+        self.inner.on_mtu_update(new_mtu);
+    }
+
+    fn window(&self) -> u64 {
+        let window = self.inner.window();
+        match self.rtt {
+            Some(rtt) => window.min(pacing_rate_window(self.pacing_rate, rtt, self.mtu)),
+            None => window,
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Controller> {
+        Box::new(self.clone())
+    }
+
+    fn initial_window(&self) -> u64 {
+        self.inner.initial_window()
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacing_rate_window() {
+        // 1 MB/s over 100ms
+        assert_eq!(
+            pacing_rate_window(1_000_000, Duration::from_millis(100), 1200),
+            100_000
+        );
+        // too low rates still allow two packets in flight
+        assert_eq!(
+            pacing_rate_window(1, Duration::from_millis(100), 1200),
+            2400
+        );
+    }
+
+    #[test]
+    fn test_pacing_rate_controller_before_ack() {
+        let controller = Arc::new(PacingRateControllerFactory::new(1)).build(Instant::now(), 1200);
+        // the rate can't be enforced without knowing rtt
+        assert_eq!(controller.window(), controller.initial_window());
+    }
+}
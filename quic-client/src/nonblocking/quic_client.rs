@@ -2,23 +2,28 @@
 //! and provides an interface for sending data which is restricted by the
 //! server's flow control.
 use {
+    crate::nonblocking::{
+        pacing::PacingRateControllerFactory,
+        stream_cap::{StreamCap, UNCAPPED_STREAMS},
+    },
     async_mutex::Mutex,
     async_trait::async_trait,
     futures::future::{join_all, TryFutureExt},
-    itertools::Itertools,
     log::*,
     quinn::{
-        ClientConfig, ConnectError, Connection, ConnectionError, Endpoint, EndpointConfig,
-        IdleTimeout, TokioRuntime, TransportConfig, WriteError,
+        ClientConfig, ConnectError, Connecting, Connection, ConnectionError, Endpoint,
+        EndpointConfig, IdleTimeout, SendStream, TokioRuntime, TransportConfig, VarInt, WriteError,
     },
     solana_connection_cache::{
-        client_connection::ClientStats, connection_cache_stats::ConnectionCacheStats,
+        client_connection::ClientStats,
+        connection_cache_stats::{ConnectionCacheStats, PeerKey, PeerStatsCounters},
         nonblocking::client_connection::ClientConnection,
     },
     solana_measure::measure::Measure,
     solana_net_utils::VALIDATOR_PORT_RANGE,
     solana_rpc_client_api::client_error::ErrorKind as ClientErrorKind,
     solana_sdk::{
+        pubkey::Pubkey,
         quic::{
             QUIC_CONNECTION_HANDSHAKE_TIMEOUT, QUIC_KEEP_ALIVE, QUIC_MAX_TIMEOUT,
             QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS,
@@ -27,17 +32,32 @@ use {
         transport::Result as TransportResult,
     },
     solana_streamer::{
-        nonblocking::quic::ALPN_TPU_PROTOCOL_ID, tls_certificates::new_dummy_x509_certificate,
+        nonblocking::quic::{
+            encode_zstd_batch, get_alpn_protocol, get_remote_pubkey, ALPN_TPU_PROTOCOL_ID,
+            ALPN_TPU_ZSTD_PROTOCOL_ID, CONNECTION_CLOSE_CODE_ZSTD_BATCH_DISALLOWED,
+            MAX_ZSTD_BATCH_PACKETS,
+        },
+        tls_certificates::{get_pubkey_from_tls_certificate, new_dummy_x509_certificate},
     },
     std::{
+        collections::HashSet,
+        io,
         net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
-        sync::{atomic::Ordering, Arc},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, OnceLock, RwLock,
+        },
         thread,
+        time::{Duration, SystemTime},
     },
     thiserror::Error,
     tokio::{sync::OnceCell, time::timeout},
 };
 
+/// The application close code of the connections closed by [`QuicClient::close`].
+pub const CONNECTION_CLOSE_CODE_CLIENT_SHUTDOWN: u32 = 0;
+const CONNECTION_CLOSE_REASON_CLIENT_SHUTDOWN: &[u8] = b"client_shutdown";
+
 pub struct SkipServerVerification;
 
 impl SkipServerVerification {
@@ -60,6 +80,42 @@ impl rustls::client::ServerCertVerifier for SkipServerVerification {
     }
 }
 
+/// Accepts only the servers whose certificates embed one of the given validator identities, which
+/// the caller is expected to keep up to date (e.g. from gossip). The handshake signatures are
+/// still verified against the certificate, so that peers can't impersonate the known validators.
+pub struct KnownValidatorsServerVerification {
+    known_validators: Arc<RwLock<HashSet<Pubkey>>>,
+}
+
+impl KnownValidatorsServerVerification {
+    pub fn new(known_validators: Arc<RwLock<HashSet<Pubkey>>>) -> Arc<Self> {
+        Arc::new(Self { known_validators })
+    }
+}
+
+impl rustls::client::ServerCertVerifier for KnownValidatorsServerVerification {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+        let pubkey = get_pubkey_from_tls_certificate(end_entity).ok_or_else(|| {
+            rustls::Error::General("server certificate has no validator identity".to_string())
+        })?;
+        if self.known_validators.read().unwrap().contains(&pubkey) {
+            Ok(rustls::client::ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(format!(
+                "server identity {pubkey} isn't a known validator"
+            )))
+        }
+    }
+}
+
 pub struct QuicClientCertificate {
     pub certificate: rustls::Certificate,
     pub key: rustls::PrivateKey,
@@ -70,6 +126,14 @@ pub struct QuicLazyInitializedEndpoint {
     endpoint: OnceCell<Arc<Endpoint>>,
     client_certificate: Arc<QuicClientCertificate>,
     client_endpoint: Option<Endpoint>,
+    // Whether to offer ALPN_TPU_ZSTD_PROTOCOL_ID to servers, sending zstd-compressed batches if
+    // the server agrees
+    enable_zstd_batches: bool,
+    // If specified, servers are verified against these identities. Otherwise, any server is
+    // accepted. See KnownValidatorsServerVerification.
+    known_validators: Option<Arc<RwLock<HashSet<Pubkey>>>>,
+    // The per-connection send window of the connections, if not quinn's default
+    send_window: Option<u64>,
 }
 
 #[derive(Error, Debug)]
@@ -80,6 +144,10 @@ pub enum QuicError {
     ConnectionError(#[from] ConnectionError),
     #[error(transparent)]
     ConnectError(#[from] ConnectError),
+    #[error("failed to encode zstd batch: {0}")]
+    ZstdBatchError(io::Error),
+    #[error("the client has been closed")]
+    ClientClosed,
 }
 
 impl From<QuicError> for ClientErrorKind {
@@ -92,14 +160,62 @@ impl QuicLazyInitializedEndpoint {
     pub fn new(
         client_certificate: Arc<QuicClientCertificate>,
         client_endpoint: Option<Endpoint>,
+    ) -> Self {
+        Self::new_with_zstd_batches(client_certificate, client_endpoint, false)
+    }
+
+    pub fn new_with_zstd_batches(
+        client_certificate: Arc<QuicClientCertificate>,
+        client_endpoint: Option<Endpoint>,
+        enable_zstd_batches: bool,
+    ) -> Self {
+        Self::new_with_known_validators(
+            client_certificate,
+            client_endpoint,
+            enable_zstd_batches,
+            None,
+        )
+    }
+
+    /// Creates an endpoint which rejects the servers not in `known_validators`, if specified.
+    pub fn new_with_known_validators(
+        client_certificate: Arc<QuicClientCertificate>,
+        client_endpoint: Option<Endpoint>,
+        enable_zstd_batches: bool,
+        known_validators: Option<Arc<RwLock<HashSet<Pubkey>>>>,
+    ) -> Self {
+        Self::new_with_send_window(
+            client_certificate,
+            client_endpoint,
+            enable_zstd_batches,
+            known_validators,
+            None,
+        )
+    }
+
+    /// `send_window` limits the unacknowledged bytes in flight on each connection, if specified.
+    pub fn new_with_send_window(
+        client_certificate: Arc<QuicClientCertificate>,
+        client_endpoint: Option<Endpoint>,
+        enable_zstd_batches: bool,
+        known_validators: Option<Arc<RwLock<HashSet<Pubkey>>>>,
+        send_window: Option<u64>,
     ) -> Self {
         Self {
             endpoint: OnceCell::<Arc<Endpoint>>::new(),
             client_certificate,
             client_endpoint,
+            enable_zstd_batches,
+            known_validators,
+            send_window,
         }
     }
 
+    /// Returns the per-connection send window of the connections, if not quinn's default.
+    pub fn send_window(&self) -> Option<u64> {
+        self.send_window
+    }
+
     fn create_endpoint(&self) -> Endpoint {
         let mut endpoint = if let Some(endpoint) = &self.client_endpoint {
             endpoint.clone()
@@ -114,16 +230,46 @@ impl QuicLazyInitializedEndpoint {
             QuicNewConnection::create_endpoint(EndpointConfig::default(), client_socket)
         };
 
+        endpoint.set_default_client_config(self.create_client_config(None));
+
+        endpoint
+    }
+
+    /// Creates the client config of connections, whose sending rate is capped to `pacing_rate`
+    /// bytes per second if specified. See [`PacingRateControllerFactory`].
+    fn create_client_config(&self, pacing_rate: Option<u64>) -> ClientConfig {
+        self.create_client_config_with_zstd_batches(pacing_rate, self.enable_zstd_batches)
+    }
+
+    fn create_client_config_with_zstd_batches(
+        &self,
+        pacing_rate: Option<u64>,
+        enable_zstd_batches: bool,
+    ) -> ClientConfig {
+        let verifier: Arc<dyn rustls::client::ServerCertVerifier> = match &self.known_validators {
+            Some(known_validators) => {
+                KnownValidatorsServerVerification::new(known_validators.clone())
+            }
+            None => SkipServerVerification::new(),
+        };
         let mut crypto = rustls::ClientConfig::builder()
             .with_safe_defaults()
-            .with_custom_certificate_verifier(SkipServerVerification::new())
+            .with_custom_certificate_verifier(verifier)
             .with_client_auth_cert(
                 vec![self.client_certificate.certificate.clone()],
                 self.client_certificate.key.clone(),
             )
             .expect("Failed to set QUIC client certificates");
         crypto.enable_early_data = true;
-        crypto.alpn_protocols = vec![ALPN_TPU_PROTOCOL_ID.to_vec()];
+        crypto.alpn_protocols = if enable_zstd_batches {
+            // falls back to the standard protocol if the server doesn't support the compressed one
+            vec![
+                ALPN_TPU_ZSTD_PROTOCOL_ID.to_vec(),
+                ALPN_TPU_PROTOCOL_ID.to_vec(),
+            ]
+        } else {
+            vec![ALPN_TPU_PROTOCOL_ID.to_vec()]
+        };
 
         let mut config = ClientConfig::new(Arc::new(crypto));
         let mut transport_config = TransportConfig::default();
@@ -131,11 +277,17 @@ impl QuicLazyInitializedEndpoint {
         let timeout = IdleTimeout::try_from(QUIC_MAX_TIMEOUT).unwrap();
         transport_config.max_idle_timeout(Some(timeout));
         transport_config.keep_alive_interval(Some(QUIC_KEEP_ALIVE));
+        if let Some(send_window) = self.send_window {
+            transport_config.send_window(send_window);
+        }
+        if let Some(pacing_rate) = pacing_rate {
+            transport_config.congestion_controller_factory(Arc::new(
+                PacingRateControllerFactory::new(pacing_rate),
+            ));
+        }
         config.transport_config(Arc::new(transport_config));
 
-        endpoint.set_default_client_config(config);
-
-        endpoint
+        config
     }
 
     async fn get_endpoint(&self) -> Arc<Endpoint> {
@@ -144,6 +296,14 @@ impl QuicLazyInitializedEndpoint {
             .await
             .clone()
     }
+
+    /// Waits for the closed connections of the endpoint to be fully closed with the servers, if the
+    /// endpoint has been initialized.
+    pub async fn wait_idle(&self) {
+        if let Some(endpoint) = self.endpoint.get() {
+            endpoint.wait_idle().await;
+        }
+    }
 }
 
 impl Default for QuicLazyInitializedEndpoint {
@@ -165,6 +325,8 @@ impl Default for QuicLazyInitializedEndpoint {
 struct QuicNewConnection {
     endpoint: Arc<Endpoint>,
     connection: Arc<Connection>,
+    // overrides the endpoint's default client config if specified
+    client_config: Option<ClientConfig>,
 }
 
 impl QuicNewConnection {
@@ -173,11 +335,12 @@ impl QuicNewConnection {
         endpoint: Arc<QuicLazyInitializedEndpoint>,
         addr: SocketAddr,
         stats: &ClientStats,
+        client_config: Option<ClientConfig>,
     ) -> Result<Self, QuicError> {
         let mut make_connection_measure = Measure::start("make_connection_measure");
         let endpoint = endpoint.get_endpoint().await;
 
-        let connecting = endpoint.connect(addr, "connect")?;
+        let connecting = Self::connect(&endpoint, addr, client_config.as_ref())?;
         stats.total_connections.fetch_add(1, Ordering::Relaxed);
         if let Ok(connecting_result) = timeout(QUIC_CONNECTION_HANDSHAKE_TIMEOUT, connecting).await
         {
@@ -194,12 +357,24 @@ impl QuicNewConnection {
             Ok(Self {
                 endpoint,
                 connection: Arc::new(connection),
+                client_config,
             })
         } else {
             Err(ConnectionError::TimedOut.into())
         }
     }
 
+    fn connect(
+        endpoint: &Endpoint,
+        addr: SocketAddr,
+        client_config: Option<&ClientConfig>,
+    ) -> Result<Connecting, ConnectError> {
+        match client_config {
+            Some(client_config) => endpoint.connect_with(client_config.clone(), addr, "connect"),
+            None => endpoint.connect(addr, "connect"),
+        }
+    }
+
     fn create_endpoint(config: EndpointConfig, client_socket: UdpSocket) -> Endpoint {
         quinn::Endpoint::new(config, None, client_socket, Arc::new(TokioRuntime))
             .expect("QuicNewConnection::create_endpoint quinn::Endpoint::new")
@@ -212,7 +387,7 @@ impl QuicNewConnection {
         addr: SocketAddr,
         stats: &ClientStats,
     ) -> Result<Arc<Connection>, QuicError> {
-        let connecting = self.endpoint.connect(addr, "connect")?;
+        let connecting = Self::connect(&self.endpoint, addr, self.client_config.as_ref())?;
         stats.total_connections.fetch_add(1, Ordering::Relaxed);
         let connection = match connecting.into_0rtt() {
             Ok((connection, zero_rtt)) => {
@@ -250,6 +425,25 @@ pub struct QuicClient {
     addr: SocketAddr,
     stats: Arc<ClientStats>,
     chunk_size: usize,
+    // How many times a batch send re-establishes the connection to resend the unsent remainder of
+    // the batch, after the connection is lost in the middle of it
+    batch_send_retries: usize,
+    // The cap of the sending rate in bytes per second, if any
+    pacing_rate: Option<u64>,
+    // The client config enforcing pacing_rate. Retained so that 0rtt can resume the session of
+    // the previous connection.
+    client_config: Option<ClientConfig>,
+    // The client config without ALPN_TPU_ZSTD_PROTOCOL_ID, used for the following connections once
+    // the server has refused compressed batches from this client, e.g. because it isn't staked
+    fallback_client_config: OnceLock<ClientConfig>,
+    // Holds back the streams of all sends at the credit of the server and the client-side cap, if
+    // any. Otherwise, each batch opens up to chunk_size streams at once regardless of the other
+    // sends.
+    stream_cap: StreamCap,
+    // Set by close(), after which no send is accepted
+    closed: AtomicBool,
+    // The per-peer stats of the server, retained so that sends don't look up the peer
+    peer_stats: RwLock<Option<Arc<PeerStatsCounters>>>,
 }
 
 impl QuicClient {
@@ -258,26 +452,132 @@ impl QuicClient {
         addr: SocketAddr,
         chunk_size: usize,
     ) -> Self {
+        Self::new_with_batch_send_retries(endpoint, addr, chunk_size, 0)
+    }
+
+    pub fn new_with_batch_send_retries(
+        endpoint: Arc<QuicLazyInitializedEndpoint>,
+        addr: SocketAddr,
+        chunk_size: usize,
+        batch_send_retries: usize,
+    ) -> Self {
+        Self::new_with_pacing_rate(endpoint, addr, chunk_size, batch_send_retries, None)
+    }
+
+    /// `pacing_rate` caps the sending rate of the connection in bytes per second, which callers
+    /// may derive from their stake or configured limits to avoid being throttled by the server.
+    pub fn new_with_pacing_rate(
+        endpoint: Arc<QuicLazyInitializedEndpoint>,
+        addr: SocketAddr,
+        chunk_size: usize,
+        batch_send_retries: usize,
+        pacing_rate: Option<u64>,
+    ) -> Self {
+        Self::new_with_stream_cap(
+            endpoint,
+            addr,
+            chunk_size,
+            batch_send_retries,
+            pacing_rate,
+            None,
+        )
+    }
+
+    /// `max_concurrent_streams` caps the concurrent streams of all sends sharing the client on top
+    /// of the stream credit of the server, which is tracked regardless. See [`StreamCap`].
+    pub fn new_with_stream_cap(
+        endpoint: Arc<QuicLazyInitializedEndpoint>,
+        addr: SocketAddr,
+        chunk_size: usize,
+        batch_send_retries: usize,
+        pacing_rate: Option<u64>,
+        max_concurrent_streams: Option<usize>,
+    ) -> Self {
+        let client_config =
+            pacing_rate.map(|pacing_rate| endpoint.create_client_config(Some(pacing_rate)));
+        let stream_cap = StreamCap::new_with_send_window(
+            max_concurrent_streams.unwrap_or(UNCAPPED_STREAMS),
+            endpoint.send_window(),
+        );
         Self {
             endpoint,
             connection: Arc::new(Mutex::new(None)),
             addr,
             stats: Arc::new(ClientStats::default()),
             chunk_size,
+            batch_send_retries,
+            pacing_rate,
+            client_config,
+            fallback_client_config: OnceLock::new(),
+            stream_cap,
+            closed: AtomicBool::default(),
+            peer_stats: RwLock::default(),
         }
     }
 
     async fn _send_buffer_using_conn(
         data: &[u8],
         connection: &Connection,
+        stream_cap: &StreamCap,
     ) -> Result<(), QuicError> {
-        let mut send_stream = connection.open_uni().await?;
+        if Self::is_zstd_batch_connection(connection) {
+            // every stream must be a compressed batch once the compressed protocol is negotiated
+            return Self::_send_zstd_batch_using_conn(&[data], connection, stream_cap).await;
+        }
+        let (_stream_permit, mut send_stream) = stream_cap.open_uni(connection).await?;
 
         send_stream.write_all(data).await?;
         send_stream.finish().await?;
         Ok(())
     }
 
+    fn is_zstd_batch_connection(connection: &Connection) -> bool {
+        get_alpn_protocol(connection).as_deref() == Some(ALPN_TPU_ZSTD_PROTOCOL_ID)
+    }
+
+    async fn _send_zstd_batch_using_conn<T: AsRef<[u8]>>(
+        buffers: &[T],
+        connection: &Connection,
+        stream_cap: &StreamCap,
+    ) -> Result<(), QuicError> {
+        let data = encode_zstd_batch(buffers).map_err(QuicError::ZstdBatchError)?;
+        let (_stream_permit, mut send_stream) = stream_cap.open_uni(connection).await?;
+
+        send_stream.write_all(&data).await?;
+        send_stream.finish().await?;
+        Ok(())
+    }
+
+    // Sends the buffers chunk by chunk over the given connection. On failure, returns the offset
+    // of the first chunk which isn't known to be fully sent, along with the error.
+    async fn _send_buffers_using_conn<T: AsRef<[u8]>>(
+        buffers: &[T],
+        connection: &Connection,
+        chunk_size: usize,
+        stream_cap: &StreamCap,
+    ) -> Result<(), (usize, QuicError)> {
+        if Self::is_zstd_batch_connection(connection) {
+            for (batch_index, batch) in buffers.chunks(MAX_ZSTD_BATCH_PACKETS).enumerate() {
+                Self::_send_zstd_batch_using_conn(batch, connection, stream_cap)
+                    .await
+                    .map_err(|err| (batch_index * MAX_ZSTD_BATCH_PACKETS, err))?;
+            }
+            return Ok(());
+        }
+        for (chunk_index, chunk) in buffers.chunks(chunk_size).enumerate() {
+            join_all(
+                chunk
+                    .iter()
+                    .map(|buf| Self::_send_buffer_using_conn(buf.as_ref(), connection, stream_cap)),
+            )
+            .await
+            .into_iter()
+            .try_for_each(|res| res)
+            .map_err(|err| (chunk_index * chunk_size, err))?;
+        }
+        Ok(())
+    }
+
     // Attempts to send data, connecting/reconnecting as necessary
     // On success, returns the connection used to successfully send the data
     async fn _send_buffer(
@@ -286,6 +586,9 @@ impl QuicClient {
         stats: &ClientStats,
         connection_stats: Arc<ConnectionCacheStats>,
     ) -> Result<Arc<Connection>, QuicError> {
+        if self.is_closed() {
+            return Err(QuicError::ClientClosed);
+        }
         let mut measure_send_packet = Measure::start("send_packet_us");
         let mut measure_prepare_connection = Measure::start("prepare_connection");
         let mut connection_try_count = 0;
@@ -300,9 +603,16 @@ impl QuicClient {
                     Some(conn) => {
                         if conn.connection.stable_id() == last_connection_id {
                             // this is the problematic connection we had used before, create a new one
+                            let mut measure_handshake = Measure::start("handshake");
                             let conn = conn.make_connection_0rtt(self.addr, stats).await;
+                            measure_handshake.stop();
                             match conn {
                                 Ok(conn) => {
+                                    self.add_peer_handshake(
+                                        &conn,
+                                        &connection_stats,
+                                        measure_handshake.as_us(),
+                                    );
                                     info!(
                                         "Made 0rtt connection to {} with id {} try_count {}, last_connection_id: {}, last_error: {:?}",
                                         self.addr,
@@ -328,14 +638,22 @@ impl QuicClient {
                         }
                     }
                     None => {
+                        let mut measure_handshake = Measure::start("handshake");
                         let conn = QuicNewConnection::make_connection(
                             self.endpoint.clone(),
                             self.addr,
                             stats,
+                            self.client_config(),
                         )
                         .await;
+                        measure_handshake.stop();
                         match conn {
                             Ok(conn) => {
+                                self.add_peer_handshake(
+                                    &conn.connection,
+                                    &connection_stats,
+                                    measure_handshake.as_us(),
+                                );
                                 *conn_guard = Some(conn.clone());
                                 info!(
                                     "Made connection to {} id {} try_count {}, from connection cache warming?: {}",
@@ -393,7 +711,7 @@ impl QuicClient {
             last_connection_id = connection.stable_id();
             measure_prepare_connection.stop();
 
-            match Self::_send_buffer_using_conn(data, &connection).await {
+            match Self::_send_buffer_using_conn(data, &connection, &self.stream_cap).await {
                 Ok(()) => {
                     measure_send_packet.stop();
                     stats.successful_packets.fetch_add(1, Ordering::Relaxed);
@@ -415,21 +733,28 @@ impl QuicClient {
 
                     return Ok(connection);
                 }
-                Err(err) => match err {
-                    QuicError::ConnectionError(_) => {
+                Err(err) => {
+                    if self.fall_back_if_zstd_batch_disallowed(&connection).await {
+                        // reconnect with the standard protocol and resend
                         last_error = Some(err);
+                        continue;
                     }
-                    _ => {
-                        info!(
-                            "Error sending to {} with id {}, error {:?} thread: {:?}",
-                            self.addr,
-                            connection.stable_id(),
-                            err,
-                            thread::current().id(),
-                        );
-                        return Err(err);
+                    match err {
+                        QuicError::ConnectionError(_) => {
+                            last_error = Some(err);
+                        }
+                        _ => {
+                            info!(
+                                "Error sending to {} with id {}, error {:?} thread: {:?}",
+                                self.addr,
+                                connection.stable_id(),
+                                err,
+                                thread::current().id(),
+                            );
+                            return Err(err);
+                        }
                     }
-                },
+                }
             }
         }
 
@@ -477,48 +802,238 @@ impl QuicClient {
         // since testing even in the ideal GCE environment has found no cases
         // where reconnecting and retrying in the middle of a batch send
         // (i.e. we encounter a connection error in the middle of a batch send, which presumably cannot
-        // be due to a timed out connection) has succeeded
+        // be due to a timed out connection) has succeeded.
+        // Still, callers can opt into a bounded number of such retries with batch_send_retries, in
+        // which case the unsent remainder of the batch is resent over a re-established connection.
+        // Note that resending isn't strictly idempotent: the chunk which was being sent when the
+        // connection was lost is resent as a whole, so some of its buffers may be delivered twice.
+        // This is harmless for transactions, which are deduplicated by the server.
         if buffers.is_empty() {
             return Ok(());
         }
-        let connection = self
-            ._send_buffer(buffers[0].as_ref(), stats, connection_stats)
+        let mut connection = self
+            ._send_buffer(buffers[0].as_ref(), stats, connection_stats.clone())
             .await
             .map_err(Into::<ClientErrorKind>::into)?;
 
-        // Used to avoid dereferencing the Arc multiple times below
-        // by just getting a reference to the NewConnection once
-        let connection_ref: &Connection = &connection;
+        let mut unsent_buffers = &buffers[1..];
+        let mut retries_left = self.batch_send_retries;
+        loop {
+            let (unsent_offset, err) = match Self::_send_buffers_using_conn(
+                unsent_buffers,
+                &connection,
+                self.chunk_size,
+                &self.stream_cap,
+            )
+            .await
+            {
+                Ok(()) => return Ok(()),
+                Err(offset_and_err) => offset_and_err,
+            };
+            unsent_buffers = &unsent_buffers[unsent_offset..];
 
-        let chunks = buffers[1..buffers.len()].iter().chunks(self.chunk_size);
+            // the streams in flight when the connection is lost fail to be written, rather than
+            // to be opened
+            let is_connection_lost = matches!(
+                err,
+                QuicError::ConnectionError(_)
+                    | QuicError::WriteError(WriteError::ConnectionLost(_))
+            );
+            if retries_left == 0 || !is_connection_lost {
+                if self.batch_send_retries > 0 {
+                    stats
+                        .batch_lost_packets
+                        .fetch_add(unsent_buffers.len() as u64, Ordering::Relaxed);
+                }
+                return Err(err.into());
+            }
+            retries_left -= 1;
 
-        let futures: Vec<_> = chunks
-            .into_iter()
-            .map(|buffs| {
-                join_all(
-                    buffs
-                        .into_iter()
-                        .map(|buf| Self::_send_buffer_using_conn(buf.as_ref(), connection_ref)),
-                )
-            })
-            .collect();
+            info!(
+                "Connection to {} was lost in the middle of a batch send with {} unsent buffers, \
+                 retrying: {:?}",
+                self.addr,
+                unsent_buffers.len(),
+                err,
+            );
+            stats
+                .batch_retried_packets
+                .fetch_add(unsent_buffers.len() as u64, Ordering::Relaxed);
+            // _send_buffer() re-establishes the connection, because the current one is broken
+            connection = match self
+                ._send_buffer(unsent_buffers[0].as_ref(), stats, connection_stats.clone())
+                .await
+            {
+                Ok(connection) => connection,
+                Err(err) => {
+                    stats
+                        .batch_lost_packets
+                        .fetch_add(unsent_buffers.len() as u64, Ordering::Relaxed);
+                    return Err(err.into());
+                }
+            };
+            unsent_buffers = &unsent_buffers[1..];
+        }
+    }
+
+    /// Opens a uni stream to the server, connecting or reconnecting as necessary, and leaves
+    /// writing it to the caller. Unlike the sends, the stream isn't subject to
+    /// [`Self::stream_cap`], so that tools can exercise the stream limits and timeouts of
+    /// servers with direct control over the writes.
+    pub async fn open_uni_stream(
+        &self,
+        stats: &ClientStats,
+        connection_stats: Arc<ConnectionCacheStats>,
+    ) -> Result<SendStream, ClientErrorKind> {
+        let connection = self
+            ._send_buffer(&[], stats, connection_stats.clone())
+            .await?;
+        match connection.open_uni().await {
+            Ok(send_stream) => return Ok(send_stream),
+            Err(err) => info!(
+                "Cannot open stream to {} with id {}, reconnecting: {:?}",
+                self.addr,
+                connection.stable_id(),
+                err
+            ),
+        }
+        {
+            // connecting with an empty buffer reuses the current connection as is, so drop it
+            // unless another caller has already replaced it
+            let mut conn_guard = self.connection.lock().await;
+            if conn_guard.as_ref().map(|conn| conn.connection.stable_id())
+                == Some(connection.stable_id())
+            {
+                *conn_guard = None;
+            }
+        }
+        let connection = self._send_buffer(&[], stats, connection_stats).await?;
+        Ok(connection.open_uni().await.map_err(QuicError::from)?)
+    }
 
-        for f in futures {
-            f.await
-                .into_iter()
-                .try_for_each(|res| res)
-                .map_err(Into::<ClientErrorKind>::into)?;
+    fn client_config(&self) -> Option<ClientConfig> {
+        self.fallback_client_config
+            .get()
+            .or(self.client_config.as_ref())
+            .cloned()
+    }
+
+    // Falls back to ALPN_TPU_PROTOCOL_ID for the following connections if the server has closed
+    // the connection because it doesn't accept compressed batches from this client, dropping the
+    // connection so that the next send reconnects. Returns true if so.
+    async fn fall_back_if_zstd_batch_disallowed(&self, connection: &Connection) -> bool {
+        match connection.close_reason() {
+            Some(ConnectionError::ApplicationClosed(close))
+                if close.error_code
+                    == VarInt::from_u32(CONNECTION_CLOSE_CODE_ZSTD_BATCH_DISALLOWED) => {}
+            _ => return false,
         }
-        Ok(())
+        self.fallback_client_config.get_or_init(|| {
+            info!(
+                "{} disallows zstd batches, falling back to the standard protocol",
+                self.addr
+            );
+            self.endpoint
+                .create_client_config_with_zstd_batches(self.pacing_rate, false)
+        });
+        let mut conn_guard = self.connection.lock().await;
+        if conn_guard.as_ref().map(|conn| conn.connection.stable_id())
+            == Some(connection.stable_id())
+        {
+            *conn_guard = None;
+        }
+        true
     }
 
     pub fn server_addr(&self) -> &SocketAddr {
         &self.addr
     }
 
+    // Attributes the server's stats to the identity presented by its certificate from now on, if
+    // any.
+    fn add_peer_handshake(
+        &self,
+        connection: &Connection,
+        connection_stats: &ConnectionCacheStats,
+        handshake_us: u64,
+    ) {
+        let peer_key =
+            get_remote_pubkey(connection).map_or(PeerKey::Addr(self.addr), PeerKey::Identity);
+        let peer_stats = connection_stats.peer_stats(peer_key);
+        peer_stats.add_handshake(handshake_us);
+        *self.peer_stats.write().unwrap() = Some(peer_stats);
+    }
+
+    fn add_peer_send(
+        &self,
+        connection_stats: &ConnectionCacheStats,
+        num_packets: usize,
+        num_bytes: usize,
+        is_success: bool,
+    ) {
+        if let Some(peer_stats) = self.peer_stats.read().unwrap().as_ref() {
+            peer_stats.add_send(num_packets, num_bytes, is_success);
+            return;
+        }
+        // no handshake has succeeded yet, so the server's identity is unknown
+        self.peer_stats
+            .write()
+            .unwrap()
+            .get_or_insert_with(|| connection_stats.peer_stats(PeerKey::Addr(self.addr)))
+            .add_send(num_packets, num_bytes, is_success);
+    }
+
     pub fn stats(&self) -> Arc<ClientStats> {
         self.stats.clone()
     }
+
+    /// Returns the limiter of the concurrent streams, which callers can query or await before
+    /// initiating sends, e.g. to see the stream credit of the server.
+    pub fn stream_cap(&self) -> &StreamCap {
+        &self.stream_cap
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+
+    /// Closes the client gracefully: stops accepting new sends, waits up to `drain_timeout` for
+    /// the in-flight streams to be written, and then closes the connection with
+    /// [`CONNECTION_CLOSE_CODE_CLIENT_SHUTDOWN`], so that the server doesn't see truncated
+    /// streams. Returns whether all of the in-flight streams have been finished in time.
+    pub async fn close(&self, drain_timeout: Duration) -> bool {
+        self.closed.store(true, Ordering::Relaxed);
+        let drained = self.stream_cap.close(drain_timeout).await;
+        if !drained {
+            info!(
+                "Closing the connection to {} with unfinished streams after {:?}",
+                self.addr, drain_timeout
+            );
+        }
+        if let Some(conn) = self.connection.lock().await.take() {
+            conn.connection.close(
+                CONNECTION_CLOSE_CODE_CLIENT_SHUTDOWN.into(),
+                CONNECTION_CLOSE_REASON_CLIENT_SHUTDOWN,
+            );
+        }
+        drained
+    }
+
+    /// Returns the configured cap of the sending rate in bytes per second.
+    pub fn pacing_rate(&self) -> Option<u64> {
+        self.pacing_rate
+    }
+
+    /// Returns the rate in bytes per second which the current connection can send at, as
+    /// determined by its congestion window and round trip time. This is lower than
+    /// [`Self::pacing_rate`] under congestion. Returns `None` if not connected yet.
+    pub async fn effective_pacing_rate(&self) -> Option<u64> {
+        let conn_guard = self.connection.lock().await;
+        let connection = &conn_guard.as_ref()?.connection;
+        let path_stats = connection.stats().path;
+        let rtt = connection.rtt().max(Duration::from_micros(1));
+        Some((u128::from(path_stats.cwnd) * 1_000_000_000 / rtt.as_nanos()) as u64)
+    }
 }
 
 pub struct QuicClientConnection {
@@ -574,6 +1089,12 @@ impl ClientConnection for QuicClientConnection {
             .await;
         self.connection_stats
             .add_client_stats(&stats, len, res.is_ok());
+        self.client.add_peer_send(
+            &self.connection_stats,
+            len,
+            buffers.iter().map(Vec::len).sum(),
+            res.is_ok(),
+        );
         res?;
         Ok(())
     }
@@ -587,6 +1108,8 @@ impl ClientConnection for QuicClientConnection {
             .map_ok(|v| {
                 self.connection_stats
                     .add_client_stats(&stats, num_packets, true);
+                self.client
+                    .add_peer_send(&self.connection_stats, num_packets, data.len(), true);
                 v
             })
             .map_err(|e| {
@@ -598,6 +1121,8 @@ impl ClientConnection for QuicClientConnection {
                 datapoint_warn!("send-wire-async", ("failure", 1, i64),);
                 self.connection_stats
                     .add_client_stats(&stats, num_packets, false);
+                self.client
+                    .add_peer_send(&self.connection_stats, num_packets, data.len(), false);
                 e.into()
             })
             .await
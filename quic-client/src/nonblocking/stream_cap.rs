@@ -0,0 +1,362 @@
+//! A cooperative limiter of the concurrent uni streams of a connection, shared by all of its
+//! senders, so that they can query or await the availability of a stream before initiating
+//! sends, instead of stalling in quinn without telling whether a send is slow or blocked.
+//!
+//! Streams are held back by two limits:
+//!
+//! - The stream credit which the server of the current connection grants. quinn doesn't expose
+//!   the peer's `max_streams` limit, and servers adjust it after the handshake anyway (e.g. the
+//!   streamer computes it from the stake of the client). So, it's learned instead: once
+//!   `open_uni()` can't open a stream right away, the credit is exhausted by the streams already
+//!   in flight on the connection, whose count is then retained as the server's limit until the
+//!   client reconnects.
+//! - An optional client-side cap (e.g. as configured by `QuicConfig::set_max_concurrent_streams`)
+//!   across connections. Uncapped by default.
+//!
+//! As every stream holds a permit until it's finished, the cap also tells when all of the
+//! in-flight streams have been written, which is used to drain a connection before closing it.
+
+use {
+    crate::nonblocking::quic_client::QuicError,
+    futures::poll,
+    quinn::{Connection, SendStream},
+    std::{
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Mutex,
+        },
+        task::Poll,
+        time::Duration,
+    },
+    tokio::{
+        sync::{Notify, Semaphore, SemaphorePermit},
+        time::timeout,
+    },
+};
+
+// The most permits which the semaphore can hold on any platform, which close() can also acquire
+// at once.
+pub(crate) const UNCAPPED_STREAMS: usize = (u32::MAX >> 3) as usize;
+
+// The stream credit of the server of the current connection, as observed so far.
+#[derive(Debug, Default)]
+struct PeerStreamLimits {
+    connection: Option<Connection>,
+    // the streams which have been reserved on the connection and aren't finished yet
+    in_flight: usize,
+    // the most concurrent streams which the server has been observed to grant, if exhausted ever
+    max_streams: Option<usize>,
+}
+
+impl PeerStreamLimits {
+    fn has_credit(&self) -> bool {
+        self.max_streams
+            .map_or(true, |max_streams| self.in_flight < max_streams)
+    }
+}
+
+#[derive(Debug)]
+pub struct StreamCap {
+    max_streams: usize,
+    semaphore: Semaphore,
+    peer_limits: Mutex<PeerStreamLimits>,
+    // notified whenever a stream reserved on the current connection is finished
+    peer_stream_finished: Notify,
+    // the per-connection send window configured for the connections, if not quinn's default
+    send_window: Option<u64>,
+    // the number of streams which had to wait for another stream to finish
+    throttled_stream_count: AtomicU64,
+    // the number of streams which the server didn't grant right away
+    stream_limited_count: AtomicU64,
+}
+
+/// A stream reserved by [`StreamCap::open_uni`], which is released when dropped.
+pub(crate) struct StreamPermit<'a> {
+    _permit: SemaphorePermit<'a>,
+    cap: &'a StreamCap,
+    // the connection whose server credit the stream has been reserved from, if any
+    connection_id: Option<usize>,
+}
+
+impl Drop for StreamPermit<'_> {
+    fn drop(&mut self) {
+        if let Some(connection_id) = self.connection_id {
+            self.cap.release_peer_stream(connection_id);
+        }
+    }
+}
+
+impl StreamCap {
+    pub fn new(max_streams: usize) -> Self {
+        Self::new_with_send_window(max_streams, None)
+    }
+
+    /// `send_window` is the per-connection send window which the connections are configured
+    /// with, only to be reported by [`Self::send_window`].
+    pub fn new_with_send_window(max_streams: usize, send_window: Option<u64>) -> Self {
+        assert!(max_streams > 0);
+        Self {
+            max_streams,
+            semaphore: Semaphore::new(max_streams),
+            peer_limits: Mutex::default(),
+            peer_stream_finished: Notify::new(),
+            send_window,
+            throttled_stream_count: AtomicU64::default(),
+            stream_limited_count: AtomicU64::default(),
+        }
+    }
+
+    /// Doesn't hold back any stream on the client side, only tracking the in-flight ones for
+    /// draining and the credit of the server.
+    pub fn uncapped() -> Self {
+        Self::new(UNCAPPED_STREAMS)
+    }
+
+    pub fn max_streams(&self) -> usize {
+        self.max_streams
+    }
+
+    /// Returns the most concurrent streams which the server of the current connection has been
+    /// observed to grant. `None` until the credit of the connection is exhausted for the first
+    /// time.
+    pub fn peer_max_streams(&self) -> Option<usize> {
+        self.peer_limits.lock().unwrap().max_streams
+    }
+
+    /// Returns the per-connection send window which the connections are configured with, i.e.
+    /// the most bytes which can be in flight on a connection. `None` if it's quinn's default.
+    pub fn send_window(&self) -> Option<u64> {
+        self.send_window
+    }
+
+    pub fn in_flight_streams(&self) -> usize {
+        self.max_streams - self.semaphore.available_permits()
+    }
+
+    /// Returns the number of streams which can be opened right away, within both the client-side
+    /// cap and the observed credit of the server.
+    pub fn available_streams(&self) -> usize {
+        let available_streams = self.semaphore.available_permits();
+        let peer_limits = self.peer_limits.lock().unwrap();
+        peer_limits
+            .max_streams
+            .map_or(available_streams, |max_streams| {
+                available_streams.min(max_streams.saturating_sub(peer_limits.in_flight))
+            })
+    }
+
+    pub fn throttled_stream_count(&self) -> u64 {
+        self.throttled_stream_count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of streams which exhausted the credit of the server, having to wait
+    /// for it to grant more.
+    pub fn stream_limited_count(&self) -> u64 {
+        self.stream_limited_count.load(Ordering::Relaxed)
+    }
+
+    /// Waits until a stream is available, without reserving it. Senders can await this before
+    /// initiating sends, so that they don't pile up on the connection.
+    pub async fn wait_for_available_stream(&self) {
+        let Some(_permit) = self.acquire().await else {
+            return;
+        };
+        loop {
+            let finished = self.peer_stream_finished.notified();
+            if self.peer_limits.lock().unwrap().has_credit() {
+                return;
+            }
+            finished.await;
+        }
+    }
+
+    /// Reserves a stream until the returned permit is dropped. Returns `None` once closed.
+    pub(crate) async fn acquire(&self) -> Option<SemaphorePermit<'_>> {
+        if let Ok(permit) = self.semaphore.try_acquire() {
+            return Some(permit);
+        }
+        if self.is_closed() {
+            return None;
+        }
+        self.throttled_stream_count.fetch_add(1, Ordering::Relaxed);
+        self.semaphore.acquire().await.ok()
+    }
+
+    /// Reserves a stream within the limits and opens it on `connection`. The stream is released
+    /// when the returned permit is dropped, which should be after the stream is finished.
+    pub(crate) async fn open_uni<'a>(
+        &'a self,
+        connection: &Connection,
+    ) -> Result<(StreamPermit<'a>, SendStream), QuicError> {
+        let permit = self.acquire().await.ok_or(QuicError::ClientClosed)?;
+        let connection_id = self
+            .reserve_peer_stream(connection)
+            .await
+            .then(|| connection.stable_id());
+        let permit = StreamPermit {
+            _permit: permit,
+            cap: self,
+            connection_id,
+        };
+
+        let open_uni = connection.open_uni();
+        tokio::pin!(open_uni);
+        let send_stream = match poll!(open_uni.as_mut()) {
+            Poll::Ready(send_stream) => send_stream?,
+            Poll::Pending => {
+                if let Some(connection_id) = connection_id {
+                    self.record_peer_max_streams(connection_id);
+                }
+                open_uni.await?
+            }
+        };
+        Ok((permit, send_stream))
+    }
+
+    // Waits until the server of `connection` is expected to grant another stream, and reserves
+    // it. Returns false if the connection has been replaced already, in which case the stream
+    // isn't held back, as it will fail anyway.
+    async fn reserve_peer_stream(&self, connection: &Connection) -> bool {
+        let mut is_throttled = false;
+        loop {
+            let finished = self.peer_stream_finished.notified();
+            {
+                let mut peer_limits = self.peer_limits.lock().unwrap();
+                match &peer_limits.connection {
+                    Some(current) if current.stable_id() == connection.stable_id() => {}
+                    // the client has reconnected, possibly to a server with another credit
+                    None => peer_limits.connection = Some(connection.clone()),
+                    Some(current) if current.close_reason().is_some() => {
+                        *peer_limits = PeerStreamLimits {
+                            connection: Some(connection.clone()),
+                            ..PeerStreamLimits::default()
+                        };
+                        self.peer_stream_finished.notify_waiters();
+                    }
+                    Some(_) => return false,
+                }
+                if peer_limits.has_credit() {
+                    peer_limits.in_flight += 1;
+                    return true;
+                }
+            }
+            if !is_throttled {
+                is_throttled = true;
+                self.throttled_stream_count.fetch_add(1, Ordering::Relaxed);
+            }
+            finished.await;
+        }
+    }
+
+    fn release_peer_stream(&self, connection_id: usize) {
+        let mut peer_limits = self.peer_limits.lock().unwrap();
+        if peer_limits
+            .connection
+            .as_ref()
+            .is_some_and(|connection| connection.stable_id() == connection_id)
+        {
+            peer_limits.in_flight -= 1;
+            drop(peer_limits);
+            self.peer_stream_finished.notify_waiters();
+        }
+    }
+
+    // Called once the credit of the server is exhausted by the streams in flight other than the
+    // one being opened.
+    fn record_peer_max_streams(&self, connection_id: usize) {
+        self.stream_limited_count.fetch_add(1, Ordering::Relaxed);
+        let mut peer_limits = self.peer_limits.lock().unwrap();
+        if peer_limits
+            .connection
+            .as_ref()
+            .is_some_and(|connection| connection.stable_id() == connection_id)
+        {
+            let max_streams = peer_limits.in_flight.saturating_sub(1).max(1);
+            peer_limits.max_streams = Some(
+                peer_limits
+                    .max_streams
+                    .map_or(max_streams, |current| current.min(max_streams)),
+            );
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.semaphore.is_closed()
+    }
+
+    /// Waits up to `drain_timeout` for the streams reserved so far to be finished, and then stops
+    /// reserving streams. Returns whether all of them have been finished in time.
+    ///
+    /// The streams which are already waiting for a reservation are still served before the
+    /// draining completes, because the semaphore is fair.
+    pub(crate) async fn close(&self, drain_timeout: Duration) -> bool {
+        let max_streams = u32::try_from(self.max_streams).unwrap_or(u32::MAX);
+        let drained = match timeout(drain_timeout, self.semaphore.acquire_many(max_streams)).await {
+            Ok(Ok(permits)) => {
+                permits.forget();
+                true
+            }
+            // already closed by another caller
+            Ok(Err(_)) => true,
+            Err(_) => false,
+        };
+        self.semaphore.close();
+        drained
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, futures::future::join_all, std::time::Duration, tokio::time::timeout};
+
+    #[tokio::test]
+    async fn test_stream_cap() {
+        let cap = StreamCap::new(2);
+        assert_eq!(cap.available_streams(), 2);
+
+        let permit1 = cap.acquire().await.unwrap();
+        let _permit2 = cap.acquire().await.unwrap();
+        assert_eq!(cap.available_streams(), 0);
+        assert_eq!(cap.throttled_stream_count(), 0);
+        assert!(
+            timeout(Duration::from_millis(10), cap.wait_for_available_stream())
+                .await
+                .is_err()
+        );
+        assert_eq!(cap.throttled_stream_count(), 1);
+
+        drop(permit1);
+        cap.wait_for_available_stream().await;
+        // waiting doesn't reserve the stream
+        assert_eq!(cap.available_streams(), 1);
+        assert_eq!(cap.in_flight_streams(), 1);
+
+        let cap = StreamCap::uncapped();
+        let _permits: Vec<_> = join_all((0..100).map(|_| cap.acquire())).await;
+        assert_eq!(cap.in_flight_streams(), 100);
+        assert_eq!(cap.throttled_stream_count(), 0);
+        // nothing is known about the server without a connection
+        assert_eq!(cap.peer_max_streams(), None);
+        assert_eq!(cap.stream_limited_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_stream_cap_close() {
+        let cap = StreamCap::new(2);
+
+        let permit = cap.acquire().await.unwrap();
+        let (drained, ()) = tokio::join!(cap.close(Duration::from_secs(10)), async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            drop(permit);
+        });
+        assert!(drained);
+        assert!(cap.is_closed());
+        assert!(cap.acquire().await.is_none());
+
+        // gives up on the streams which aren't finished in time
+        let cap = StreamCap::new(2);
+        let _permit = cap.acquire().await.unwrap();
+        assert!(!cap.close(Duration::from_millis(10)).await);
+        assert!(cap.acquire().await.is_none());
+    }
+}
@@ -2,9 +2,14 @@
 //! an interface for sending data which is restricted by the server's flow control.
 
 use {
-    crate::nonblocking::quic_client::{
-        QuicClient, QuicClientConnection as NonblockingQuicConnection, QuicLazyInitializedEndpoint,
+    crate::{
+        nonblocking::quic_client::{
+            QuicClient, QuicClientConnection as NonblockingQuicConnection,
+            QuicLazyInitializedEndpoint,
+        },
+        QuicPool,
     },
+    futures::future::join_all,
     lazy_static::lazy_static,
     log::*,
     solana_connection_cache::{
@@ -75,6 +80,15 @@ lazy_static! {
         .unwrap();
 }
 
+/// Closes the connections of all of the pools concurrently, blocking until done. See
+/// [`QuicPool::close`]. Returns whether all of the in-flight streams have been finished in time.
+pub fn close_pools(pools: &[QuicPool], drain_timeout: Duration) -> bool {
+    RUNTIME
+        .block_on(join_all(pools.iter().map(|pool| pool.close(drain_timeout))))
+        .into_iter()
+        .all(|drained| drained)
+}
+
 async fn send_data_async(
     connection: Arc<NonblockingQuicConnection>,
     buffer: Vec<u8>,
@@ -109,6 +109,118 @@ mod tests {
         t.join().unwrap();
     }
 
+    #[test]
+    fn test_quic_client_zstd_batches() {
+        use {
+            solana_connection_cache::client_connection::ClientConnection,
+            solana_quic_client::quic_client::QuicClientConnection, solana_sdk::signature::Signer,
+            std::collections::HashMap,
+        };
+        solana_logger::setup();
+        let (sender, receiver) = unbounded();
+        // compressed batches are accepted only from staked peers
+        let client_keypair = Keypair::new();
+        let staked_nodes = Arc::new(RwLock::new(StakedNodes::new(
+            Arc::new(HashMap::from([(client_keypair.pubkey(), 100)])),
+            HashMap::default(),
+        )));
+        let (s, exit, keypair) = server_args();
+        let SpawnServerResult {
+            endpoint: _,
+            thread: t,
+            key_updater: _,
+        } = solana_streamer::quic::spawn_server(
+            "solQuicTest",
+            "quic_streamer_test",
+            s.try_clone().unwrap(),
+            &keypair,
+            sender,
+            exit.clone(),
+            1,
+            staked_nodes,
+            10,
+            10,
+            DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
+            DEFAULT_TPU_COALESCE,
+        )
+        .unwrap();
+
+        let tpu_addr = s.local_addr().unwrap();
+        let (certificate, key) = new_dummy_x509_certificate(&client_keypair);
+        let endpoint = QuicLazyInitializedEndpoint::new_with_zstd_batches(
+            Arc::new(QuicClientCertificate { certificate, key }),
+            None,
+            true,
+        );
+        let connection_cache_stats = Arc::new(ConnectionCacheStats::default());
+        let client =
+            QuicClientConnection::new(Arc::new(endpoint), tpu_addr, connection_cache_stats);
+
+        let num_bytes = PACKET_DATA_SIZE;
+        let num_expected_packets: usize = 300;
+        let packets = vec![vec![0u8; PACKET_DATA_SIZE]; num_expected_packets];
+
+        assert!(client.send_data_batch_async(packets).is_ok());
+
+        check_packets(receiver, num_bytes, num_expected_packets);
+        exit.store(true, Ordering::Relaxed);
+        t.join().unwrap();
+    }
+
+    #[test]
+    fn test_quic_client_zstd_batches_unstaked_fallback() {
+        use {
+            solana_connection_cache::client_connection::ClientConnection,
+            solana_quic_client::quic_client::QuicClientConnection,
+        };
+        solana_logger::setup();
+        let (sender, receiver) = unbounded();
+        let staked_nodes = Arc::new(RwLock::new(StakedNodes::default()));
+        let (s, exit, keypair) = server_args();
+        let SpawnServerResult {
+            endpoint: _,
+            thread: t,
+            key_updater: _,
+        } = solana_streamer::quic::spawn_server(
+            "solQuicTest",
+            "quic_streamer_test",
+            s.try_clone().unwrap(),
+            &keypair,
+            sender,
+            exit.clone(),
+            1,
+            staked_nodes,
+            10,
+            10,
+            DEFAULT_WAIT_FOR_CHUNK_TIMEOUT,
+            DEFAULT_TPU_COALESCE,
+        )
+        .unwrap();
+
+        // the server refuses compressed batches from the unstaked client, which then falls back to
+        // the standard protocol
+        let tpu_addr = s.local_addr().unwrap();
+        let (certificate, key) = new_dummy_x509_certificate(&Keypair::new());
+        let endpoint = QuicLazyInitializedEndpoint::new_with_zstd_batches(
+            Arc::new(QuicClientCertificate { certificate, key }),
+            None,
+            true,
+        );
+        let connection_cache_stats = Arc::new(ConnectionCacheStats::default());
+        let client =
+            QuicClientConnection::new(Arc::new(endpoint), tpu_addr, connection_cache_stats);
+
+        let num_bytes = PACKET_DATA_SIZE;
+        let num_expected_packets: usize = 300;
+        let packets = vec![vec![0u8; PACKET_DATA_SIZE]; num_expected_packets];
+
+        assert!(client.send_data_batch_async(packets).is_ok());
+
+        check_packets(receiver, num_bytes, num_expected_packets);
+        exit.store(true, Ordering::Relaxed);
+        t.join().unwrap();
+    }
+
     // A version of check_packets that avoids blocking in an
     // async environment. todo: we really need a way of guaranteeing
     // we don't block in async code/tests, as it can lead to subtle bugs
@@ -189,6 +301,454 @@ mod tests {
         t.await.unwrap();
     }
 
+    #[tokio::test]
+    async fn test_nonblocking_quic_client_pacing_rate() {
+        use {
+            solana_connection_cache::client_connection::ClientStats,
+            solana_quic_client::nonblocking::quic_client::QuicClient,
+        };
+        solana_logger::setup();
+        let (sender, receiver) = unbounded();
+        let staked_nodes = Arc::new(RwLock::new(StakedNodes::default()));
+        let (s, exit, keypair) = server_args();
+        let (_, _, t) = solana_streamer::nonblocking::quic::spawn_server(
+            "quic_streamer_test",
+            s.try_clone().unwrap(),
+            &keypair,
+            sender,
+            exit.clone(),
+            1,
+            staked_nodes,
+            10,
+            10,
+            Duration::from_secs(1), // wait_for_chunk_timeout
+            DEFAULT_TPU_COALESCE,
+        )
+        .unwrap();
+
+        let tpu_addr = s.local_addr().unwrap();
+        let pacing_rate = 1_000_000;
+        let client = QuicClient::new_with_pacing_rate(
+            Arc::new(QuicLazyInitializedEndpoint::default()),
+            tpu_addr,
+            1,
+            0,
+            Some(pacing_rate),
+        );
+        assert_eq!(client.pacing_rate(), Some(pacing_rate));
+        assert_eq!(client.effective_pacing_rate().await, None);
+
+        let num_bytes = PACKET_DATA_SIZE;
+        let num_expected_packets: usize = 100;
+        let packets = vec![vec![0u8; PACKET_DATA_SIZE]; num_expected_packets];
+        client
+            .send_batch(
+                &packets,
+                &ClientStats::default(),
+                Arc::new(ConnectionCacheStats::default()),
+            )
+            .await
+            .unwrap();
+        assert!(client.effective_pacing_rate().await.is_some());
+
+        nonblocking_check_packets(receiver, num_bytes, num_expected_packets).await;
+        exit.store(true, Ordering::Relaxed);
+        t.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_nonblocking_quic_client_stream_credit() {
+        use {
+            solana_connection_cache::client_connection::ClientStats,
+            solana_quic_client::nonblocking::quic_client::QuicClient,
+            solana_sdk::quic::QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS,
+        };
+        solana_logger::setup();
+        let (sender, receiver) = unbounded();
+        let staked_nodes = Arc::new(RwLock::new(StakedNodes::default()));
+        let (s, exit, keypair) = server_args();
+        let (_, _, t) = solana_streamer::nonblocking::quic::spawn_server(
+            "quic_streamer_test",
+            s.try_clone().unwrap(),
+            &keypair,
+            sender,
+            exit.clone(),
+            1,
+            staked_nodes,
+            10,
+            10,
+            Duration::from_secs(1), // wait_for_chunk_timeout
+            DEFAULT_TPU_COALESCE,
+        )
+        .unwrap();
+
+        let tpu_addr = s.local_addr().unwrap();
+        let send_window = 1_000_000;
+        let (certificate, key) = new_dummy_x509_certificate(&Keypair::new());
+        let client = QuicClient::new(
+            Arc::new(QuicLazyInitializedEndpoint::new_with_send_window(
+                Arc::new(QuicClientCertificate { certificate, key }),
+                None,
+                false,
+                None,
+                Some(send_window),
+            )),
+            tpu_addr,
+            // far more than the server grants to an unstaked client at once
+            1024,
+        );
+        assert_eq!(client.stream_cap().send_window(), Some(send_window));
+        assert_eq!(client.stream_cap().peer_max_streams(), None);
+
+        let num_bytes = PACKET_DATA_SIZE;
+        let num_expected_packets: usize = 1024;
+        let packets = vec![vec![0u8; PACKET_DATA_SIZE]; num_expected_packets];
+        client
+            .send_batch(
+                &packets,
+                &ClientStats::default(),
+                Arc::new(ConnectionCacheStats::default()),
+            )
+            .await
+            .unwrap();
+
+        // the streams beyond the credit of the server have been held back, rather than failing
+        let stream_cap = client.stream_cap();
+        assert!(stream_cap.stream_limited_count() > 0);
+        let peer_max_streams = stream_cap.peer_max_streams().unwrap();
+        assert!(peer_max_streams <= QUIC_MAX_UNSTAKED_CONCURRENT_STREAMS * 2);
+        assert!(stream_cap.throttled_stream_count() > 0);
+        assert_eq!(stream_cap.in_flight_streams(), 0);
+        assert_eq!(stream_cap.available_streams(), peer_max_streams);
+
+        nonblocking_check_packets(receiver, num_bytes, num_expected_packets).await;
+        exit.store(true, Ordering::Relaxed);
+        t.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_nonblocking_quic_client_close() {
+        use {
+            solana_connection_cache::client_connection::ClientStats,
+            solana_quic_client::nonblocking::quic_client::QuicClient,
+        };
+        solana_logger::setup();
+        let (sender, receiver) = unbounded();
+        let staked_nodes = Arc::new(RwLock::new(StakedNodes::default()));
+        let (s, exit, keypair) = server_args();
+        let (_, _, t) = solana_streamer::nonblocking::quic::spawn_server(
+            "quic_streamer_test",
+            s.try_clone().unwrap(),
+            &keypair,
+            sender,
+            exit.clone(),
+            1,
+            staked_nodes,
+            10,
+            10,
+            Duration::from_secs(1), // wait_for_chunk_timeout
+            DEFAULT_TPU_COALESCE,
+        )
+        .unwrap();
+
+        let tpu_addr = s.local_addr().unwrap();
+        let client = Arc::new(QuicClient::new(
+            Arc::new(QuicLazyInitializedEndpoint::default()),
+            tpu_addr,
+            4,
+        ));
+
+        let num_bytes = PACKET_DATA_SIZE;
+        let num_expected_packets: usize = 100;
+        let packets = vec![vec![0u8; PACKET_DATA_SIZE]; num_expected_packets];
+        let send = {
+            let client = client.clone();
+            tokio::spawn(async move {
+                client
+                    .send_batch(
+                        &packets,
+                        &ClientStats::default(),
+                        Arc::new(ConnectionCacheStats::default()),
+                    )
+                    .await
+            })
+        };
+        // let the batch get in flight
+        while client.stream_cap().in_flight_streams() == 0 && !send.is_finished() {
+            sleep(Duration::from_millis(1)).await;
+        }
+        assert!(client.close(Duration::from_secs(10)).await);
+        assert!(client.is_closed());
+        // the batch which was in flight isn't necessarily sent as a whole, but nothing after close()
+        let _ = send.await.unwrap();
+        assert!(client
+            .send_buffer(
+                vec![0u8; PACKET_DATA_SIZE],
+                &ClientStats::default(),
+                Arc::new(ConnectionCacheStats::default()),
+            )
+            .await
+            .is_err());
+
+        nonblocking_check_packets(receiver, num_bytes, 1).await;
+        exit.store(true, Ordering::Relaxed);
+        t.await.unwrap();
+    }
+
+    // Spawns a bare quinn server which grants a single stream at a time, so that no stream can be
+    // acknowledged without being read, and closes the first connection once it has read
+    // `close_after` streams. The packets read from all connections are sent to the returned
+    // receiver.
+    fn spawn_closing_server(
+        close_after: usize,
+    ) -> (
+        SocketAddr,
+        tokio::sync::mpsc::UnboundedReceiver<Vec<u8>>,
+        tokio::task::JoinHandle<()>,
+    ) {
+        use solana_streamer::nonblocking::quic::ALPN_TPU_PROTOCOL_ID;
+
+        let (cert, key) = new_dummy_x509_certificate(&Keypair::new());
+        let mut tls_config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert], key)
+            .unwrap();
+        tls_config.alpn_protocols = vec![ALPN_TPU_PROTOCOL_ID.to_vec()];
+        let mut server_config = quinn::ServerConfig::with_crypto(Arc::new(tls_config));
+        Arc::get_mut(&mut server_config.transport)
+            .unwrap()
+            .max_concurrent_uni_streams(1u32.into());
+        let endpoint =
+            quinn::Endpoint::server(server_config, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = endpoint.local_addr().unwrap();
+
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let server = tokio::spawn(async move {
+            let mut is_first_connection = true;
+            while let Some(connecting) = endpoint.accept().await {
+                let Ok(connection) = connecting.await else {
+                    continue;
+                };
+                let mut stream_count = 0;
+                while let Ok(mut stream) = connection.accept_uni().await {
+                    let Ok(packet) = stream.read_to_end(PACKET_DATA_SIZE).await else {
+                        break;
+                    };
+                    sender.send(packet).unwrap();
+                    stream_count += 1;
+                    if is_first_connection && stream_count == close_after {
+                        connection.close(0u32.into(), b"closed_by_test");
+                        break;
+                    }
+                }
+                is_first_connection = false;
+            }
+        });
+        (addr, receiver, server)
+    }
+
+    #[tokio::test]
+    async fn test_nonblocking_quic_client_batch_send_retries() {
+        use {
+            solana_connection_cache::client_connection::ClientStats,
+            solana_quic_client::nonblocking::quic_client::QuicClient, std::collections::HashSet,
+        };
+        solana_logger::setup();
+        let num_packets = 32;
+        let packets: Vec<_> = (0..num_packets as u64)
+            .map(|index| index.to_le_bytes().to_vec())
+            .collect();
+
+        // the unsent remainder of the batch is resent over a new connection
+        let (addr, mut receiver, server) = spawn_closing_server(num_packets / 2);
+        let client = QuicClient::new_with_batch_send_retries(
+            Arc::new(QuicLazyInitializedEndpoint::default()),
+            addr,
+            4,
+            1,
+        );
+        let stats = ClientStats::default();
+        client
+            .send_batch(&packets, &stats, Arc::new(ConnectionCacheStats::default()))
+            .await
+            .unwrap();
+        assert!(stats.batch_retried_packets.load(Ordering::Relaxed) > 0);
+        assert_eq!(stats.batch_lost_packets.load(Ordering::Relaxed), 0);
+        // some packets may be delivered twice, but none is missing
+        let mut received = HashSet::new();
+        while received.len() < num_packets {
+            let packet = tokio::time::timeout(Duration::from_secs(10), receiver.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            received.insert(packet);
+        }
+        assert_eq!(received, packets.iter().cloned().collect::<HashSet<_>>());
+        server.abort();
+
+        // without retries, the loss of the connection surfaces as is
+        let (addr, mut receiver, server) = spawn_closing_server(num_packets / 2);
+        let client = QuicClient::new_with_batch_send_retries(
+            Arc::new(QuicLazyInitializedEndpoint::default()),
+            addr,
+            4,
+            0,
+        );
+        let stats = ClientStats::default();
+        assert!(client
+            .send_batch(&packets, &stats, Arc::new(ConnectionCacheStats::default()))
+            .await
+            .is_err());
+        assert_eq!(stats.batch_retried_packets.load(Ordering::Relaxed), 0);
+        assert_eq!(stats.batch_lost_packets.load(Ordering::Relaxed), 0);
+        let mut received_count = 0;
+        while let Ok(Some(_packet)) =
+            tokio::time::timeout(Duration::from_millis(100), receiver.recv()).await
+        {
+            received_count += 1;
+        }
+        assert_eq!(received_count, num_packets / 2);
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_nonblocking_quic_client_known_validators() {
+        use {
+            solana_connection_cache::client_connection::ClientStats,
+            solana_quic_client::nonblocking::quic_client::QuicClient,
+            solana_sdk::signature::Signer, std::collections::HashSet,
+        };
+        solana_logger::setup();
+        let (sender, receiver) = unbounded();
+        let staked_nodes = Arc::new(RwLock::new(StakedNodes::default()));
+        let (s, exit, keypair) = server_args();
+        let (_, _, t) = solana_streamer::nonblocking::quic::spawn_server(
+            "quic_streamer_test",
+            s.try_clone().unwrap(),
+            &keypair,
+            sender,
+            exit.clone(),
+            1,
+            staked_nodes,
+            10,
+            10,
+            Duration::from_secs(1), // wait_for_chunk_timeout
+            DEFAULT_TPU_COALESCE,
+        )
+        .unwrap();
+        let tpu_addr = s.local_addr().unwrap();
+        let known_validators = Arc::new(RwLock::new(HashSet::from([Keypair::new().pubkey()])));
+        let create_client = || {
+            let (cert, key) = new_dummy_x509_certificate(&Keypair::new());
+            QuicClient::new(
+                Arc::new(QuicLazyInitializedEndpoint::new_with_known_validators(
+                    Arc::new(QuicClientCertificate {
+                        certificate: cert,
+                        key,
+                    }),
+                    None,
+                    false,
+                    Some(known_validators.clone()),
+                )),
+                tpu_addr,
+                1,
+            )
+        };
+        let packet = vec![0u8; PACKET_DATA_SIZE];
+
+        // the server is unknown
+        let client = create_client();
+        assert!(client
+            .send_buffer(
+                &packet,
+                &ClientStats::default(),
+                Arc::new(ConnectionCacheStats::default())
+            )
+            .await
+            .is_err());
+
+        // the server becomes known
+        known_validators.write().unwrap().insert(keypair.pubkey());
+        let client = create_client();
+        client
+            .send_buffer(
+                &packet,
+                &ClientStats::default(),
+                Arc::new(ConnectionCacheStats::default()),
+            )
+            .await
+            .unwrap();
+
+        nonblocking_check_packets(receiver, PACKET_DATA_SIZE, 1).await;
+        exit.store(true, Ordering::Relaxed);
+        t.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_nonblocking_named_quic_client() {
+        use {
+            solana_connection_cache::client_connection::ClientStats,
+            solana_quic_client::nonblocking::named_quic_client::NamedQuicClient,
+        };
+        solana_logger::setup();
+        let (sender, receiver) = unbounded();
+        let staked_nodes = Arc::new(RwLock::new(StakedNodes::default()));
+        let (s, exit, keypair) = server_args();
+        let (_, _, t) = solana_streamer::nonblocking::quic::spawn_server(
+            "quic_streamer_test",
+            s.try_clone().unwrap(),
+            &keypair,
+            sender,
+            exit.clone(),
+            1,
+            staked_nodes,
+            10,
+            10,
+            Duration::from_secs(1), // wait_for_chunk_timeout
+            DEFAULT_TPU_COALESCE,
+        )
+        .unwrap();
+        let tpu_addr = s.local_addr().unwrap();
+
+        assert!(NamedQuicClient::connect_by_name(
+            Arc::new(QuicLazyInitializedEndpoint::default()),
+            "localhost",
+            1,
+        )
+        .await
+        .is_err());
+
+        let name = format!("localhost:{}", tpu_addr.port());
+        let client = NamedQuicClient::connect_by_name(
+            Arc::new(QuicLazyInitializedEndpoint::default()),
+            &name,
+            1,
+        )
+        .await
+        .unwrap();
+        assert_eq!(client.name(), name);
+        assert_eq!(client.server_addr(), tpu_addr);
+
+        let num_expected_packets: usize = 3;
+        let packets = vec![vec![0u8; PACKET_DATA_SIZE]; num_expected_packets];
+        client
+            .send_batch(
+                &packets,
+                &ClientStats::default(),
+                Arc::new(ConnectionCacheStats::default()),
+            )
+            .await
+            .unwrap();
+        // the address is unchanged, so the connection is kept
+        assert!(!client.re_resolve().await.unwrap());
+        assert_eq!(client.server_addr(), tpu_addr);
+
+        nonblocking_check_packets(receiver, PACKET_DATA_SIZE, num_expected_packets).await;
+        exit.store(true, Ordering::Relaxed);
+        t.await.unwrap();
+    }
+
     #[test]
     fn test_quic_bi_direction() {
         /// This tests bi-directional quic communication. There are the following components
@@ -33,7 +33,9 @@ use {
         fmt::Debug,
         ops::Deref,
         sync::{Arc, RwLock},
+        time::Duration,
     },
+    thiserror::Error,
 };
 #[cfg(feature = "dev-context-only-utils")]
 use {mockall::automock, qualifier_attr::qualifiers};
@@ -111,15 +113,26 @@ pub trait InstalledScheduler: Send + Sync + Debug + 'static {
     ///
     /// This function blocks the current thread while waiting for the scheduler to complete all of
     /// the executions for the scheduled transactions and to return the finalized
-    /// `ResultWithTimings`. Along with the result, this function also makes the scheduler itself
-    /// uninstalled from the bank by transforming the consumed self.
+    /// `ResultWithTimings` and `SessionSummary`. Along with the result, this function also makes
+    /// the scheduler itself uninstalled from the bank by transforming the consumed self.
+    ///
+    /// If the scheduler itself has failed (e.g. one of its threads has panicked), `SchedulerError`
+    /// is returned instead of the result, which then doesn't reflect the scheduled transactions.
     ///
     /// If no transaction is scheduled, the result and timing will be `Ok(())` and
     /// `ExecuteTimings::default()` respectively.
+    ///
+    /// If `is_dropped` is true, the caller is going to discard the result. So, the scheduler is
+    /// allowed to abort without executing the remaining scheduled transactions, in which case the
+    /// returned result doesn't reflect them.
     fn wait_for_termination(
         self: Box<Self>,
         is_dropped: bool,
-    ) -> (ResultWithTimings, UninstalledSchedulerBox);
+    ) -> (
+        SchedulerResult<ResultWithTimings>,
+        SessionSummary,
+        UninstalledSchedulerBox,
+    );
 
     /// Pause a scheduler after processing to update bank's recent blockhash.
     ///
@@ -173,6 +186,38 @@ impl SchedulingContext {
 
 pub type ResultWithTimings = (Result<()>, ExecuteTimings);
 
+/// The failure of a scheduler itself, as opposed to the failure of the scheduled transactions.
+///
+/// The session in which this is returned must be considered incomplete, because some of its
+/// transactions may not have been executed.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum SchedulerError {
+    /// One of the scheduler's threads has exited unexpectedly, disconnecting its channels.
+    #[error("the scheduler's threads have been disconnected")]
+    Disconnected,
+    /// One of the scheduler's threads has panicked, with the panic message if any.
+    #[error("a scheduler thread has panicked: {0}")]
+    ThreadPanicked(String),
+    /// A transaction has been scheduled after the session has already ended.
+    #[error("the session has already ended")]
+    SessionEnded,
+}
+
+pub type SchedulerResult<T> = std::result::Result<T, SchedulerError>;
+
+/// Session-level timestamps measured by the scheduler, relative to the start of the session.
+///
+/// Unlike `ExecuteTimings`, which are accumulated across tasks, these tell how long the scheduler
+/// took to get going and to drain a session, so that the latency of a slot attributable to the
+/// scheduler can be told apart from the rest (e.g. banking or ledger I/O).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SessionSummary {
+    /// When the first task was dispatched for execution, if any.
+    pub first_dispatch: Option<Duration>,
+    /// When the last task was committed, if any.
+    pub last_commit: Option<Duration>,
+}
+
 /// A hint from the bank about the reason the caller is waiting on its scheduler.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 enum WaitReason {
@@ -186,6 +231,9 @@ enum WaitReason {
     // The bank wants its scheduler to terminate just like `TerminatedToFreeze` and indicate that
     // Drop::drop() is the caller.
     DroppedFromBankForks,
+    // The bank wants its scheduler to terminate as soon as possible without executing the
+    // remaining transactions, because the bank has been marked dead by the replay stage.
+    AbortedForDeadBank,
     // The bank wants its scheduler to pause after the completion without being returned to the
     // pool. This is to update bank's recent blockhash and to collect scheduler's internally-held
     // `ResultWithTimings` later.
@@ -198,7 +246,9 @@ impl WaitReason {
         // decision to be made, should we add new variants like `PausedForFooBar`...
         match self {
             WaitReason::PausedForRecentBlockhash => true,
-            WaitReason::TerminatedToFreeze | WaitReason::DroppedFromBankForks => false,
+            WaitReason::TerminatedToFreeze
+            | WaitReason::DroppedFromBankForks
+            | WaitReason::AbortedForDeadBank => false,
         }
     }
 
@@ -206,7 +256,7 @@ impl WaitReason {
         // Exhaustive `match` is preferred here than `matches!()` to trigger an explicit
         // decision to be made, should we add new variants like `PausedForFooBar`...
         match self {
-            WaitReason::DroppedFromBankForks => true,
+            WaitReason::DroppedFromBankForks | WaitReason::AbortedForDeadBank => true,
             WaitReason::TerminatedToFreeze | WaitReason::PausedForRecentBlockhash => false,
         }
     }
@@ -323,7 +373,17 @@ impl BankWithScheduler {
     }
 
     #[must_use]
-    pub fn wait_for_completed_scheduler(&self) -> Option<ResultWithTimings> {
+    pub fn wait_for_completed_scheduler(&self) -> Option<SchedulerResult<ResultWithTimings>> {
+        self.wait_for_completed_scheduler_with_summary()
+            .map(|(result_with_timings, _summary)| result_with_timings)
+    }
+
+    /// Same as `wait_for_completed_scheduler()`, but also returns the `SessionSummary` of the
+    /// scheduler.
+    #[must_use]
+    pub fn wait_for_completed_scheduler_with_summary(
+        &self,
+    ) -> Option<(SchedulerResult<ResultWithTimings>, SessionSummary)> {
         BankWithSchedulerInner::wait_for_scheduler_termination(
             &self.inner.bank,
             &self.inner.scheduler,
@@ -331,6 +391,29 @@ impl BankWithScheduler {
         )
     }
 
+    /// Terminates the scheduler without waiting for the execution of the remaining scheduled
+    /// transactions, returning it to the pool. This is intended for dead banks, whose
+    /// transaction results don't matter anymore.
+    pub fn abort_scheduler(&self) {
+        match BankWithSchedulerInner::wait_for_scheduler_termination(
+            &self.inner.bank,
+            &self.inner.scheduler,
+            WaitReason::AbortedForDeadBank,
+        ) {
+            Some((Ok((Err(err), _timings)), _summary)) => debug!(
+                "abort_scheduler(): slot: {} discarding error from scheduler: {:?}",
+                self.inner.bank.slot(),
+                err,
+            ),
+            Some((Err(err), _summary)) => warn!(
+                "abort_scheduler(): slot: {} scheduler has failed: {}",
+                self.inner.bank.slot(),
+                err,
+            ),
+            Some((Ok((Ok(()), _timings)), _summary)) | None => {}
+        }
+    }
+
     pub const fn no_scheduler_available() -> InstalledSchedulerRwLock {
         RwLock::new(None)
     }
@@ -338,7 +421,9 @@ impl BankWithScheduler {
 
 impl BankWithSchedulerInner {
     #[must_use]
-    fn wait_for_completed_scheduler_from_drop(&self) -> Option<ResultWithTimings> {
+    fn wait_for_completed_scheduler_from_drop(
+        &self,
+    ) -> Option<(SchedulerResult<ResultWithTimings>, SessionSummary)> {
         Self::wait_for_scheduler_termination(
             &self.bank,
             &self.scheduler,
@@ -351,7 +436,7 @@ impl BankWithSchedulerInner {
         bank: &Bank,
         scheduler: &InstalledSchedulerRwLock,
         reason: WaitReason,
-    ) -> Option<ResultWithTimings> {
+    ) -> Option<(SchedulerResult<ResultWithTimings>, SessionSummary)> {
         debug!(
             "wait_for_scheduler_termination(slot: {}, reason: {:?}): started...",
             bank.slot(),
@@ -364,10 +449,10 @@ impl BankWithSchedulerInner {
                 scheduler.pause_for_recent_blockhash();
                 None
             } else if let Some(scheduler) = scheduler.take() {
-                let (result_with_timings, uninstalled_scheduler) =
+                let (result_with_timings, summary, uninstalled_scheduler) =
                     scheduler.wait_for_termination(reason.is_dropped());
                 uninstalled_scheduler.return_to_pool();
-                Some(result_with_timings)
+                Some((result_with_timings, summary))
             } else {
                 None
             };
@@ -375,7 +460,9 @@ impl BankWithSchedulerInner {
             "wait_for_scheduler_termination(slot: {}, reason: {:?}): finished with: {:?}...",
             bank.slot(),
             reason,
-            result_with_timings.as_ref().map(|(result, _)| result),
+            result_with_timings
+                .as_ref()
+                .map(|(result, summary)| (result.as_ref().map(|(result, _)| result), summary)),
         );
 
         result_with_timings
@@ -391,15 +478,21 @@ impl BankWithSchedulerInner {
         }
 
         // There's no guarantee ResultWithTimings is available or not at all when being dropped.
-        if let Some(Err(err)) = self
+        match self
             .wait_for_completed_scheduler_from_drop()
-            .map(|(result, _timings)| result)
+            .map(|(result_with_timings, _summary)| result_with_timings)
         {
-            warn!(
+            Some(Ok((Err(err), _timings))) => warn!(
                 "BankWithSchedulerInner::drop_scheduler(): slot: {} discarding error from scheduler: {:?}",
                 self.bank.slot(),
                 err,
-            );
+            ),
+            Some(Err(err)) => warn!(
+                "BankWithSchedulerInner::drop_scheduler(): slot: {} scheduler has failed: {}",
+                self.bank.slot(),
+                err,
+            ),
+            Some(Ok((Ok(()), _timings))) | None => {}
         }
     }
 }
@@ -459,7 +552,8 @@ mod tests {
                         .in_sequence(&mut seq_cloned.lock().unwrap())
                         .returning(|| ());
                     (
-                        (Ok(()), ExecuteTimings::default()),
+                        Ok((Ok(()), ExecuteTimings::default())),
+                        SessionSummary::default(),
                         Box::new(mock_uninstalled),
                     )
                 });
@@ -525,6 +619,25 @@ mod tests {
         drop(bank);
     }
 
+    #[test]
+    fn test_scheduler_abort() {
+        solana_logger::setup();
+
+        let bank = Arc::new(Bank::default_for_tests());
+        let bank = BankWithScheduler::new(
+            bank.clone(),
+            Some(setup_mocked_scheduler(bank, [true].into_iter())),
+        );
+        assert!(bank.has_installed_scheduler());
+        bank.abort_scheduler();
+
+        // The scheduler has already been returned to the pool, so neither repeated abort nor the
+        // drop calls wait_for_termination() again.
+        assert!(!bank.has_installed_scheduler());
+        bank.abort_scheduler();
+        assert_matches!(bank.wait_for_completed_scheduler(), None);
+    }
+
     #[test]
     fn test_scheduler_pause() {
         solana_logger::setup();
@@ -31,6 +31,7 @@ use {
         timing,
     },
     std::{
+        io,
         iter::repeat_with,
         net::{IpAddr, SocketAddr, UdpSocket},
         // CAUTION: be careful not to introduce any awaits while holding an RwLock.
@@ -59,6 +60,20 @@ const WAIT_FOR_STREAM_TIMEOUT: Duration = Duration::from_millis(100);
 pub const DEFAULT_WAIT_FOR_CHUNK_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub const ALPN_TPU_PROTOCOL_ID: &[u8] = b"solana-tpu";
+/// Opt-in variant of [`ALPN_TPU_PROTOCOL_ID`], where each stream carries a zstd-compressed batch of
+/// packets encoded by [`encode_zstd_batch`], instead of a single packet. Only staked peers are
+/// allowed to use it: connections of unstaked peers negotiating it are closed right after the
+/// handshake with [`CONNECTION_CLOSE_CODE_ZSTD_BATCH_DISALLOWED`], so that clients can reconnect
+/// with [`ALPN_TPU_PROTOCOL_ID`]. Each packet of a batch counts as a stream against the peer's
+/// stream quota.
+pub const ALPN_TPU_ZSTD_PROTOCOL_ID: &[u8] = b"solana-tpu-zstd";
+/// The maximum number of packets in a single batch of [`ALPN_TPU_ZSTD_PROTOCOL_ID`].
+pub const MAX_ZSTD_BATCH_PACKETS: usize = 64;
+// Each packet is prefixed with its length as u16.
+const MAX_ZSTD_BATCH_DECOMPRESSED_SIZE: usize = MAX_ZSTD_BATCH_PACKETS * (2 + PACKET_DATA_SIZE);
+// Tolerate compressed batches slightly larger than the decompressed ones, for incompressible data.
+const MAX_ZSTD_BATCH_COMPRESSED_SIZE: usize = MAX_ZSTD_BATCH_DECOMPRESSED_SIZE * 2;
+const ZSTD_BATCH_COMPRESSION_LEVEL: i32 = 3;
 
 const CONNECTION_CLOSE_CODE_DROPPED_ENTRY: u32 = 1;
 const CONNECTION_CLOSE_REASON_DROPPED_ENTRY: &[u8] = b"dropped";
@@ -72,6 +87,9 @@ const CONNECTION_CLOSE_REASON_EXCEED_MAX_STREAM_COUNT: &[u8] = b"exceed_max_stre
 const CONNECTION_CLOSE_CODE_TOO_MANY: u32 = 4;
 const CONNECTION_CLOSE_REASON_TOO_MANY: &[u8] = b"too_many";
 
+pub const CONNECTION_CLOSE_CODE_ZSTD_BATCH_DISALLOWED: u32 = 5;
+const CONNECTION_CLOSE_REASON_ZSTD_BATCH_DISALLOWED: &[u8] = b"zstd_batch_disallowed";
+
 // A sequence of bytes that is part of a packet
 // along with where in the packet it is
 struct PacketChunk {
@@ -237,6 +255,56 @@ pub fn get_remote_pubkey(connection: &Connection) -> Option<Pubkey> {
         .and_then(get_pubkey_from_tls_certificate)
 }
 
+/// Returns the ALPN protocol negotiated for the connection, if any.
+pub fn get_alpn_protocol(connection: &Connection) -> Option<Vec<u8>> {
+    connection
+        .handshake_data()?
+        .downcast::<quinn::crypto::rustls::HandshakeData>()
+        .ok()?
+        .protocol
+}
+
+/// Encodes the packets into a single zstd-compressed batch for [`ALPN_TPU_ZSTD_PROTOCOL_ID`].
+pub fn encode_zstd_batch<T: AsRef<[u8]>>(packets: &[T]) -> io::Result<Vec<u8>> {
+    if packets.len() > MAX_ZSTD_BATCH_PACKETS {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("too many packets in a batch: {}", packets.len()),
+        ));
+    }
+    let mut encoded = Vec::new();
+    for packet in packets {
+        let packet = packet.as_ref();
+        if packet.len() > PACKET_DATA_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("packet too large: {}", packet.len()),
+            ));
+        }
+        encoded.extend_from_slice(&(packet.len() as u16).to_le_bytes());
+        encoded.extend_from_slice(packet);
+    }
+    zstd::bulk::compress(&encoded, ZSTD_BATCH_COMPRESSION_LEVEL)
+}
+
+/// Decodes a batch encoded by [`encode_zstd_batch`], returning `None` if it's malformed or exceeds
+/// the size limits.
+pub fn decode_zstd_batch(compressed: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let decoded = zstd::bulk::decompress(compressed, MAX_ZSTD_BATCH_DECOMPRESSED_SIZE).ok()?;
+    let mut packets = Vec::new();
+    let mut rest = decoded.as_slice();
+    while !rest.is_empty() {
+        let len = usize::from(u16::from_le_bytes(rest.get(..2)?.try_into().unwrap()));
+        let packet = rest.get(2..2 + len)?;
+        if len > PACKET_DATA_SIZE || packets.len() >= MAX_ZSTD_BATCH_PACKETS {
+            return None;
+        }
+        packets.push(packet.to_vec());
+        rest = &rest[2 + len..];
+    }
+    Some(packets)
+}
+
 fn get_connection_stake(
     connection: &Connection,
     staked_nodes: &RwLock<StakedNodes>,
@@ -515,6 +583,21 @@ async fn setup_connection(
                     },
                 );
 
+                if !params.peer_type.is_staked()
+                    && get_alpn_protocol(&new_connection).as_deref()
+                        == Some(ALPN_TPU_ZSTD_PROTOCOL_ID)
+                {
+                    // compressed batches are meant only for trusted peers
+                    stats
+                        .connection_add_failed_zstd_batch_disallowed
+                        .fetch_add(1, Ordering::Relaxed);
+                    new_connection.close(
+                        CONNECTION_CLOSE_CODE_ZSTD_BATCH_DISALLOWED.into(),
+                        CONNECTION_CLOSE_REASON_ZSTD_BATCH_DISALLOWED,
+                    );
+                    return;
+                }
+
                 match params.peer_type {
                     ConnectionPeerType::Staked(stake) => {
                         let mut connection_table_l = staked_connection_table.lock().await;
@@ -737,6 +820,8 @@ async fn handle_connection(
         stats.total_connections.load(Ordering::Relaxed),
     );
     let stable_id = connection.stable_id();
+    let is_zstd_batch_connection =
+        get_alpn_protocol(&connection).as_deref() == Some(ALPN_TPU_ZSTD_PROTOCOL_ID);
     stats.total_connections.fetch_add(1, Ordering::Relaxed);
     while !stream_exit.load(Ordering::Relaxed) {
         if let Ok(stream) =
@@ -767,6 +852,29 @@ async fn handle_connection(
                     let packet_sender = params.packet_sender.clone();
                     let last_update = last_update.clone();
                     let stream_load_ema = stream_load_ema.clone();
+                    if is_zstd_batch_connection {
+                        let stream_counter = stream_counter.clone();
+                        tokio::spawn(async move {
+                            if handle_zstd_batch_stream(
+                                stream,
+                                &remote_addr,
+                                &packet_sender,
+                                &stats,
+                                params.peer_type,
+                                &stream_load_ema,
+                                &stream_counter,
+                                max_streams_per_throttling_interval,
+                                wait_for_chunk_timeout,
+                            )
+                            .await
+                            {
+                                last_update.store(timing::timestamp(), Ordering::Relaxed);
+                            }
+                            stats.total_streams.fetch_sub(1, Ordering::Relaxed);
+                            stream_load_ema.update_ema_if_needed();
+                        });
+                        continue;
+                    }
                     tokio::spawn(async move {
                         let mut maybe_batch = None;
                         // The min is to guard against a value too small which can wake up unnecessarily
@@ -835,6 +943,102 @@ async fn handle_connection(
     stats.total_connections.fetch_sub(1, Ordering::Relaxed);
 }
 
+// Reads the whole stream of a connection which negotiated ALPN_TPU_ZSTD_PROTOCOL_ID and hands the
+// packets of the decoded batch over to the packet batcher. Returns true if the batch is accepted.
+//
+// The stream itself has already been counted against the peer's quota by the caller, so only the
+// rest of the batch is counted here. Packets exceeding the quota are dropped.
+#[allow(clippy::too_many_arguments)]
+async fn handle_zstd_batch_stream(
+    mut stream: quinn::RecvStream,
+    remote_addr: &SocketAddr,
+    packet_sender: &AsyncSender<PacketAccumulator>,
+    stats: &StreamStats,
+    peer_type: ConnectionPeerType,
+    stream_load_ema: &StakedStreamLoadEMA,
+    stream_counter: &ConnectionStreamCounter,
+    max_streams_per_throttling_interval: u64,
+    wait_for_chunk_timeout: Duration,
+) -> bool {
+    let compressed = match timeout(
+        wait_for_chunk_timeout,
+        stream.read_to_end(MAX_ZSTD_BATCH_COMPRESSED_SIZE),
+    )
+    .await
+    {
+        Ok(Ok(compressed)) => compressed,
+        Ok(Err(err)) => {
+            debug!("failed to read compressed batch: {:?}", err);
+            stats
+                .total_invalid_chunk_size
+                .fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        Err(_) => {
+            debug!("Timeout in receiving on stream");
+            stats
+                .total_stream_read_timeouts
+                .fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+    };
+    let Some(mut packets) = decode_zstd_batch(&compressed) else {
+        stats.total_invalid_chunks.fetch_add(1, Ordering::Relaxed);
+        return false;
+    };
+    stats
+        .total_staked_chunks_received
+        .fetch_add(packets.len(), Ordering::Relaxed);
+
+    let extra_packets = packets.len().saturating_sub(1) as u64;
+    let available_streams = max_streams_per_throttling_interval
+        .saturating_sub(stream_counter.stream_count.load(Ordering::Relaxed));
+    let allowed_extra_packets = extra_packets.min(available_streams);
+    stream_load_ema.increment_load_by(peer_type, allowed_extra_packets);
+    stream_counter
+        .stream_count
+        .fetch_add(allowed_extra_packets, Ordering::Relaxed);
+    if allowed_extra_packets < extra_packets {
+        stats.throttled_zstd_batch_packets.fetch_add(
+            (extra_packets - allowed_extra_packets) as usize,
+            Ordering::Relaxed,
+        );
+        packets.truncate(1 + allowed_extra_packets as usize);
+    }
+
+    for packet in packets {
+        let mut meta = Meta::default();
+        meta.set_socket_addr(remote_addr);
+        meta.size = packet.len();
+        let accum = PacketAccumulator {
+            meta,
+            chunks: vec![PacketChunk {
+                end_of_chunk: packet.len(),
+                bytes: Bytes::from(packet),
+                offset: 0,
+            }],
+        };
+        let bytes_sent = accum.meta.size;
+        if let Err(err) = packet_sender.send(accum).await {
+            stats
+                .total_handle_chunk_to_packet_batcher_send_err
+                .fetch_add(1, Ordering::Relaxed);
+            trace!("packet batch send error {:?}", err);
+            return false;
+        }
+        stats
+            .total_packets_sent_for_batching
+            .fetch_add(1, Ordering::Relaxed);
+        stats
+            .total_bytes_sent_for_batching
+            .fetch_add(bytes_sent, Ordering::Relaxed);
+        stats
+            .total_chunks_sent_for_batching
+            .fetch_add(1, Ordering::Relaxed);
+    }
+    true
+}
+
 // Return true if the server should drop the stream
 async fn handle_chunk(
     chunk: Result<Option<quinn::Chunk>, quinn::ReadError>,
@@ -1424,6 +1628,26 @@ pub mod test {
         }
     }
 
+    #[test]
+    fn test_zstd_batch_encode_decode() {
+        let packets = vec![vec![1u8; 10], vec![], vec![2u8; PACKET_DATA_SIZE]];
+        let encoded = encode_zstd_batch(&packets).unwrap();
+        assert_eq!(decode_zstd_batch(&encoded), Some(packets));
+
+        assert!(encode_zstd_batch(&[vec![0u8; PACKET_DATA_SIZE + 1]]).is_err());
+        assert!(encode_zstd_batch(&vec![vec![0u8]; MAX_ZSTD_BATCH_PACKETS + 1]).is_err());
+
+        // a truncated length prefix or packet is rejected
+        let truncated = zstd::bulk::compress(&[5, 0, 1, 2], 0).unwrap();
+        assert_eq!(decode_zstd_batch(&truncated), None);
+        // garbage is rejected
+        assert_eq!(decode_zstd_batch(&[1, 2, 3]), None);
+        // too large decompressed size is rejected
+        let oversized =
+            zstd::bulk::compress(&vec![0u8; MAX_ZSTD_BATCH_DECOMPRESSED_SIZE + 2], 0).unwrap();
+        assert_eq!(decode_zstd_batch(&oversized), None);
+    }
+
     #[tokio::test]
     async fn test_quic_server_exit() {
         let (t, exit, _receiver, _server_address, _stats) = setup_quic_server(None, 1);
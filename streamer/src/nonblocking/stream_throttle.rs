@@ -137,8 +137,13 @@ impl StakedStreamLoadEMA {
     }
 
     pub(crate) fn increment_load(&self, peer_type: ConnectionPeerType) {
+        self.increment_load_by(peer_type, 1);
+    }
+
+    pub(crate) fn increment_load_by(&self, peer_type: ConnectionPeerType, load: u64) {
         if peer_type.is_staked() {
-            self.load_in_recent_interval.fetch_add(1, Ordering::Relaxed);
+            self.load_in_recent_interval
+                .fetch_add(load, Ordering::Relaxed);
         }
         self.update_ema_if_needed();
     }
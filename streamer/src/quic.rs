@@ -1,6 +1,7 @@
 use {
     crate::{
-        nonblocking::quic::ALPN_TPU_PROTOCOL_ID, streamer::StakedNodes,
+        nonblocking::quic::{ALPN_TPU_PROTOCOL_ID, ALPN_TPU_ZSTD_PROTOCOL_ID},
+        streamer::StakedNodes,
         tls_certificates::new_dummy_x509_certificate,
     },
     crossbeam_channel::Sender,
@@ -73,7 +74,14 @@ pub(crate) fn configure_server(
         .with_safe_defaults()
         .with_client_cert_verifier(SkipClientVerification::new())
         .with_single_cert(vec![cert], priv_key)?;
-    server_tls_config.alpn_protocols = vec![ALPN_TPU_PROTOCOL_ID.to_vec()];
+    // The server prefers the compressed protocol when the client offers it as an opt-in, while the
+    // clients offering only the standard protocol are served just as before. Unstaked clients which
+    // negotiate the compressed protocol are disconnected after the handshake, so that they fall
+    // back to the standard one.
+    server_tls_config.alpn_protocols = vec![
+        ALPN_TPU_ZSTD_PROTOCOL_ID.to_vec(),
+        ALPN_TPU_PROTOCOL_ID.to_vec(),
+    ];
 
     let mut server_config = ServerConfig::with_crypto(Arc::new(server_tls_config));
     server_config.use_retry(true);
@@ -161,6 +169,7 @@ pub struct StreamStats {
     pub(crate) connection_add_failed_staked_node: AtomicUsize,
     pub(crate) connection_add_failed_unstaked_node: AtomicUsize,
     pub(crate) connection_add_failed_on_pruning: AtomicUsize,
+    pub(crate) connection_add_failed_zstd_batch_disallowed: AtomicUsize,
     pub(crate) connection_setup_timeout: AtomicUsize,
     pub(crate) connection_setup_error: AtomicUsize,
     pub(crate) connection_setup_error_closed: AtomicUsize,
@@ -172,6 +181,7 @@ pub struct StreamStats {
     pub(crate) connection_removed: AtomicUsize,
     pub(crate) connection_remove_failed: AtomicUsize,
     pub(crate) throttled_streams: AtomicUsize,
+    pub(crate) throttled_zstd_batch_packets: AtomicUsize,
     pub(crate) stream_load_ema: AtomicUsize,
     pub(crate) stream_load_ema_overflow: AtomicUsize,
     pub(crate) stream_load_capacity_overflow: AtomicUsize,
@@ -247,6 +257,12 @@ impl StreamStats {
                     .swap(0, Ordering::Relaxed),
                 i64
             ),
+            (
+                "connection_add_failed_zstd_batch_disallowed",
+                self.connection_add_failed_zstd_batch_disallowed
+                    .swap(0, Ordering::Relaxed),
+                i64
+            ),
             (
                 "connection_removed",
                 self.connection_removed.swap(0, Ordering::Relaxed),
@@ -410,6 +426,11 @@ impl StreamStats {
                 self.throttled_streams.swap(0, Ordering::Relaxed),
                 i64
             ),
+            (
+                "throttled_zstd_batch_packets",
+                self.throttled_zstd_batch_packets.swap(0, Ordering::Relaxed),
+                i64
+            ),
             (
                 "stream_load_ema",
                 self.stream_load_ema.load(Ordering::Relaxed),
@@ -0,0 +1,366 @@
+//! Compares replaying a block of transfers through `PooledScheduler` against the legacy path of
+//! `process_entries()`, under a few workloads. Like the latter, the legacy path locks the accounts
+//! of a whole entry at once and executes the queued batches upon lock conflicts, albeit on a
+//! single thread to match the scheduler's single handler thread.
+//!
+//! Each iteration replays `TRANSACTION_COUNT` transactions into a fresh child bank. So, the TPS of
+//! each path is `TRANSACTION_COUNT` divided by the reported time per iteration. Also, the p50 and
+//! p99 latencies of transactions (i.e. how long it takes since the start of the block until each
+//! of them is executed) are logged at the end of each bench.
+//!
+//! The scheduler is also benched with `CompletionBatching`, logging the number of completion
+//! messages which the scheduler thread receives per task with and without it, i.e. how many of
+//! its wake-ups are saved.
+
+#![feature(test)]
+
+extern crate test;
+
+use {
+    assert_matches::assert_matches,
+    log::*,
+    rand::distributions::{Distribution, WeightedIndex},
+    solana_ledger::blockstore_processor::{execute_batch, TransactionBatchWithIndexes},
+    solana_program_runtime::timings::ExecuteTimings,
+    solana_runtime::{
+        bank::Bank,
+        bank_forks::BankForks,
+        genesis_utils::{create_genesis_config, GenesisConfigInfo},
+        installed_scheduler_pool::BankWithScheduler,
+        prioritization_fee_cache::PrioritizationFeeCache,
+    },
+    solana_sdk::{
+        account::AccountSharedData,
+        clock::Slot,
+        pubkey::Pubkey,
+        signer::{keypair::Keypair, Signer},
+        system_program, system_transaction,
+        transaction::SanitizedTransaction,
+    },
+    solana_unified_scheduler_pool::{
+        event_log::{EventKind, EventLog},
+        CompletionBatching, DefaultSchedulerPool, SchedulerPoolConfig,
+    },
+    std::{
+        collections::HashSet,
+        ops::Range,
+        sync::{Arc, RwLock},
+        time::{Duration, Instant},
+    },
+    test::Bencher,
+};
+
+const TRANSACTION_COUNT: usize = 512;
+const ACCOUNT_COUNT: usize = TRANSACTION_COUNT;
+// caps the entries, which are otherwise only split at lock conflicts
+const MAX_TRANSACTIONS_PER_ENTRY: usize = 64;
+const COMPLETION_BATCHING: CompletionBatching = CompletionBatching {
+    max_count: 64,
+    max_delay: Duration::from_millis(1),
+};
+
+#[derive(Clone, Copy, Debug)]
+enum Workload {
+    // every transaction touches its own accounts
+    NoConflict,
+    // every transaction writes to the same recipient
+    HotAccount,
+    // payers and recipients are drawn from a zipfian distribution
+    Zipfian,
+}
+
+struct BenchSetup {
+    bank_forks: Arc<RwLock<BankForks>>,
+    transactions: Vec<SanitizedTransaction>,
+    // the index ranges of the transactions, none of which conflict with each other in the same
+    // entry
+    entries: Vec<Range<usize>>,
+    last_slot: Slot,
+}
+
+impl BenchSetup {
+    fn new(workload: Workload) -> Self {
+        let GenesisConfigInfo {
+            mut genesis_config, ..
+        } = create_genesis_config(1_000_000_000);
+        let payers: Vec<_> = (0..ACCOUNT_COUNT).map(|_| Keypair::new()).collect();
+        for payer in &payers {
+            genesis_config.add_account(
+                payer.pubkey(),
+                AccountSharedData::new(1_000_000_000, 0, &system_program::id()),
+            );
+        }
+        let recipients: Vec<_> = (0..ACCOUNT_COUNT).map(|_| Pubkey::new_unique()).collect();
+        let zipf = WeightedIndex::new((1..=ACCOUNT_COUNT).map(|rank| 1.0 / rank as f64)).unwrap();
+        let mut rng = rand::thread_rng();
+        let transactions = (0..TRANSACTION_COUNT)
+            .map(|index| {
+                let (payer, recipient) = match workload {
+                    Workload::NoConflict => (index, index),
+                    Workload::HotAccount => (index, 0),
+                    Workload::Zipfian => (zipf.sample(&mut rng), zipf.sample(&mut rng)),
+                };
+                // the distinct amounts make the transactions unique even with the same accounts
+                SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+                    &payers[payer],
+                    &recipients[recipient],
+                    index as u64 + 1,
+                    genesis_config.hash(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let entries = split_into_entries(&transactions);
+
+        let bank_forks = BankForks::new_rw_arc(Bank::new_for_tests(&genesis_config));
+        let root_bank = bank_forks.read().unwrap().root_bank();
+        root_bank.set_fork_graph_in_program_cache(bank_forks.clone());
+
+        Self {
+            bank_forks,
+            transactions,
+            entries,
+            last_slot: 0,
+        }
+    }
+
+    fn new_bank(&mut self) -> BankWithScheduler {
+        let root_bank = self.bank_forks.read().unwrap().root_bank();
+        self.last_slot += 1;
+        let bank = Bank::new_from_parent(root_bank, &Pubkey::default(), self.last_slot);
+        self.bank_forks.write().unwrap().insert(bank)
+    }
+
+    fn remove_bank(&self, bank: BankWithScheduler) {
+        let slot = bank.slot();
+        drop(bank);
+        self.bank_forks.write().unwrap().remove(slot);
+    }
+}
+
+fn split_into_entries(transactions: &[SanitizedTransaction]) -> Vec<Range<usize>> {
+    let mut entries = vec![];
+    let mut entry_start = 0;
+    let mut write_locked = HashSet::new();
+    let mut read_locked = HashSet::new();
+    for (index, transaction) in transactions.iter().enumerate() {
+        let locks = transaction.get_account_locks_unchecked();
+        let conflicts = locks
+            .writable
+            .iter()
+            .any(|key| write_locked.contains(key) || read_locked.contains(key))
+            || locks.readonly.iter().any(|key| write_locked.contains(key));
+        if conflicts || index - entry_start == MAX_TRANSACTIONS_PER_ENTRY {
+            entries.push(entry_start..index);
+            entry_start = index;
+            write_locked.clear();
+            read_locked.clear();
+        }
+        write_locked.extend(locks.writable);
+        read_locked.extend(locks.readonly);
+    }
+    entries.push(entry_start..transactions.len());
+    entries
+}
+
+fn report_latencies(path: &str, workload: Workload, mut latencies: Vec<Duration>) {
+    if latencies.is_empty() {
+        return;
+    }
+    latencies.sort_unstable();
+    let percentile = |percent: usize| latencies[(latencies.len() - 1) * percent / 100];
+    info!(
+        "{path} ({workload:?}): p50 latency: {:?}, p99 latency: {:?}, over {} transactions",
+        percentile(50),
+        percentile(99),
+        latencies.len(),
+    );
+}
+
+fn bench_scheduler(
+    bencher: &mut Bencher,
+    workload: Workload,
+    completion_batching: Option<CompletionBatching>,
+) {
+    solana_logger::setup();
+    let mut setup = BenchSetup::new(workload);
+    // retains the events of a whole session, whose timestamps tell the latencies. there are up to
+    // 5 events per task, including a CompletionReceived without CompletionBatching.
+    let event_log = Arc::new(EventLog::new(TRANSACTION_COUNT * 5 + 16));
+    let config = SchedulerPoolConfig {
+        event_log: Some(event_log.clone()),
+        completion_batching,
+        ..SchedulerPoolConfig::default()
+    };
+    let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+    let pool = DefaultSchedulerPool::new_with_config(
+        None,
+        None,
+        None,
+        None,
+        ignored_prioritization_fee_cache,
+        config,
+    );
+    setup
+        .bank_forks
+        .write()
+        .unwrap()
+        .install_scheduler_pool(pool.clone());
+    let indexes: Vec<_> = (0..setup.transactions.len()).collect();
+    let mut latencies = vec![];
+    let mut completion_message_count = 0;
+    let mut completed_task_count = 0;
+
+    bencher.iter(|| {
+        let bank = setup.new_bank();
+        bank.schedule_transaction_executions(setup.transactions.iter().zip(&indexes));
+        assert_matches!(bank.wait_for_completed_scheduler(), Some(Ok((Ok(()), _))));
+        setup.remove_bank(bank);
+
+        let records = event_log.snapshot();
+        let session_start = records
+            .iter()
+            .rposition(|record| record.kind == EventKind::SessionStarted as u32)
+            .unwrap();
+        let session_started_ns = records[session_start].timestamp_ns;
+        let session_records = &records[session_start..];
+        latencies.extend(
+            session_records
+                .iter()
+                .filter(|record| record.kind == EventKind::TaskExecutionFinished as u32)
+                .map(|record| {
+                    Duration::from_nanos(record.timestamp_ns.saturating_sub(session_started_ns))
+                }),
+        );
+        let count_kind = |kind: EventKind| {
+            session_records
+                .iter()
+                .filter(|r| r.kind == kind as u32)
+                .count()
+        };
+        completion_message_count += count_kind(EventKind::CompletionReceived);
+        completed_task_count += count_kind(EventKind::TaskCompleted);
+    });
+    pool.shutdown_all();
+    let path = if completion_batching.is_some() {
+        "scheduler with completion batching"
+    } else {
+        "scheduler"
+    };
+    report_latencies(path, workload, latencies);
+    if completed_task_count > 0 {
+        info!(
+            "{path} ({workload:?}): {:.3} completion messages per task, over {} tasks",
+            completion_message_count as f64 / completed_task_count as f64,
+            completed_task_count,
+        );
+    }
+}
+
+fn bench_batches(bencher: &mut Bencher, workload: Workload) {
+    solana_logger::setup();
+    let mut setup = BenchSetup::new(workload);
+    let ignored_prioritization_fee_cache = PrioritizationFeeCache::new(0u64);
+    let mut latencies = vec![];
+
+    // executes the queued batches like process_batches(), recording when their transactions are
+    // executed since the start of the block
+    let execute_batches = |bank: &Arc<Bank>,
+                           batches: &mut Vec<TransactionBatchWithIndexes>,
+                           block_started_at: Instant,
+                           latencies: &mut Vec<Duration>| {
+        let mut timings = ExecuteTimings::default();
+        for batch_with_indexes in batches.drain(..) {
+            assert_matches!(
+                execute_batch(
+                    &batch_with_indexes,
+                    bank,
+                    None,
+                    None,
+                    &mut timings,
+                    None,
+                    &ignored_prioritization_fee_cache,
+                ),
+                Ok(())
+            );
+            let latency = block_started_at.elapsed();
+            latencies.extend(
+                batch_with_indexes
+                    .transaction_indexes
+                    .iter()
+                    .map(|_| latency),
+            );
+        }
+    };
+
+    bencher.iter(|| {
+        let bank = setup.new_bank();
+        let block_started_at = Instant::now();
+        let mut batches = vec![];
+        for entry in &setup.entries {
+            loop {
+                let batch = bank.prepare_sanitized_batch(&setup.transactions[entry.clone()]);
+                if batch.lock_results().iter().all(Result::is_ok) {
+                    batches.push(TransactionBatchWithIndexes {
+                        batch,
+                        transaction_indexes: entry.clone().collect(),
+                    });
+                    break;
+                }
+                // the entry conflicts with the queued ones, which must be executed first
+                assert!(!batches.is_empty());
+                drop(batch);
+                execute_batches(&bank, &mut batches, block_started_at, &mut latencies);
+            }
+        }
+        execute_batches(&bank, &mut batches, block_started_at, &mut latencies);
+        // the batches borrow the bank
+        drop(batches);
+        setup.remove_bank(bank);
+    });
+    report_latencies("batches", workload, latencies);
+}
+
+#[bench]
+fn bench_scheduler_no_conflict(bencher: &mut Bencher) {
+    bench_scheduler(bencher, Workload::NoConflict, None);
+}
+
+#[bench]
+fn bench_scheduler_no_conflict_completion_batching(bencher: &mut Bencher) {
+    bench_scheduler(bencher, Workload::NoConflict, Some(COMPLETION_BATCHING));
+}
+
+#[bench]
+fn bench_batches_no_conflict(bencher: &mut Bencher) {
+    bench_batches(bencher, Workload::NoConflict);
+}
+
+#[bench]
+fn bench_scheduler_hot_account(bencher: &mut Bencher) {
+    bench_scheduler(bencher, Workload::HotAccount, None);
+}
+
+#[bench]
+fn bench_scheduler_hot_account_completion_batching(bencher: &mut Bencher) {
+    bench_scheduler(bencher, Workload::HotAccount, Some(COMPLETION_BATCHING));
+}
+
+#[bench]
+fn bench_batches_hot_account(bencher: &mut Bencher) {
+    bench_batches(bencher, Workload::HotAccount);
+}
+
+#[bench]
+fn bench_scheduler_zipfian(bencher: &mut Bencher) {
+    bench_scheduler(bencher, Workload::Zipfian, None);
+}
+
+#[bench]
+fn bench_scheduler_zipfian_completion_batching(bencher: &mut Bencher) {
+    bench_scheduler(bencher, Workload::Zipfian, Some(COMPLETION_BATCHING));
+}
+
+#[bench]
+fn bench_batches_zipfian(bencher: &mut Bencher) {
+    bench_batches(bencher, Workload::Zipfian);
+}
@@ -0,0 +1,152 @@
+//! A [`TaskHandler`] which injects delays and failures into transaction executions, for chaos
+//! testing the robustness of replay (e.g. in local-cluster tests).
+//!
+//! [`ChaosHandler`] wraps [`DefaultTaskHandler`]. Because task handlers are stateless by design,
+//! its [`ChaosConfig`] is process-wide and is set with [`ChaosHandler::configure`]. Until then, it
+//! behaves exactly like [`DefaultTaskHandler`].
+
+use {
+    crate::{DefaultTaskHandler, HandlerContext, TaskHandler},
+    rand::{thread_rng, Rng},
+    solana_program_runtime::timings::ExecuteTimings,
+    solana_runtime::bank::Bank,
+    solana_sdk::transaction::{Result, SanitizedTransaction, TransactionError},
+    std::{
+        sync::{Arc, RwLock},
+        thread,
+        time::Duration,
+    },
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct ChaosConfig {
+    /// Probability of an execution to be delayed, in the range of `0.0..=1.0`.
+    pub delay_rate: f64,
+    /// Delayed executions sleep for a random duration up to this before executing.
+    pub max_delay: Duration,
+    /// Probability of an execution to fail, in the range of `0.0..=1.0`. Failed executions don't
+    /// execute the transaction at all.
+    pub failure_rate: f64,
+    /// Errors which failed executions result in, chosen randomly. Must not be empty if
+    /// `failure_rate` is positive.
+    pub errors: Vec<TransactionError>,
+}
+
+static CHAOS_CONFIG: RwLock<ChaosConfig> = RwLock::new(ChaosConfig {
+    delay_rate: 0.0,
+    max_delay: Duration::ZERO,
+    failure_rate: 0.0,
+    errors: Vec::new(),
+});
+
+#[derive(Debug)]
+pub struct ChaosHandler;
+
+impl ChaosHandler {
+    /// Replaces the process-wide config, taking effect from the next execution.
+    pub fn configure(config: ChaosConfig) {
+        assert!((0.0..=1.0).contains(&config.delay_rate));
+        assert!((0.0..=1.0).contains(&config.failure_rate));
+        assert!(config.failure_rate == 0.0 || !config.errors.is_empty());
+        *CHAOS_CONFIG.write().unwrap() = config;
+    }
+}
+
+impl TaskHandler for ChaosHandler {
+    fn handle(
+        result: &mut Result<()>,
+        timings: &mut ExecuteTimings,
+        bank: &Arc<Bank>,
+        transaction: &SanitizedTransaction,
+        index: usize,
+        handler_context: &HandlerContext,
+    ) {
+        // decide everything upfront so that the lock isn't held while sleeping or executing
+        let (delay, error) = {
+            let config = CHAOS_CONFIG.read().unwrap();
+            let mut rng = thread_rng();
+            let delay = rng
+                .gen_bool(config.delay_rate)
+                .then(|| config.max_delay.mul_f64(rng.gen()));
+            let error = rng
+                .gen_bool(config.failure_rate)
+                .then(|| config.errors[rng.gen_range(0..config.errors.len())].clone());
+            (delay, error)
+        };
+
+        if let Some(delay) = delay {
+            thread::sleep(delay);
+        }
+        if let Some(error) = error {
+            *result = Err(error);
+            return;
+        }
+        DefaultTaskHandler::handle(result, timings, bank, transaction, index, handler_context);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{tests::setup_dummy_fork_graph, PooledScheduler, SchedulerPool},
+        assert_matches::assert_matches,
+        solana_runtime::{
+            genesis_utils::{create_genesis_config, GenesisConfigInfo},
+            installed_scheduler_pool::{BankWithScheduler, SchedulingContext},
+            prioritization_fee_cache::PrioritizationFeeCache,
+        },
+        solana_sdk::system_transaction,
+    };
+
+    // This is the only test touching the process-wide config, so that tests don't interfere.
+    #[test]
+    fn test_chaos_handler() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let tx = &SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            2,
+            genesis_config.hash(),
+        ));
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = SchedulerPool::<PooledScheduler<ChaosHandler>, ChaosHandler>::new_dyn(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+        );
+
+        ChaosHandler::configure(ChaosConfig {
+            delay_rate: 1.0,
+            max_delay: Duration::from_millis(10),
+            failure_rate: 1.0,
+            errors: vec![TransactionError::AccountInUse],
+        });
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let scheduler = pool.take_scheduler(SchedulingContext::new(bank.clone()));
+        scheduler.schedule_execution(&(tx, 0));
+        let bank = BankWithScheduler::new(bank, Some(scheduler));
+        assert_matches!(
+            bank.wait_for_completed_scheduler(),
+            Some(Ok((Err(TransactionError::AccountInUse), _)))
+        );
+        assert_eq!(bank.transaction_count(), 0);
+
+        // without chaos, the handler executes the transaction as usual
+        ChaosHandler::configure(ChaosConfig::default());
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let scheduler = pool.take_scheduler(SchedulingContext::new(bank.clone()));
+        scheduler.schedule_execution(&(tx, 0));
+        let bank = BankWithScheduler::new(bank, Some(scheduler));
+        assert_matches!(bank.wait_for_completed_scheduler(), Some(Ok((Ok(()), _))));
+        assert_eq!(bank.transaction_count(), 1);
+    }
+}
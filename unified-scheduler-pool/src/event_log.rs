@@ -0,0 +1,198 @@
+//! An optional, fixed-size ring buffer of scheduler events for external profilers.
+//!
+//! Each event is recorded as an [`EventRecord`], whose layout is `#[repr(C)]` and stable across
+//! releases, so that tools like perf scripts or custom TUIs can reconstruct the scheduler's
+//! behavior from a dump without recompiling the validator with tracing enabled.
+//!
+//! The dump format produced by [`EventLog::dump`] is a fixed header followed by the records in
+//! the order of recording (oldest first), all encoded in little endian:
+//!
+//! | offset | size | field                                           |
+//! |--------|------|-------------------------------------------------|
+//! | 0      | 8    | magic: `b"SCHEDLOG"`                            |
+//! | 8      | 4    | format version: [`EVENT_LOG_FORMAT_VERSION`]    |
+//! | 12     | 4    | record size in bytes: 24                        |
+//! | 16     | 8    | number of records following this header         |
+//! | 24     | 24*n | records (`timestamp_ns`, `task_index`, `thread_id`, `kind`) |
+//!
+//! Recording is lock-free: a writer claims a slot by bumping an atomic cursor and then stores the
+//! record fields with relaxed atomics. Consequently, a dump taken while the scheduler is running
+//! can observe a record which is being overwritten concurrently. Profilers should tolerate such
+//! rare torn records, which is deemed acceptable to keep the recording overhead minimal.
+
+use std::{
+    io::{self, Write},
+    sync::atomic::{AtomicU64, Ordering::Relaxed},
+    time::Instant,
+};
+
+pub const EVENT_LOG_FORMAT_VERSION: u32 = 1;
+const EVENT_LOG_MAGIC: &[u8; 8] = b"SCHEDLOG";
+
+/// `thread_id` of events recorded by the scheduler thread. Handler threads use their index.
+pub const SCHEDULER_THREAD_ID: u32 = u32::MAX;
+
+/// Kind of [`EventRecord`]. The discriminants are part of the dump format and must not change.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    SessionStarted = 1,
+    SessionEnded = 2,
+    TaskReceived = 3,
+    TaskExecutionStarted = 4,
+    TaskExecutionFinished = 5,
+    TaskCompleted = 6,
+    // a message of completed tasks received from a handler thread, which carries several of them
+    // under CompletionBatching. Recorded before the TaskCompleted events of the tasks.
+    CompletionReceived = 7,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EventRecord {
+    /// Nanoseconds elapsed since the creation of the [`EventLog`].
+    pub timestamp_ns: u64,
+    /// Index of the task in the session, or `u64::MAX` for session-wide events.
+    pub task_index: u64,
+    pub thread_id: u32,
+    /// [`EventKind`] as `u32`.
+    pub kind: u32,
+}
+
+const RECORD_SIZE: usize = std::mem::size_of::<EventRecord>();
+const _: () = assert!(RECORD_SIZE == 24);
+
+#[derive(Default)]
+struct EventSlot {
+    timestamp_ns: AtomicU64,
+    task_index: AtomicU64,
+    // thread_id in the upper 32 bits and kind in the lower 32 bits
+    thread_id_and_kind: AtomicU64,
+}
+
+pub struct EventLog {
+    epoch: Instant,
+    cursor: AtomicU64,
+    slots: Box<[EventSlot]>,
+}
+
+impl std::fmt::Debug for EventLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventLog")
+            .field("capacity", &self.capacity())
+            .field("recorded_count", &self.recorded_count())
+            .finish()
+    }
+}
+
+impl EventLog {
+    /// Creates a log retaining the latest `capacity` events.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0);
+        Self {
+            epoch: Instant::now(),
+            cursor: AtomicU64::default(),
+            slots: (0..capacity).map(|_| EventSlot::default()).collect(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns the number of events recorded so far, including overwritten ones.
+    pub fn recorded_count(&self) -> u64 {
+        self.cursor.load(Relaxed)
+    }
+
+    pub fn record(&self, kind: EventKind, thread_id: u32, task_index: Option<usize>) {
+        let timestamp_ns = u64::try_from(self.epoch.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        let position = self.cursor.fetch_add(1, Relaxed);
+        let slot = &self.slots[(position % self.slots.len() as u64) as usize];
+        slot.timestamp_ns.store(timestamp_ns, Relaxed);
+        slot.task_index
+            .store(task_index.map_or(u64::MAX, |index| index as u64), Relaxed);
+        slot.thread_id_and_kind
+            .store((u64::from(thread_id) << 32) | kind as u64, Relaxed);
+    }
+
+    /// Returns the retained records, oldest first.
+    pub fn snapshot(&self) -> Vec<EventRecord> {
+        let end = self.cursor.load(Relaxed);
+        let start = end.saturating_sub(self.slots.len() as u64);
+        (start..end)
+            .map(|position| {
+                let slot = &self.slots[(position % self.slots.len() as u64) as usize];
+                let thread_id_and_kind = slot.thread_id_and_kind.load(Relaxed);
+                EventRecord {
+                    timestamp_ns: slot.timestamp_ns.load(Relaxed),
+                    task_index: slot.task_index.load(Relaxed),
+                    thread_id: (thread_id_and_kind >> 32) as u32,
+                    kind: thread_id_and_kind as u32,
+                }
+            })
+            .collect()
+    }
+
+    /// Writes the retained records in the format described in the module documentation.
+    pub fn dump(&self, writer: &mut impl Write) -> io::Result<()> {
+        let records = self.snapshot();
+        writer.write_all(EVENT_LOG_MAGIC)?;
+        writer.write_all(&EVENT_LOG_FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&(RECORD_SIZE as u32).to_le_bytes())?;
+        writer.write_all(&(records.len() as u64).to_le_bytes())?;
+        for record in records {
+            writer.write_all(&record.timestamp_ns.to_le_bytes())?;
+            writer.write_all(&record.task_index.to_le_bytes())?;
+            writer.write_all(&record.thread_id.to_le_bytes())?;
+            writer.write_all(&record.kind.to_le_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_log_wraps_around() {
+        let event_log = EventLog::new(2);
+        event_log.record(EventKind::SessionStarted, SCHEDULER_THREAD_ID, None);
+        event_log.record(EventKind::TaskReceived, SCHEDULER_THREAD_ID, Some(3));
+        event_log.record(EventKind::TaskExecutionStarted, 0, Some(3));
+        assert_eq!(event_log.recorded_count(), 3);
+
+        let records = event_log.snapshot();
+        assert_eq!(
+            records
+                .iter()
+                .map(|record| (record.kind, record.thread_id, record.task_index))
+                .collect::<Vec<_>>(),
+            vec![
+                (EventKind::TaskReceived as u32, SCHEDULER_THREAD_ID, 3),
+                (EventKind::TaskExecutionStarted as u32, 0, 3),
+            ]
+        );
+        assert!(records[0].timestamp_ns <= records[1].timestamp_ns);
+    }
+
+    #[test]
+    fn test_event_log_dump() {
+        let event_log = EventLog::new(4);
+        event_log.record(EventKind::TaskCompleted, SCHEDULER_THREAD_ID, Some(7));
+
+        let mut dump = vec![];
+        event_log.dump(&mut dump).unwrap();
+        assert_eq!(dump.len(), 24 + RECORD_SIZE);
+        assert_eq!(&dump[0..8], EVENT_LOG_MAGIC);
+        assert_eq!(dump[8..12], EVENT_LOG_FORMAT_VERSION.to_le_bytes());
+        assert_eq!(dump[16..24], 1u64.to_le_bytes());
+        assert_eq!(dump[32..40], 7u64.to_le_bytes());
+        assert_eq!(dump[40..44], SCHEDULER_THREAD_ID.to_le_bytes());
+        assert_eq!(
+            dump[44..48],
+            (EventKind::TaskCompleted as u32).to_le_bytes()
+        );
+    }
+}
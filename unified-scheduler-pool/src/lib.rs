@@ -8,35 +8,60 @@
 //! and commits any side-effects (i.e. on-chain state changes) into the associated `Bank` via
 //! `solana-ledger`'s helper function called `execute_batch()`.
 
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod event_log;
+mod self_test;
+
 use {
+    crate::event_log::{EventKind, EventLog, SCHEDULER_THREAD_ID},
     assert_matches::assert_matches,
-    crossbeam_channel::{select, unbounded, Receiver, SendError, Sender},
+    core_affinity::CoreId,
+    crossbeam_channel::{
+        bounded, never, select, unbounded, Receiver, SendError, Sender, TryRecvError,
+    },
     derivative::Derivative,
     log::*,
     solana_ledger::blockstore_processor::{
-        execute_batch, TransactionBatchWithIndexes, TransactionStatusSender,
+        execute_batch, TransactionBatchWithIndexes, TransactionStatusBatch,
+        TransactionStatusMessage, TransactionStatusSender,
     },
+    solana_metrics::{datapoint_error, datapoint_info, datapoint_warn},
+    solana_perf::thread::renice_this_thread,
     solana_program_runtime::timings::ExecuteTimings,
     solana_runtime::{
         bank::Bank,
         installed_scheduler_pool::{
             InstalledScheduler, InstalledSchedulerBox, InstalledSchedulerPool,
-            InstalledSchedulerPoolArc, ResultWithTimings, SchedulerId, SchedulingContext,
-            UninstalledScheduler, UninstalledSchedulerBox,
+            InstalledSchedulerPoolArc, ResultWithTimings, SchedulerError, SchedulerId,
+            SchedulerResult, SchedulingContext, SessionSummary, UninstalledScheduler,
+            UninstalledSchedulerBox,
         },
         prioritization_fee_cache::PrioritizationFeeCache,
     },
-    solana_sdk::transaction::{Result, SanitizedTransaction},
+    solana_sdk::{
+        clock::{Slot, MAX_PROCESSING_AGE},
+        hash::Hash,
+        transaction::{Result, SanitizedTransaction, TransactionError},
+    },
+    solana_svm::transaction_error_metrics::TransactionErrorMetrics,
     solana_unified_scheduler_logic::Task,
     solana_vote::vote_sender_types::ReplayVoteSender,
     std::{
+        any::Any,
+        collections::{HashSet, VecDeque},
         fmt::Debug,
         marker::PhantomData,
+        panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
         sync::{
-            atomic::{AtomicU64, Ordering::Relaxed},
-            Arc, Mutex, OnceLock, Weak,
+            atomic::{
+                AtomicBool, AtomicU64, AtomicUsize,
+                Ordering::{Acquire, Relaxed, Release},
+            },
+            Arc, Condvar, Mutex, OnceLock, Weak,
         },
         thread::{self, JoinHandle},
+        time::{Duration, Instant},
     },
 };
 
@@ -47,9 +72,35 @@ type AtomicSchedulerId = AtomicU64;
 // TransactionStatusSender; also, PohRecorder in the future)...
 #[derive(Debug)]
 pub struct SchedulerPool<S: SpawnableScheduler<TH>, TH: TaskHandler> {
-    scheduler_inners: Mutex<Vec<S::Inner>>,
+    // along with when each of them was returned, in the order of returning
+    scheduler_inners: Mutex<Vec<(S::Inner, Instant)>>,
+    // notified when a scheduler is returned, for the takers throttled by max_spawns_per_second
+    scheduler_returned: Condvar,
+    // the start of the current one-second window and the number of spawns in it
+    spawn_window: Mutex<(Instant, usize)>,
+    // the number of takes which have been throttled due to max_spawns_per_second
+    throttled_take_count: AtomicUsize,
+    // the number of takes which have found the pool empty, thus spawning a new scheduler
+    exhausted_take_count: AtomicUsize,
+    // the number of returned schedulers which have been dropped due to max_pooled_schedulers
+    spilled_scheduler_count: AtomicUsize,
+    // the number of scheduler and handler threads spawned and not stopped yet. pooled schedulers
+    // retain their threads until they're pruned by the watchdog thread.
+    spawned_thread_count: AtomicUsize,
     handler_count: usize,
     handler_context: HandlerContext,
+    config: SchedulerPoolConfig,
+    // the number of scheduler and handler threads which have panicked so far. see health().
+    panicked_thread_count: AtomicUsize,
+    // the number of sessions whose task counts didn't match. see record_task_counts().
+    integrity_violation_count: AtomicUsize,
+    // the progress of every scheduler alive, for detecting stalled sessions. see health().
+    scheduler_progresses: Mutex<Vec<Weak<SchedulerProgress>>>,
+    // the number of takes currently waiting due to max_spawns_per_second. see health().
+    waiting_take_count: AtomicUsize,
+    // set by shutdown_all() under the lock of scheduler_inners, after which returned schedulers
+    // are stopped instead of being pooled.
+    is_shut_down: AtomicBool,
     // weak_self could be elided by changing InstalledScheduler::take_scheduler()'s receiver to
     // Arc<Self> from &Self, because SchedulerPool is used as in the form of Arc<SchedulerPool>
     // almost always. But, this would cause wasted and noisy Arc::clone()'s at every call sites.
@@ -65,12 +116,387 @@ pub struct SchedulerPool<S: SpawnableScheduler<TH>, TH: TaskHandler> {
     _phantom: PhantomData<TH>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct HandlerContext {
     log_messages_bytes_limit: Option<usize>,
     transaction_status_sender: Option<TransactionStatusSender>,
     replay_vote_sender: Option<ReplayVoteSender>,
     prioritization_fee_cache: Arc<PrioritizationFeeCache>,
+    pre_check_transaction_age: bool,
+}
+
+/// Optional knobs of [`SchedulerPool`], which aren't needed to be specified by most callers.
+#[derive(Clone, Debug)]
+pub struct SchedulerPoolConfig {
+    /// Runs the main loops of handler threads. Defaults to [`StdThreadHandlerExecutor`].
+    pub handler_executor: Arc<dyn HandlerExecutor>,
+    /// Records scheduler events for external profilers if specified. See [`event_log`].
+    pub event_log: Option<Arc<EventLog>>,
+    /// When to spawn the threads of newly-created schedulers. Defaults to
+    /// [`ThreadStartPolicy::Eager`].
+    pub thread_start_policy: ThreadStartPolicy,
+    /// Checks the age of transactions' blockhashes before preparing their executions, so that
+    /// transactions with expired blockhashes fail with `BlockhashNotFound` without the overhead of
+    /// `execute_batch()`. The results stay the same either way. Defaults to `false`, as this is
+    /// only worthwhile when replaying old forks full of such transactions.
+    pub pre_check_transaction_age: bool,
+    /// Lets handler threads report their completed tasks to the scheduler thread in batches. Each
+    /// completion is reported individually by default.
+    pub completion_batching: Option<CompletionBatching>,
+    /// Lets handler threads send the transaction statuses of their executed tasks in batches,
+    /// instead of a message per transaction. Only effective if a `TransactionStatusSender` is
+    /// given. Disabled by default.
+    pub transaction_status_batching: Option<TransactionStatusBatching>,
+    /// Caps the number of schedulers newly spawned per second, to protect the box from bursts of
+    /// thread creation when many forks appear at once. Once capped, `take_scheduler()` waits for a
+    /// scheduler to be returned or for the next second, whichever comes first. Unlimited by
+    /// default.
+    pub max_spawns_per_second: Option<usize>,
+    /// Warns when the threads of all schedulers outnumber the cpu cores by more than the given
+    /// factor, optionally clamping the handler count. Disabled by default.
+    pub thread_oversubscription: Option<ThreadOversubscription>,
+    /// Stops the threads of the schedulers which have been idle in the pool for longer than the
+    /// given duration, by spawning a watchdog thread. Otherwise, a long-running validator retains
+    /// the threads of as many schedulers as it has ever needed at once (e.g. while replaying many
+    /// forks). Disabled by default.
+    pub idle_scheduler_ttl: Option<Duration>,
+    /// Caps the number of schedulers retained in the pool. Schedulers returned beyond it are
+    /// dropped, stopping their threads, instead of being pooled. Otherwise, the pool retains as
+    /// many schedulers as have ever been in use at once (e.g. during a fork-heavy period).
+    /// Unlimited by default.
+    pub max_pooled_schedulers: Option<usize>,
+    /// Verifies that tasks are committed in the order they're scheduled (i.e. the entry order
+    /// for block verification), which any locking scheme must preserve for conflicting
+    /// transactions. Divergences are logged in detail and degrade the pool's health. This costs
+    /// per-task bookkeeping in the scheduler thread, so it's meant for debugging. Defaults to
+    /// `false`.
+    pub verify_commit_order: bool,
+    /// Makes `schedule_execution()` block while the given number of tasks have been scheduled but
+    /// not completed yet, so that a burst of transactions can't pile up in memory unboundedly.
+    /// Unlimited by default.
+    pub max_in_flight_task_count: Option<usize>,
+    /// Fails transactions whose message hashes have already been seen in the session with
+    /// `AlreadyProcessed` in the scheduler thread, sparing their dispatching and execution. The
+    /// results stay the same either way, as the bank's status cache rejects them likewise.
+    /// Defaults to `false`, as this costs a hash set lookup per task in the scheduler thread.
+    pub reject_duplicate_transactions: bool,
+    /// Pins handler threads to the given cpu cores, assigned round-robin by handler index, so
+    /// that they can gain from cache locality. Note that this pins the executor's threads which
+    /// happen to run the handlers, if a custom [`HandlerExecutor`] is used. Unpinned by default.
+    pub handler_core_ids: Option<Vec<usize>>,
+    /// Adds the given adjustment to the nice value of scheduler threads. Negative values
+    /// prioritize them over other threads, so that tasks keep being dispatched under load, which
+    /// requires `CAP_SYS_NICE` on Linux and isn't supported elsewhere. Failures are logged and
+    /// ignored. Defaults to `0`.
+    pub scheduler_thread_niceness_adj: i8,
+    /// Reports a session as stalled in [`SchedulerPool::health()`], once none of its dispatched
+    /// tasks has been completed for the given duration. Defaults to 10 seconds.
+    pub stall_threshold: Duration,
+}
+
+impl Default for SchedulerPoolConfig {
+    fn default() -> Self {
+        Self {
+            handler_executor: Arc::new(StdThreadHandlerExecutor),
+            event_log: None,
+            thread_start_policy: ThreadStartPolicy::Eager,
+            pre_check_transaction_age: false,
+            completion_batching: None,
+            transaction_status_batching: None,
+            max_spawns_per_second: None,
+            thread_oversubscription: None,
+            idle_scheduler_ttl: None,
+            max_pooled_schedulers: None,
+            verify_commit_order: false,
+            max_in_flight_task_count: None,
+            reject_duplicate_transactions: false,
+            handler_core_ids: None,
+            scheduler_thread_niceness_adj: 0,
+            stall_threshold: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Detection of misconfigurations which make scheduler and handler threads thrash, due to too
+/// many of them being spawned for the available cpu cores.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThreadOversubscription {
+    /// The number of threads per cpu core, beyond which a warning is logged and reported as a
+    /// metric on every thread spawn.
+    pub max_threads_per_core: usize,
+    /// Lowers the handler count at the pool creation, so that at least the threads of a single
+    /// scheduler stay within the limit.
+    pub auto_clamp: bool,
+}
+
+impl ThreadOversubscription {
+    fn max_thread_count(&self, cpu_core_count: usize) -> usize {
+        cpu_core_count.saturating_mul(self.max_threads_per_core)
+    }
+
+    fn clamp_handler_count(&self, handler_count: usize, cpu_core_count: usize) -> usize {
+        // spare one for the scheduler thread
+        handler_count
+            .min(self.max_thread_count(cpu_core_count).saturating_sub(1))
+            .max(1)
+    }
+}
+
+/// Limits on how handler threads accumulate transaction statuses before sending them to the
+/// `TransactionStatusSender` together, reducing the messages flooding its channel under replay.
+///
+/// Like [`CompletionBatching`], statuses are only accumulated while the handler has more runnable
+/// tasks to execute. They're always sent before the completion of the task which ran the handler
+/// out of work is reported, so that they still precede the freeze message of the bank.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TransactionStatusBatching {
+    /// The maximum number of transactions whose statuses are sent together.
+    pub max_count: usize,
+    /// The maximum duration for which the first accumulated status can be delayed.
+    pub max_delay: Duration,
+}
+
+// Owned by each handler thread for TransactionStatusBatching. The handler executes its tasks with
+// local_sender, whose statuses are merged here before being forwarded to the actual sender.
+struct TransactionStatusBatcher {
+    batching: TransactionStatusBatching,
+    sender: TransactionStatusSender,
+    local_sender: TransactionStatusSender,
+    local_receiver: Receiver<TransactionStatusMessage>,
+    // along with when the first status of it was accumulated
+    held_batch: Option<(TransactionStatusBatch, Instant)>,
+}
+
+impl TransactionStatusBatcher {
+    fn new(batching: TransactionStatusBatching, sender: TransactionStatusSender) -> Self {
+        let (local_sender, local_receiver) = unbounded();
+        Self {
+            batching,
+            sender,
+            local_sender: TransactionStatusSender {
+                sender: local_sender,
+            },
+            local_receiver,
+            held_batch: None,
+        }
+    }
+
+    // Accumulates the statuses sent since the last call, flushing them if either of the limits
+    // is reached or the handler has no more tasks to execute.
+    fn accumulate(&mut self, has_more_tasks: bool) {
+        while let Ok(message) = self.local_receiver.try_recv() {
+            match message {
+                TransactionStatusMessage::Batch(batch) => self.hold(batch),
+                // not sent by execute_batch(). forwarded just in case, retaining the order.
+                TransactionStatusMessage::Freeze(_) => {
+                    self.flush();
+                    self.send(message);
+                }
+            }
+        }
+
+        if let Some((batch, held_since)) = &self.held_batch {
+            let TransactionStatusBatching {
+                max_count,
+                max_delay,
+            } = self.batching;
+            if !has_more_tasks
+                || batch.transactions.len() >= max_count
+                || held_since.elapsed() >= max_delay
+            {
+                self.flush();
+            }
+        }
+    }
+
+    fn hold(&mut self, batch: TransactionStatusBatch) {
+        match &mut self.held_batch {
+            Some((held_batch, _)) if Arc::ptr_eq(&held_batch.bank, &batch.bank) => {
+                let TransactionStatusBatch {
+                    bank: _,
+                    transactions,
+                    execution_results,
+                    balances,
+                    token_balances,
+                    rent_debits,
+                    transaction_indexes,
+                } = batch;
+                held_batch.transactions.extend(transactions);
+                held_batch.execution_results.extend(execution_results);
+                held_batch
+                    .balances
+                    .pre_balances
+                    .extend(balances.pre_balances);
+                held_batch
+                    .balances
+                    .post_balances
+                    .extend(balances.post_balances);
+                held_batch
+                    .token_balances
+                    .pre_token_balances
+                    .extend(token_balances.pre_token_balances);
+                held_batch
+                    .token_balances
+                    .post_token_balances
+                    .extend(token_balances.post_token_balances);
+                held_batch.rent_debits.extend(rent_debits);
+                held_batch.transaction_indexes.extend(transaction_indexes);
+            }
+            _ => {
+                self.flush();
+                self.held_batch = Some((batch, Instant::now()));
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some((batch, _)) = self.held_batch.take() {
+            self.send(TransactionStatusMessage::Batch(batch));
+        }
+    }
+
+    fn send(&self, message: TransactionStatusMessage) {
+        // like TransactionStatusSender, tolerate the disconnection at shutdown
+        if self.sender.sender.send(message).is_err() {
+            trace!("transaction status send failed");
+        }
+    }
+}
+
+fn detected_cpu_core_count() -> Option<usize> {
+    thread::available_parallelism()
+        .ok()
+        .map(|non_zero| non_zero.get())
+}
+
+/// Limits on how handler threads hold back completed tasks before reporting them to the scheduler
+/// thread together, reducing its wake-ups under bursty completion.
+///
+/// Completions are only held back while the handler has more runnable tasks to execute. So, they
+/// aren't delayed at all once the handler runs out of work, regardless of these limits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompletionBatching {
+    /// The maximum number of completed tasks reported together.
+    pub max_count: usize,
+    /// The maximum duration for which the first held-back completion can be delayed.
+    pub max_delay: Duration,
+}
+
+/// When a scheduler spawns its scheduler and handler threads. Pooled schedulers retain their
+/// threads, so this only affects schedulers which are newly created by `take_scheduler()` due to
+/// the pool being empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThreadStartPolicy {
+    /// Spawn threads when the scheduler is created, so that the first `schedule_execution()`
+    /// doesn't pay the spawn cost. Suitable for banks which are sure to receive transactions, like
+    /// the ones for the upcoming leader slots.
+    Eager,
+    /// Spawn threads on the first `schedule_execution()`, so that the schedulers of the banks
+    /// which never receive transactions (commonly for skipped slots) never spawn them.
+    Lazy,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SchedulerPoolHealth {
+    Healthy,
+    Degraded { reason: String },
+    Failed { reason: String },
+}
+
+// Counts the panic of the current thread into the pool's health signals, when dropped while
+// unwinding.
+struct PanicDetector<'a>(&'a AtomicUsize);
+
+impl Drop for PanicDetector<'_> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.0.fetch_add(1, Relaxed);
+        }
+    }
+}
+
+// The failure of the current session, which is reported in place of its result. Recorded by the
+// threads of the scheduler before they exit.
+#[derive(Debug, Default)]
+struct SessionError(Mutex<Option<SchedulerError>>);
+
+impl SessionError {
+    fn record(&self, error: SchedulerError) {
+        let mut recorded = self.0.lock().expect("not poisoned");
+        match &*recorded {
+            // a panic explains the disconnections caused by it, so it's retained over them.
+            Some(SchedulerError::ThreadPanicked(_)) => {}
+            Some(_) if !matches!(error, SchedulerError::ThreadPanicked(_)) => {}
+            _ => *recorded = Some(error),
+        }
+    }
+
+    fn get(&self) -> Option<SchedulerError> {
+        self.0.lock().expect("not poisoned").clone()
+    }
+
+    fn take(&self) -> Option<SchedulerError> {
+        self.0.lock().expect("not poisoned").take()
+    }
+}
+
+// The progress of a scheduler's dispatched tasks, which is updated by its scheduler thread and
+// checked by SchedulerPool::health() for stalls.
+#[derive(Debug)]
+struct SchedulerProgress {
+    scheduler_id: SchedulerId,
+    created_at: Instant,
+    active_task_count: AtomicUsize,
+    // since created_at, as Instant can't be stored atomically
+    last_progress_us: AtomicU64,
+}
+
+impl SchedulerProgress {
+    fn new(scheduler_id: SchedulerId) -> Self {
+        Self {
+            scheduler_id,
+            created_at: Instant::now(),
+            active_task_count: AtomicUsize::default(),
+            last_progress_us: AtomicU64::default(),
+        }
+    }
+
+    fn record_progress(&self, progressed_at: Instant) {
+        let last_progress = progressed_at.saturating_duration_since(self.created_at);
+        self.last_progress_us
+            .store(last_progress.as_micros() as u64, Relaxed);
+    }
+
+    // Released after record_progress(), so that a newly-active scheduler isn't mistaken as stalled
+    // since its previous progress.
+    fn set_active_task_count(&self, active_task_count: usize) {
+        self.active_task_count.store(active_task_count, Release);
+    }
+
+    // Returns the number of dispatched tasks and how long none of them has been completed, unless
+    // idle.
+    fn stalled_for(&self) -> Option<(usize, Duration)> {
+        let active_task_count = self.active_task_count.load(Acquire);
+        if active_task_count == 0 {
+            return None;
+        }
+        let last_progress = Duration::from_micros(self.last_progress_us.load(Relaxed));
+        Some((
+            active_task_count,
+            self.created_at.elapsed().saturating_sub(last_progress),
+        ))
+    }
+}
+
+// Panic payloads are usually either &str or String, as created by panic!().
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_owned()
+    }
 }
 
 pub type DefaultSchedulerPool =
@@ -83,31 +509,156 @@ where
 {
     // Some internal impl and test code want an actual concrete type, NOT the
     // `dyn InstalledSchedulerPool`. So don't merge this into `Self::new_dyn()`.
-    fn new(
+    pub fn new(
+        handler_count: Option<usize>,
+        log_messages_bytes_limit: Option<usize>,
+        transaction_status_sender: Option<TransactionStatusSender>,
+        replay_vote_sender: Option<ReplayVoteSender>,
+        prioritization_fee_cache: Arc<PrioritizationFeeCache>,
+    ) -> Arc<Self> {
+        Self::new_with_config(
+            handler_count,
+            log_messages_bytes_limit,
+            transaction_status_sender,
+            replay_vote_sender,
+            prioritization_fee_cache,
+            SchedulerPoolConfig::default(),
+        )
+    }
+
+    pub fn new_with_config(
         handler_count: Option<usize>,
         log_messages_bytes_limit: Option<usize>,
         transaction_status_sender: Option<TransactionStatusSender>,
         replay_vote_sender: Option<ReplayVoteSender>,
         prioritization_fee_cache: Arc<PrioritizationFeeCache>,
+        config: SchedulerPoolConfig,
     ) -> Arc<Self> {
-        let handler_count = handler_count.unwrap_or(1);
+        let mut handler_count = handler_count.unwrap_or(1);
+        if let Some((oversubscription, cpu_core_count)) = config
+            .thread_oversubscription
+            .filter(|oversubscription| oversubscription.auto_clamp)
+            .zip(detected_cpu_core_count())
+        {
+            let clamped_handler_count =
+                oversubscription.clamp_handler_count(handler_count, cpu_core_count);
+            if clamped_handler_count != handler_count {
+                warn!(
+                    "clamping handler_count from {handler_count} to {clamped_handler_count} for \
+                     {cpu_core_count} cpu cores"
+                );
+                handler_count = clamped_handler_count;
+            }
+        }
         // we're hard-coding the number of handler thread to 1, meaning this impl is currently
         // single-threaded still.
         assert_eq!(handler_count, 1); // replace this with assert!(handler_count >= 1) later
 
-        Arc::new_cyclic(|weak_self| Self {
+        // a zero cap would make schedule_execution() block forever.
+        assert_ne!(config.max_in_flight_task_count, Some(0));
+        assert!(!config
+            .handler_core_ids
+            .as_ref()
+            .is_some_and(|core_ids| core_ids.is_empty()));
+
+        let pool = Arc::new_cyclic(|weak_self| Self {
             scheduler_inners: Mutex::default(),
+            scheduler_returned: Condvar::new(),
+            spawn_window: Mutex::new((Instant::now(), 0)),
+            throttled_take_count: AtomicUsize::default(),
+            exhausted_take_count: AtomicUsize::default(),
+            spilled_scheduler_count: AtomicUsize::default(),
+            spawned_thread_count: AtomicUsize::default(),
             handler_count,
             handler_context: HandlerContext {
                 log_messages_bytes_limit,
                 transaction_status_sender,
                 replay_vote_sender,
                 prioritization_fee_cache,
+                pre_check_transaction_age: config.pre_check_transaction_age,
             },
+            config,
+            panicked_thread_count: AtomicUsize::default(),
+            integrity_violation_count: AtomicUsize::default(),
+            scheduler_progresses: Mutex::default(),
+            waiting_take_count: AtomicUsize::default(),
+            is_shut_down: AtomicBool::default(),
             weak_self: weak_self.clone(),
             next_scheduler_id: AtomicSchedulerId::default(),
             _phantom: PhantomData,
-        })
+        });
+        if let Some(idle_scheduler_ttl) = pool.config.idle_scheduler_ttl {
+            Self::spawn_watchdog_thread(Arc::downgrade(&pool), idle_scheduler_ttl);
+        }
+        pool
+    }
+
+    // The watchdog thread only holds the pool weakly, exiting once the pool is dropped.
+    fn spawn_watchdog_thread(pool: Weak<Self>, idle_scheduler_ttl: Duration) {
+        const MAX_WATCHDOG_INTERVAL: Duration = Duration::from_secs(10);
+        let interval = idle_scheduler_ttl.min(MAX_WATCHDOG_INTERVAL);
+        thread::Builder::new()
+            .name("solScWatchdog".to_owned())
+            .spawn(move || loop {
+                thread::sleep(interval);
+                let Some(pool) = pool.upgrade() else {
+                    break;
+                };
+                pool.prune_idle_schedulers(idle_scheduler_ttl);
+            })
+            .unwrap();
+    }
+
+    // Drops the schedulers which have been idle for longer than `idle_scheduler_ttl`, stopping
+    // their threads. Returns the number of the pruned schedulers.
+    fn prune_idle_schedulers(&self, idle_scheduler_ttl: Duration) -> usize {
+        let now = Instant::now();
+        let idle_scheduler_inners: Vec<_> = {
+            let mut scheduler_inners = self.scheduler_inners.lock().expect("not poisoned");
+            // the longest-idle schedulers are at the front, as take_scheduler() pops from the back
+            let idle_count = scheduler_inners.partition_point(|(_, returned_at)| {
+                now.saturating_duration_since(*returned_at) > idle_scheduler_ttl
+            });
+            scheduler_inners.drain(..idle_count).collect()
+        };
+        let pruned_count = idle_scheduler_inners.len();
+        // joining the threads could take a while, so this is done outside the lock.
+        drop(idle_scheduler_inners);
+        if pruned_count > 0 {
+            datapoint_info!(
+                "unified_scheduler-idle_scheduler_pruning",
+                ("pruned_count", pruned_count, i64),
+                (
+                    "spawned_thread_count",
+                    self.spawned_thread_count.load(Relaxed),
+                    i64
+                ),
+            );
+        }
+        pruned_count
+    }
+
+    /// Stops the threads of all the pooled schedulers, waiting for them to exit. Returns the number
+    /// of the stopped schedulers.
+    ///
+    /// Pooled schedulers refer to their pool. So, neither the pool nor their threads are ever
+    /// dropped otherwise. The schedulers still in use are stopped when they're returned
+    /// afterwards. No scheduler should be taken after calling this.
+    pub fn shutdown_all(&self) -> usize {
+        let scheduler_inners = {
+            let mut scheduler_inners = self.scheduler_inners.lock().expect("not poisoned");
+            self.is_shut_down.store(true, Relaxed);
+            std::mem::take(&mut *scheduler_inners)
+        };
+        let stopped_count = scheduler_inners.len();
+        // joining the threads could take a while, so this is done outside the lock.
+        drop(scheduler_inners);
+        info!(
+            "shut down {stopped_count} pooled scheduler(s), leaving {} thread(s) of schedulers \
+             in use",
+            self.spawned_thread_count.load(Relaxed),
+        );
+        stopped_count
     }
 
     // This apparently-meaningless wrapper is handy, because some callers explicitly want
@@ -128,6 +679,24 @@ where
         )
     }
 
+    pub fn new_dyn_with_config(
+        handler_count: Option<usize>,
+        log_messages_bytes_limit: Option<usize>,
+        transaction_status_sender: Option<TransactionStatusSender>,
+        replay_vote_sender: Option<ReplayVoteSender>,
+        prioritization_fee_cache: Arc<PrioritizationFeeCache>,
+        config: SchedulerPoolConfig,
+    ) -> InstalledSchedulerPoolArc {
+        Self::new_with_config(
+            handler_count,
+            log_messages_bytes_limit,
+            transaction_status_sender,
+            replay_vote_sender,
+            prioritization_fee_cache,
+            config,
+        )
+    }
+
     // See a comment at the weak_self field for justification of this method's existence.
     fn self_arc(&self) -> Arc<Self> {
         self.weak_self
@@ -135,33 +704,283 @@ where
             .expect("self-referencing Arc-ed pool")
     }
 
+    /// Returns the health of this pool, based on the internal signals which indicate that the
+    /// scheduling subsystem can't work properly anymore.
+    ///
+    /// Specifically, this is `Failed` if the pool's internal state is poisoned. It's `Degraded` if
+    /// any of the scheduler or handler threads has panicked, any task has been lost, any session
+    /// has stalled beyond `SchedulerPoolConfig::stall_threshold`, or the pool is exhausted to the
+    /// extent that takers are waiting due to `SchedulerPoolConfig::max_spawns_per_second`. Unlike
+    /// the former two, the latter two signals recover by themselves.
+    pub fn health(&self) -> SchedulerPoolHealth {
+        if self.scheduler_inners.is_poisoned() {
+            return SchedulerPoolHealth::Failed {
+                reason: "pooled schedulers are poisoned".to_owned(),
+            };
+        }
+        let panicked_thread_count = self.panicked_thread_count.load(Relaxed);
+        if panicked_thread_count > 0 {
+            return SchedulerPoolHealth::Degraded {
+                reason: format!("{panicked_thread_count} scheduler thread(s) have panicked"),
+            };
+        }
+        let integrity_violation_count = self.integrity_violation_count.load(Relaxed);
+        if integrity_violation_count > 0 {
+            return SchedulerPoolHealth::Degraded {
+                reason: format!(
+                    "task counts or commit order of {integrity_violation_count} session(s) have \
+                     mismatched"
+                ),
+            };
+        }
+        if let Some((scheduler_id, active_task_count, stalled_for)) = self.longest_stall() {
+            return SchedulerPoolHealth::Degraded {
+                reason: format!(
+                    "scheduler {scheduler_id} has completed none of {active_task_count} \
+                     dispatched task(s) for {stalled_for:?}"
+                ),
+            };
+        }
+        let waiting_take_count = self.waiting_take_count.load(Relaxed);
+        if waiting_take_count > 0 {
+            return SchedulerPoolHealth::Degraded {
+                reason: format!(
+                    "{waiting_take_count} take(s) are waiting for a scheduler due to \
+                     max_spawns_per_second"
+                ),
+            };
+        }
+        SchedulerPoolHealth::Healthy
+    }
+
+    fn register_scheduler_progress(&self, scheduler_id: SchedulerId) -> Arc<SchedulerProgress> {
+        let progress = Arc::new(SchedulerProgress::new(scheduler_id));
+        let mut scheduler_progresses = self.scheduler_progresses.lock().expect("not poisoned");
+        // prune the progresses of dropped schedulers
+        scheduler_progresses.retain(|progress| progress.strong_count() > 0);
+        scheduler_progresses.push(Arc::downgrade(&progress));
+        progress
+    }
+
+    // Returns the scheduler which has stalled the longest beyond stall_threshold, if any, along
+    // with its number of dispatched tasks.
+    fn longest_stall(&self) -> Option<(SchedulerId, usize, Duration)> {
+        self.scheduler_progresses
+            .lock()
+            .expect("not poisoned")
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter_map(|progress| {
+                let (active_task_count, stalled_for) = progress.stalled_for()?;
+                (stalled_for >= self.config.stall_threshold).then_some((
+                    progress.scheduler_id,
+                    active_task_count,
+                    stalled_for,
+                ))
+            })
+            .max_by_key(|&(_, _, stalled_for)| stalled_for)
+    }
+
+    /// Cross-checks the task counts of a finished session, which must all match unless some task
+    /// has been lost (e.g. due to a dropped message). Only counts are compared, so that the
+    /// scheduler thread doesn't pay for per-task bookkeeping.
+    fn record_task_counts(&self, scheduler_id: SchedulerId, counts: &TaskCounts) {
+        if counts.is_consistent() {
+            return;
+        }
+        let integrity_violation_count = self.integrity_violation_count.fetch_add(1, Relaxed) + 1;
+        error!("scheduler {scheduler_id}: task counts have mismatched: {counts:?}");
+        datapoint_error!(
+            "unified_scheduler-integrity_violation",
+            ("scheduler_id", scheduler_id, i64),
+            ("sent", counts.sent, i64),
+            ("received", counts.received, i64),
+            ("duplicate", counts.duplicate, i64),
+            ("dispatched", counts.dispatched, i64),
+            ("executed", counts.executed, i64),
+            ("aborted", counts.aborted, i64),
+            ("completed", counts.completed, i64),
+            ("integrity_violation_count", integrity_violation_count, i64),
+        );
+    }
+
+    /// Reports the commit order divergences of a finished session, detected with
+    /// `SchedulerPoolConfig::verify_commit_order`.
+    fn record_commit_order_divergences(
+        &self,
+        scheduler_id: SchedulerId,
+        slot: Slot,
+        divergences: &[CommitOrderDivergence],
+    ) {
+        if divergences.is_empty() {
+            return;
+        }
+        let integrity_violation_count = self.integrity_violation_count.fetch_add(1, Relaxed) + 1;
+        error!(
+            "scheduler {scheduler_id}: commit order has diverged from the scheduled order at slot \
+             {slot}: {divergences:?}"
+        );
+        let first_divergence = &divergences[0];
+        datapoint_error!(
+            "unified_scheduler-commit_order_divergence",
+            ("scheduler_id", scheduler_id, i64),
+            ("slot", slot, i64),
+            ("divergence_count", divergences.len(), i64),
+            ("first_position", first_divergence.position, i64),
+            (
+                "first_expected_index",
+                first_divergence.expected_index.map(|index| index as i64),
+                Option<i64>
+            ),
+            ("first_committed_index", first_divergence.committed_index, i64),
+            ("integrity_violation_count", integrity_violation_count, i64),
+        );
+    }
+
     fn new_scheduler_id(&self) -> SchedulerId {
         self.next_scheduler_id.fetch_add(1, Relaxed)
     }
 
     fn return_scheduler(&self, scheduler: S::Inner) {
-        self.scheduler_inners
-            .lock()
-            .expect("not poisoned")
-            .push(scheduler);
+        let mut scheduler_inners = self.scheduler_inners.lock().expect("not poisoned");
+        if self.is_shut_down.load(Relaxed) {
+            drop(scheduler_inners);
+            // stops its threads
+            drop(scheduler);
+            return;
+        }
+        let pooled_count = scheduler_inners.len();
+        if self
+            .config
+            .max_pooled_schedulers
+            .is_some_and(|max_pooled_schedulers| pooled_count >= max_pooled_schedulers)
+        {
+            drop(scheduler_inners);
+            // stops its threads
+            drop(scheduler);
+            let spilled_scheduler_count = self.spilled_scheduler_count.fetch_add(1, Relaxed) + 1;
+            datapoint_info!(
+                "unified_scheduler-scheduler_spill",
+                ("pooled_count", pooled_count, i64),
+                ("spilled_scheduler_count", spilled_scheduler_count, i64),
+            );
+            return;
+        }
+        scheduler_inners.push((scheduler, Instant::now()));
+        drop(scheduler_inners);
+        self.scheduler_returned.notify_one();
     }
 
     fn do_take_scheduler(&self, context: SchedulingContext) -> S {
-        // pop is intentional for filo, expecting relatively warmed-up scheduler due to having been
-        // returned recently
-        if let Some(inner) = self.scheduler_inners.lock().expect("not poisoned").pop() {
-            S::from_inner(inner, context)
+        let mut scheduler_inners = self.scheduler_inners.lock().expect("not poisoned");
+        let mut throttled_since = None;
+        loop {
+            // pop is intentional for filo, expecting relatively warmed-up scheduler due to having
+            // been returned recently
+            if let Some((inner, _returned_at)) = scheduler_inners.pop() {
+                drop(scheduler_inners);
+                self.report_throttled_take(throttled_since, true);
+                return S::from_inner(inner, context);
+            }
+            match self.reserve_spawn() {
+                Ok(()) => {
+                    drop(scheduler_inners);
+                    self.report_throttled_take(throttled_since, false);
+                    self.report_exhausted_take();
+                    return S::spawn(self.self_arc(), context);
+                }
+                Err(wait) => {
+                    if throttled_since.is_none() {
+                        throttled_since = Some(Instant::now());
+                        self.waiting_take_count.fetch_add(1, Relaxed);
+                    }
+                    scheduler_inners = self
+                        .scheduler_returned
+                        .wait_timeout(scheduler_inners, wait)
+                        .expect("not poisoned")
+                        .0;
+                }
+            }
+        }
+    }
+
+    // Returns how long to wait until the next spawn is allowed, if max_spawns_per_second is
+    // reached.
+    fn reserve_spawn(&self) -> std::result::Result<(), Duration> {
+        let Some(max_spawns_per_second) = self.config.max_spawns_per_second else {
+            return Ok(());
+        };
+        const WINDOW: Duration = Duration::from_secs(1);
+        let mut spawn_window = self.spawn_window.lock().unwrap();
+        let (window_start, spawn_count) = &mut *spawn_window;
+        if window_start.elapsed() >= WINDOW {
+            *window_start = Instant::now();
+            *spawn_count = 0;
+        }
+        if *spawn_count < max_spawns_per_second {
+            *spawn_count += 1;
+            Ok(())
         } else {
-            S::spawn(self.self_arc(), context)
+            Err(WINDOW.saturating_sub(window_start.elapsed()))
+        }
+    }
+
+    fn report_throttled_take(&self, throttled_since: Option<Instant>, reused: bool) {
+        let Some(throttled_since) = throttled_since else {
+            return;
+        };
+        self.waiting_take_count.fetch_sub(1, Relaxed);
+        let throttled_take_count = self.throttled_take_count.fetch_add(1, Relaxed) + 1;
+        datapoint_info!(
+            "unified_scheduler-spawn_storm",
+            ("waited_us", throttled_since.elapsed().as_micros(), i64),
+            ("reused", reused, bool),
+            ("throttled_take_count", throttled_take_count, i64),
+        );
+    }
+
+    fn report_exhausted_take(&self) {
+        let exhausted_take_count = self.exhausted_take_count.fetch_add(1, Relaxed) + 1;
+        datapoint_info!(
+            "unified_scheduler-pool_exhaustion",
+            ("exhausted_take_count", exhausted_take_count, i64),
+            (
+                "spawned_thread_count",
+                self.spawned_thread_count.load(Relaxed),
+                i64
+            ),
+        );
+    }
+
+    // Returns whether the threads of all schedulers now outnumber the cpu cores beyond
+    // thread_oversubscription, if configured.
+    fn record_spawned_threads(&self, thread_count: usize, cpu_core_count: Option<usize>) -> bool {
+        let spawned_thread_count =
+            self.spawned_thread_count.fetch_add(thread_count, Relaxed) + thread_count;
+        let Some((oversubscription, cpu_core_count)) =
+            self.config.thread_oversubscription.zip(cpu_core_count)
+        else {
+            return false;
+        };
+        let max_thread_count = oversubscription.max_thread_count(cpu_core_count);
+        if spawned_thread_count <= max_thread_count {
+            return false;
         }
+        warn!(
+            "scheduler threads are oversubscribed: {spawned_thread_count} threads for \
+             {cpu_core_count} cpu cores (max: {max_thread_count})"
+        );
+        datapoint_warn!(
+            "unified_scheduler-thread_oversubscription",
+            ("spawned_thread_count", spawned_thread_count, i64),
+            ("cpu_core_count", cpu_core_count, i64),
+            ("max_thread_count", max_thread_count, i64),
+        );
+        true
     }
 
     pub fn default_handler_count() -> usize {
-        Self::calculate_default_handler_count(
-            thread::available_parallelism()
-                .ok()
-                .map(|non_zero| non_zero.get()),
-        )
+        Self::calculate_default_handler_count(detected_cpu_core_count())
     }
 
     pub fn calculate_default_handler_count(detected_cpu_core_count: Option<usize>) -> usize {
@@ -221,6 +1040,24 @@ impl TaskHandler for DefaultTaskHandler {
         index: usize,
         handler_context: &HandlerContext,
     ) {
+        if handler_context.pre_check_transaction_age {
+            // This is read-only and considers durable nonces, just like the check done inside
+            // execute_batch(). So, only the cost of the rejection differs.
+            let (check_result, _nonce, _lamports_per_signature) = bank
+                .check_transactions(
+                    &[transaction],
+                    &[Ok(())],
+                    MAX_PROCESSING_AGE,
+                    &mut TransactionErrorMetrics::default(),
+                )
+                .pop()
+                .unwrap();
+            if let Err(error @ TransactionError::BlockhashNotFound) = check_result {
+                *result = Err(error);
+                return;
+            }
+        }
+
         // scheduler must properly prevent conflicting tx executions. thus, task handler isn't
         // responsible for locking.
         let batch = bank.prepare_unlocked_batch_from_single_tx(transaction);
@@ -241,23 +1078,260 @@ impl TaskHandler for DefaultTaskHandler {
     }
 }
 
+/// Runs the main loops of handler threads, which never return while their scheduler is alive.
+///
+/// This abstraction exists for embedders who want to run handlers on their existing thread pool,
+/// rather than the dedicated threads spawned by the default implementation.
+pub trait HandlerExecutor: Send + Sync + Debug + 'static {
+    fn spawn(
+        &self,
+        thread_name: String,
+        handler_main_loop: Box<dyn FnOnce() + Send + 'static>,
+    ) -> HandlerJoinHandle;
+}
+
+#[derive(Debug)]
+pub enum HandlerJoinHandle {
+    Thread(JoinHandle<()>),
+    // Signaled with the outcome of handler_main_loop, for executors without native join handles.
+    Completion(Receiver<thread::Result<()>>),
+}
+
+impl HandlerJoinHandle {
+    pub fn join(self) -> thread::Result<()> {
+        match self {
+            Self::Thread(join_handle) => join_handle.join(),
+            // A disconnected receiver means the executor dropped the handler without running it.
+            Self::Completion(receiver) => receiver.recv().unwrap_or(Ok(())),
+        }
+    }
+}
+
+/// Runs each handler on its own named std thread.
+#[derive(Debug)]
+pub struct StdThreadHandlerExecutor;
+
+impl HandlerExecutor for StdThreadHandlerExecutor {
+    fn spawn(
+        &self,
+        thread_name: String,
+        handler_main_loop: Box<dyn FnOnce() + Send + 'static>,
+    ) -> HandlerJoinHandle {
+        HandlerJoinHandle::Thread(
+            thread::Builder::new()
+                .name(thread_name)
+                .spawn(handler_main_loop)
+                .unwrap(),
+        )
+    }
+}
+
+/// Runs handlers on a given rayon thread pool.
+///
+/// Note that each handler permanently occupies one of the pool's threads for as long as its
+/// scheduler is alive. So, the pool must be sized to accommodate `handler_count` threads for every
+/// live scheduler, plus any other work submitted to it.
+#[derive(Debug)]
+pub struct RayonHandlerExecutor(pub Arc<rayon::ThreadPool>);
+
+impl HandlerExecutor for RayonHandlerExecutor {
+    fn spawn(
+        &self,
+        _thread_name: String,
+        handler_main_loop: Box<dyn FnOnce() + Send + 'static>,
+    ) -> HandlerJoinHandle {
+        let (completion_sender, completion_receiver) = crossbeam_channel::bounded(1);
+        self.0.spawn(move || {
+            let result = catch_unwind(AssertUnwindSafe(handler_main_loop));
+            // the receiving HandlerJoinHandle may have been dropped already; that's fine.
+            let _ = completion_sender.send(result);
+        });
+        HandlerJoinHandle::Completion(completion_receiver)
+    }
+}
+
 struct ExecutedTask {
     task: Task,
     result_with_timings: ResultWithTimings,
+    handler_index: usize,
+    handler_timings: HandlerTimings,
+    // stamped by the handler thread with the end of its busy time, to avoid another Instant::now()
+    // in the scheduler thread.
+    committed_at: Option<Instant>,
+}
+
+/// The number of tasks which have gone through each stage of a session.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct TaskCounts {
+    /// sent to the scheduler thread by `schedule_execution()`
+    sent: usize,
+    /// received by the scheduler thread
+    received: usize,
+    /// failed by the scheduler thread without being dispatched, as duplicates
+    duplicate: usize,
+    /// dispatched to handler threads
+    dispatched: usize,
+    /// executed (thus, committed) by handler threads
+    executed: usize,
+    /// skipped by handler threads without execution, because the session has been aborted
+    aborted: usize,
+    /// accumulated into the session result by the scheduler thread
+    completed: usize,
+}
+
+impl TaskCounts {
+    fn is_consistent(&self) -> bool {
+        let Self {
+            sent,
+            received,
+            duplicate,
+            dispatched,
+            executed,
+            aborted,
+            completed,
+        } = *self;
+        [received, dispatched + duplicate]
+            .iter()
+            .all(|count| *count == sent)
+            && [executed + aborted, completed]
+                .iter()
+                .all(|count| *count == dispatched)
+    }
+}
+
+// Counted by other threads than the scheduler thread, which takes them at the end of sessions.
+#[derive(Debug, Default)]
+struct SharedTaskCounters {
+    sent: AtomicUsize,
+    executed: AtomicUsize,
+    aborted: AtomicUsize,
+    // the number of schedule_execution() calls blocked by max_in_flight_task_count
+    throttled: AtomicUsize,
+}
+
+/// A task committed out of the scheduled order.
+#[derive(Debug, PartialEq, Eq)]
+struct CommitOrderDivergence {
+    /// the position of the commit in the session
+    position: usize,
+    /// the index of the earliest-scheduled task which hasn't been committed yet
+    expected_index: Option<usize>,
+    committed_index: usize,
+}
+
+// Tracked by the scheduler thread for SchedulerPoolConfig::verify_commit_order.
+#[derive(Debug, Default)]
+struct CommitOrderVerifier {
+    uncommitted_task_indexes: VecDeque<usize>,
+    committed_count: usize,
+    divergences: Vec<CommitOrderDivergence>,
+}
+
+impl CommitOrderVerifier {
+    fn record_scheduled(&mut self, index: usize) {
+        self.uncommitted_task_indexes.push_back(index);
+    }
+
+    fn record_committed(&mut self, index: usize) {
+        let position = self.committed_count;
+        self.committed_count += 1;
+        if self.uncommitted_task_indexes.front() == Some(&index) {
+            self.uncommitted_task_indexes.pop_front();
+            return;
+        }
+        let expected_index = self.uncommitted_task_indexes.front().copied();
+        if let Some(committed) = self
+            .uncommitted_task_indexes
+            .iter()
+            .position(|uncommitted| *uncommitted == index)
+        {
+            self.uncommitted_task_indexes.remove(committed);
+        }
+        self.divergences.push(CommitOrderDivergence {
+            position,
+            expected_index,
+            committed_index: index,
+        });
+    }
+
+    fn take_divergences(&mut self) -> Vec<CommitOrderDivergence> {
+        self.uncommitted_task_indexes.clear();
+        self.committed_count = 0;
+        std::mem::take(&mut self.divergences)
+    }
+}
+
+// Sent from handler threads to the scheduler thread. `rest` is only non-empty with
+// CompletionBatching, so that reporting a single completion doesn't allocate.
+struct FinishedTasks {
+    first: Box<ExecutedTask>,
+    rest: Vec<Box<ExecutedTask>>,
+}
+
+impl FinishedTasks {
+    fn new(executed_task: Box<ExecutedTask>) -> Self {
+        Self {
+            first: executed_task,
+            rest: Vec::new(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        1 + self.rest.len()
+    }
+}
+
+impl IntoIterator for FinishedTasks {
+    type Item = Box<ExecutedTask>;
+    type IntoIter =
+        std::iter::Chain<std::iter::Once<Box<ExecutedTask>>, std::vec::IntoIter<Box<ExecutedTask>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        std::iter::once(self.first).chain(self.rest)
+    }
 }
 
 impl ExecutedTask {
-    fn new_boxed(task: Task) -> Box<Self> {
+    fn new_boxed(task: Task, handler_index: usize) -> Box<Self> {
         Box::new(Self {
             task,
             result_with_timings: initialized_result_with_timings(),
+            handler_index,
+            handler_timings: HandlerTimings::default(),
+            committed_at: None,
         })
     }
 }
 
-// A very tiny generic message type to signal about opening and closing of subchannels, which are
-// logically segmented series of Payloads (P1) over a single continuous time-span, potentially
-// carrying some subchannel metadata (P2) upon opening a new subchannel.
+/// Accumulated idle/busy time of a single handler thread.
+///
+/// Idle time is the time spent waiting for a runnable task, while busy time is the time spent on
+/// executing tasks. Handlers measure both around each task and the scheduler thread sums them
+/// per handler over a session, so that it can be told whether the handlers are starved by the
+/// scheduling or saturated by the execution.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct HandlerTimings {
+    idle: Duration,
+    busy: Duration,
+}
+
+impl HandlerTimings {
+    fn accumulate(&mut self, other: &Self) {
+        self.idle = self.idle.saturating_add(other.idle);
+        self.busy = self.busy.saturating_add(other.busy);
+    }
+
+    /// Returns the ratio of idle time to the total measured time in the range of `0.0..=1.0`, or
+    /// `None` if nothing has been measured yet.
+    fn idle_ratio(&self) -> Option<f64> {
+        let total = self.idle.saturating_add(self.busy);
+        (!total.is_zero()).then(|| self.idle.as_secs_f64() / total.as_secs_f64())
+    }
+}
+
+// A very tiny generic message type to signal about opening and closing of subchannels, which are
+// logically segmented series of Payloads (P1) over a single continuous time-span, potentially
+// carrying some subchannel metadata (P2) upon opening a new subchannel.
 // Note that the above properties can be upheld only when this is used inside MPSC or SPSC channels
 // (i.e. the consumer side needs to be single threaded). For the multiple consumer cases,
 // ChainedChannel can be used instead.
@@ -390,6 +1464,14 @@ fn initialized_result_with_timings() -> ResultWithTimings {
     (Ok(()), ExecuteTimings::default())
 }
 
+// ExecuteTimings isn't Clone, so it's copied by accumulating into an empty one.
+fn snapshot_result_with_timings((result, timings): &ResultWithTimings) -> ResultWithTimings {
+    let mut snapshot = initialized_result_with_timings();
+    snapshot.0 = result.clone();
+    snapshot.1.accumulate(timings);
+    snapshot
+}
+
 // Currently, simplest possible implementation (i.e. single-threaded)
 // this will be replaced with more proper implementation...
 // not usable at all, especially for mainnet-beta
@@ -415,14 +1497,58 @@ struct ThreadManager<S: SpawnableScheduler<TH>, TH: TaskHandler> {
     pool: Arc<SchedulerPool<S, TH>>,
     new_task_sender: Sender<NewTaskPayload>,
     new_task_receiver: Receiver<NewTaskPayload>,
-    session_result_sender: Sender<Option<ResultWithTimings>>,
-    session_result_receiver: Receiver<Option<ResultWithTimings>>,
-    session_result_with_timings: Option<ResultWithTimings>,
-    scheduler_thread: Option<JoinHandle<()>>,
-    handler_threads: Vec<JoinHandle<()>>,
+    session_result_sender: Sender<Option<(ResultWithTimings, SessionSummary)>>,
+    session_result_receiver: Receiver<Option<(ResultWithTimings, SessionSummary)>>,
+    session_result_with_timings: Option<SchedulerResult<ResultWithTimings>>,
+    session_error: Arc<SessionError>,
+    progress: Arc<SchedulerProgress>,
+    // the summary of the latest ended session, which is paired with session_result_with_timings
+    // while paused.
+    session_summary: SessionSummary,
+    // initialized by start_threads(), which could be called lazily via &self. See
+    // ThreadStartPolicy.
+    threads: OnceLock<SpawnedThreads>,
+    task_counters: Arc<SharedTaskCounters>,
+    // set while aborting the current session, so that handler threads skip the remaining tasks.
+    session_aborted: Arc<AtomicBool>,
+    // carries the reply senders of checkpoint() to the scheduler thread
+    checkpoint_request_sender: Sender<Sender<ResultWithTimings>>,
+    checkpoint_request_receiver: Receiver<Sender<ResultWithTimings>>,
+    // a slot is occupied by each in-flight task, if max_in_flight_task_count is configured. Sent
+    // by send_task() and received by the scheduler thread on completion.
+    in_flight_task_slots: Option<(Sender<()>, Receiver<()>)>,
+}
+
+#[derive(Debug)]
+struct SpawnedThreads {
+    scheduler_thread: JoinHandle<()>,
+    handler_threads: Vec<HandlerJoinHandle>,
+    // disconnected once the scheduler thread has exited. Nothing is sent.
+    scheduler_exited: Receiver<()>,
+}
+
+impl SpawnedThreads {
+    fn thread_count(&self) -> usize {
+        1 + self.handler_threads.len()
+    }
+
+    // Panics are already counted by PanicDetector, so they're ignored here.
+    fn join(self) {
+        let _ = self.scheduler_thread.join();
+        for handler_thread in self.handler_threads {
+            let _ = handler_thread.join();
+        }
+    }
 }
 
 impl<TH: TaskHandler> PooledScheduler<TH> {
+    /// Returns a snapshot of the result accumulated from the tasks completed so far in the
+    /// current session, without ending the session unlike `pause_for_recent_blockhash()`. This is
+    /// intended for reporting the incremental progress of long block replays.
+    pub fn checkpoint(&self) -> SchedulerResult<ResultWithTimings> {
+        self.inner.thread_manager.checkpoint()
+    }
+
     fn do_spawn(pool: Arc<SchedulerPool<Self, TH>>, initial_context: SchedulingContext) -> Self {
         Self::from_inner(
             PooledSchedulerInner::<Self, TH> {
@@ -437,18 +1563,25 @@ impl<S: SpawnableScheduler<TH>, TH: TaskHandler> ThreadManager<S, TH> {
     fn new(pool: Arc<SchedulerPool<S, TH>>) -> Self {
         let (new_task_sender, new_task_receiver) = unbounded();
         let (session_result_sender, session_result_receiver) = unbounded();
-        let handler_count = pool.handler_count;
-
+        let (checkpoint_request_sender, checkpoint_request_receiver) = unbounded();
+        let scheduler_id = pool.new_scheduler_id();
         Self {
-            scheduler_id: pool.new_scheduler_id(),
+            scheduler_id,
+            progress: pool.register_scheduler_progress(scheduler_id),
+            in_flight_task_slots: pool.config.max_in_flight_task_count.map(bounded),
             pool,
             new_task_sender,
             new_task_receiver,
             session_result_sender,
             session_result_receiver,
             session_result_with_timings: None,
-            scheduler_thread: None,
-            handler_threads: Vec::with_capacity(handler_count),
+            session_error: Arc::default(),
+            session_summary: SessionSummary::default(),
+            threads: OnceLock::new(),
+            task_counters: Arc::default(),
+            session_aborted: Arc::default(),
+            checkpoint_request_sender,
+            checkpoint_request_receiver,
         }
     }
 
@@ -486,11 +1619,31 @@ impl<S: SpawnableScheduler<TH>, TH: TaskHandler> ThreadManager<S, TH> {
         timings.accumulate(&executed_task.result_with_timings.1);
     }
 
-    fn take_session_result_with_timings(&mut self) -> ResultWithTimings {
+    fn report_handler_timings(scheduler_id: SchedulerId, handler_timings: &[HandlerTimings]) {
+        for (handler_index, timings) in handler_timings.iter().enumerate() {
+            let Some(idle_ratio) = timings.idle_ratio() else {
+                // this handler hasn't executed any task in this session
+                continue;
+            };
+            datapoint_info!(
+                "unified_scheduler-handler_timings",
+                ("scheduler_id", scheduler_id, i64),
+                ("handler_index", handler_index, i64),
+                ("idle_us", timings.idle.as_micros(), i64),
+                ("busy_us", timings.busy.as_micros(), i64),
+                ("idle_ratio", idle_ratio, f64),
+            );
+        }
+    }
+
+    fn take_session_result_with_timings(&mut self) -> SchedulerResult<ResultWithTimings> {
         self.session_result_with_timings.take().unwrap()
     }
 
-    fn put_session_result_with_timings(&mut self, result_with_timings: ResultWithTimings) {
+    fn put_session_result_with_timings(
+        &mut self,
+        result_with_timings: SchedulerResult<ResultWithTimings>,
+    ) {
         assert_matches!(
             self.session_result_with_timings
                 .replace(result_with_timings),
@@ -498,12 +1651,50 @@ impl<S: SpawnableScheduler<TH>, TH: TaskHandler> ThreadManager<S, TH> {
         );
     }
 
-    fn start_threads(&mut self, context: &SchedulingContext) {
+    fn are_threads_started(&self) -> bool {
+        self.threads.get().is_some()
+    }
+
+    fn scheduler_exited(&self) -> Receiver<()> {
+        self.threads
+            .get()
+            .map(|threads| threads.scheduler_exited.clone())
+            .unwrap_or_else(never)
+    }
+
+    // The scheduler thread only exits on its own when it has failed, as it's otherwise stopped by
+    // dropping self.
+    fn is_scheduler_exited(&self) -> bool {
+        self.scheduler_exited().try_recv() == Err(TryRecvError::Disconnected)
+    }
+
+    // Spawns the threads unless already spawned. `context` must be the one of the current session.
+    fn start_threads(&self, context: &SchedulingContext) {
+        self.threads.get_or_init(|| {
+            let lazy = self.pool.config.thread_start_policy == ThreadStartPolicy::Lazy;
+            let spawn_start = Instant::now();
+            let threads = self.spawn_threads(context);
+            // the handler threads and the scheduler thread
+            let thread_count = self.pool.handler_count + 1;
+            self.pool
+                .record_spawned_threads(thread_count, detected_cpu_core_count());
+            datapoint_info!(
+                "unified_scheduler-thread_spawn",
+                ("scheduler_id", self.scheduler_id, i64),
+                ("lazy", lazy, bool),
+                ("spawn_us", spawn_start.elapsed().as_micros(), i64),
+            );
+            threads
+        });
+    }
+
+    fn spawn_threads(&self, context: &SchedulingContext) -> SpawnedThreads {
         let (mut runnable_task_sender, runnable_task_receiver) =
             chained_channel::unbounded::<Task, SchedulingContext>(context.clone());
-        let (finished_task_sender, finished_task_receiver) = unbounded::<Box<ExecutedTask>>();
+        let (finished_task_sender, finished_task_receiver) = unbounded::<FinishedTasks>();
 
-        let mut result_with_timings = self.session_result_with_timings.take();
+        // the result of the current session is initialized by its queued OpenSubchannel.
+        let mut result_with_timings = None;
 
         // High-level flow of new tasks:
         // 1. the replay stage thread send a new task.
@@ -514,11 +1705,46 @@ impl<S: SpawnableScheduler<TH>, TH: TaskHandler> ThreadManager<S, TH> {
         // 6. the scheduler thread post-processes the executed task.
         let scheduler_main_loop = || {
             let handler_count = self.pool.handler_count;
+            let scheduler_id = self.scheduler_id;
+            let event_log = self.pool.config.event_log.clone();
             let session_result_sender = self.session_result_sender.clone();
-            let new_task_receiver = self.new_task_receiver.clone();
+            let mut new_task_receiver = self.new_task_receiver.clone();
+            let checkpoint_request_receiver = self.checkpoint_request_receiver.clone();
+            let pool = self.pool.clone();
+            let task_counters = self.task_counters.clone();
+            let session_error = self.session_error.clone();
+            let progress = self.progress.clone();
 
             let mut session_ending = false;
+            // set once the thread manager is dropped, after which the thread exits as soon as the
+            // dispatched tasks are completed.
+            let mut is_stopping = false;
             let mut active_task_count: usize = 0;
+            // the peak of active_task_count in the current session, to tell how deep the handler
+            // threads have been queued.
+            let mut max_active_task_count: usize = 0;
+            let mut session_slot = None;
+            let in_flight_task_slot_receiver = self
+                .in_flight_task_slots
+                .as_ref()
+                .map(|(_, receiver)| receiver.clone());
+            let mut seen_message_hashes = pool
+                .config
+                .reject_duplicate_transactions
+                .then(HashSet::<Hash>::default);
+            let mut commit_order_verifier = pool
+                .config
+                .verify_commit_order
+                .then(CommitOrderVerifier::default);
+            let mut handler_timings = vec![HandlerTimings::default(); handler_count];
+            // to quantify the wake-ups saved by CompletionBatching
+            let mut completion_message_count: usize = 0;
+            let mut task_counts = TaskCounts::default();
+            // for SessionSummary. Instant::now() is only called once per session here; the commit
+            // times are stamped by the handler threads.
+            let mut session_started_at = None;
+            let mut first_dispatched_at = None;
+            let mut last_committed_at: Option<Instant> = None;
 
             // Now, this is the main loop for the scheduler thread, which is a special beast.
             //
@@ -562,126 +1788,558 @@ impl<S: SpawnableScheduler<TH>, TH: TaskHandler> ThreadManager<S, TH> {
                 let mut is_finished = false;
                 while !is_finished {
                     select! {
-                        recv(finished_task_receiver) -> executed_task => {
-                            let executed_task = executed_task.unwrap();
-
-                            active_task_count = active_task_count.checked_sub(1).unwrap();
-                            let result_with_timings = result_with_timings.as_mut().unwrap();
-                            Self::accumulate_result_with_timings(result_with_timings, executed_task);
+                        recv(finished_task_receiver) -> finished_tasks => {
+                            let Ok(finished_tasks) = finished_tasks else {
+                                // the handler threads have exited unexpectedly, so the remaining
+                                // tasks can never be completed.
+                                session_error.record(SchedulerError::Disconnected);
+                                return;
+                            };
+
+                            completion_message_count += 1;
+                            if let Some(event_log) = &event_log {
+                                event_log.record(
+                                    EventKind::CompletionReceived,
+                                    SCHEDULER_THREAD_ID,
+                                    None,
+                                );
+                            }
+                            for executed_task in finished_tasks {
+                                task_counts.completed += 1;
+                                active_task_count = active_task_count.checked_sub(1).unwrap();
+                                if let Some(event_log) = &event_log {
+                                    event_log.record(
+                                        EventKind::TaskCompleted,
+                                        SCHEDULER_THREAD_ID,
+                                        Some(executed_task.task.task_index()),
+                                    );
+                                }
+                                handler_timings[executed_task.handler_index]
+                                    .accumulate(&executed_task.handler_timings);
+                                last_committed_at = last_committed_at.max(executed_task.committed_at);
+                                if let Some(verifier) = &mut commit_order_verifier {
+                                    verifier.record_committed(executed_task.task.task_index());
+                                }
+                                if let Some(receiver) = &in_flight_task_slot_receiver {
+                                    receiver
+                                        .try_recv()
+                                        .expect("occupied by send_task() before sending");
+                                }
+                                let result_with_timings = result_with_timings.as_mut().unwrap();
+                                Self::accumulate_result_with_timings(result_with_timings, executed_task);
+                            }
+                            if let Some(last_committed_at) = last_committed_at {
+                                progress.record_progress(last_committed_at);
+                            }
+                            progress.set_active_task_count(active_task_count);
+                        },
+                        recv(checkpoint_request_receiver) -> reply_sender => {
+                            // the session may not have been opened yet, as the requests are sent
+                            // via a separate channel. In that case, nothing has been completed.
+                            let snapshot = result_with_timings
+                                .as_ref()
+                                .map(snapshot_result_with_timings)
+                                .unwrap_or_else(initialized_result_with_timings);
+                            // the requester is blocked on the reply, unless it has stopped waiting
+                            // for some reason.
+                            if let Ok(reply_sender) = reply_sender {
+                                let _ = reply_sender.send(snapshot);
+                            }
                         },
                         recv(new_task_receiver) -> message => {
                             assert!(!session_ending);
 
-                            match message.unwrap() {
-                                NewTaskPayload::Payload(task) => {
+                            match message {
+                                Ok(NewTaskPayload::Payload(task)) => {
+                                    task_counts.received += 1;
+                                    if let Some(event_log) = &event_log {
+                                        event_log.record(
+                                            EventKind::TaskReceived,
+                                            SCHEDULER_THREAD_ID,
+                                            Some(task.task_index()),
+                                        );
+                                    }
+                                    if let Some(seen_message_hashes) = &mut seen_message_hashes {
+                                        if !seen_message_hashes.insert(*task.transaction().message_hash()) {
+                                            // the bank's status cache would reject it likewise
+                                            // after all. so, just fail it without dispatching.
+                                            task_counts.duplicate += 1;
+                                            if let Some(receiver) = &in_flight_task_slot_receiver {
+                                                receiver
+                                                    .try_recv()
+                                                    .expect("occupied by send_task() before sending");
+                                            }
+                                            let mut duplicate_task = ExecutedTask::new_boxed(task, 0);
+                                            duplicate_task.result_with_timings.0 =
+                                                Err(TransactionError::AlreadyProcessed);
+                                            let result_with_timings = result_with_timings.as_mut().unwrap();
+                                            Self::accumulate_result_with_timings(result_with_timings, duplicate_task);
+                                            continue;
+                                        }
+                                    }
+                                    if let Some(verifier) = &mut commit_order_verifier {
+                                        verifier.record_scheduled(task.task_index());
+                                    }
                                     // so, we're NOT scheduling at all here; rather, just execute
                                     // tx straight off. the inter-tx locking deps aren't needed to
                                     // be resolved in the case of single-threaded FIFO like this.
-                                    runnable_task_sender
-                                        .send_payload(task)
-                                        .unwrap();
+                                    if runnable_task_sender.send_payload(task).is_err() {
+                                        // the handler threads have exited unexpectedly
+                                        session_error.record(SchedulerError::Disconnected);
+                                        return;
+                                    }
+                                    if first_dispatched_at.is_none() {
+                                        first_dispatched_at = Some(Instant::now());
+                                    }
+                                    if active_task_count == 0 {
+                                        // stalls are measured since becoming busy at the earliest
+                                        progress.record_progress(Instant::now());
+                                    }
+                                    task_counts.dispatched += 1;
                                     active_task_count = active_task_count.checked_add(1).unwrap();
+                                    progress.set_active_task_count(active_task_count);
+                                    max_active_task_count = max_active_task_count.max(active_task_count);
                                 }
-                                NewTaskPayload::OpenSubchannel(context) => {
+                                Ok(NewTaskPayload::OpenSubchannel(context)) => {
+                                    if let Some(event_log) = &event_log {
+                                        event_log.record(
+                                            EventKind::SessionStarted,
+                                            SCHEDULER_THREAD_ID,
+                                            None,
+                                        );
+                                    }
+                                    session_started_at = Some(Instant::now());
+                                    session_slot = Some(context.slot());
                                     // signal about new SchedulingContext to handler threads
-                                    runnable_task_sender
+                                    if runnable_task_sender
                                         .send_chained_channel(context, handler_count)
-                                        .unwrap();
+                                        .is_err()
+                                    {
+                                        // the handler threads have exited unexpectedly
+                                        session_error.record(SchedulerError::Disconnected);
+                                        return;
+                                    }
                                     assert_matches!(
                                         result_with_timings.replace(initialized_result_with_timings()),
                                         None
                                     );
                                 }
-                                NewTaskPayload::CloseSubchannel => {
+                                Ok(NewTaskPayload::CloseSubchannel) => {
                                     session_ending = true;
                                 }
+                                Err(_) => {
+                                    is_stopping = true;
+                                }
                             }
                         },
                     };
 
+                    if is_stopping {
+                        // stop selecting the disconnected receiver
+                        new_task_receiver = never();
+                    }
+
                     // a really simplistic termination condition, which only works under the
                     // assumption of single handler thread...
-                    is_finished = session_ending && active_task_count == 0;
+                    is_finished = (session_ending || is_stopping) && active_task_count == 0;
+                }
+
+                if is_stopping {
+                    // dropping runnable_task_sender lets the handler threads exit in turn.
+                    break;
                 }
 
                 if session_ending {
-                    session_result_sender
-                        .send(Some(
+                    if let Some(event_log) = &event_log {
+                        event_log.record(EventKind::SessionEnded, SCHEDULER_THREAD_ID, None);
+                    }
+                    Self::report_handler_timings(scheduler_id, &handler_timings);
+                    handler_timings.fill(HandlerTimings::default());
+                    if task_counts.completed > 0 {
+                        datapoint_info!(
+                            "unified_scheduler-completions",
+                            ("scheduler_id", scheduler_id, i64),
+                            ("task_count", task_counts.completed, i64),
+                            ("message_count", completion_message_count, i64),
+                        );
+                    }
+                    if let (Some(verifier), Some(slot)) = (&mut commit_order_verifier, session_slot)
+                    {
+                        pool.record_commit_order_divergences(
+                            scheduler_id,
+                            slot,
+                            &verifier.take_divergences(),
+                        );
+                    }
+                    let throttled_task_count = task_counters.throttled.swap(0, Relaxed);
+                    if let Some(slot) = session_slot.take() {
+                        datapoint_info!(
+                            "unified_scheduler-session_stats",
+                            ("scheduler_id", scheduler_id, i64),
+                            ("slot", slot, i64),
+                            ("task_count", task_counts.completed, i64),
+                            ("max_active_task_count", max_active_task_count, i64),
+                            ("completion_message_count", completion_message_count, i64),
+                            ("throttled_task_count", throttled_task_count, i64),
+                            ("duplicate_task_count", task_counts.duplicate, i64),
+                            (
+                                "duration_us",
+                                session_started_at.map(|instant| instant.elapsed().as_micros() as i64),
+                                Option<i64>
+                            ),
+                        );
+                    }
+                    max_active_task_count = 0;
+                    completion_message_count = 0;
+                    // all tasks have been completed, so the other threads are done with counting
+                    // for this session.
+                    task_counts.sent = task_counters.sent.swap(0, Relaxed);
+                    task_counts.executed = task_counters.executed.swap(0, Relaxed);
+                    task_counts.aborted = task_counters.aborted.swap(0, Relaxed);
+                    pool.record_task_counts(scheduler_id, &task_counts);
+                    task_counts = TaskCounts::default();
+                    if let Some(seen_message_hashes) = &mut seen_message_hashes {
+                        seen_message_hashes.clear();
+                    }
+                    let since_session_start = |instant: Option<Instant>| {
+                        Some(instant?.saturating_duration_since(session_started_at?))
+                    };
+                    let summary = SessionSummary {
+                        first_dispatch: since_session_start(first_dispatched_at.take()),
+                        last_commit: since_session_start(last_committed_at.take()),
+                    };
+                    session_started_at = None;
+                    if session_result_sender
+                        .send(Some((
                             result_with_timings
                                 .take()
                                 .unwrap_or_else(initialized_result_with_timings),
-                        ))
-                        .unwrap();
+                            summary,
+                        )))
+                        .is_err()
+                    {
+                        // the thread manager has gone
+                        return;
+                    }
                     session_ending = false;
                 }
             }
         };
 
-        let handler_main_loop = || {
-            let pool = self.pool.clone();
+        let handler_main_loop = |handler_index| {
             let mut runnable_task_receiver = runnable_task_receiver.clone();
             let finished_task_sender = finished_task_sender.clone();
-
-            move || loop {
-                let (task, sender) = select! {
-                    recv(runnable_task_receiver.for_select()) -> message => {
-                        if let Some(task) = runnable_task_receiver.after_select(message.unwrap()) {
-                            (task, &finished_task_sender)
-                        } else {
-                            continue;
+            let event_log = self.pool.config.event_log.clone();
+            let completion_batching = self.pool.config.completion_batching;
+            let task_counters = self.task_counters.clone();
+            let session_aborted = self.session_aborted.clone();
+            let thread_id = u32::try_from(handler_index).unwrap();
+            let mut transaction_status_batcher = self
+                .pool
+                .config
+                .transaction_status_batching
+                .zip(self.pool.handler_context.transaction_status_sender.clone())
+                .map(|(batching, sender)| TransactionStatusBatcher::new(batching, sender));
+            // substitute the sender, so that the statuses are sent to the batcher instead.
+            let handler_context = HandlerContext {
+                transaction_status_sender: transaction_status_batcher
+                    .as_ref()
+                    .map(|batcher| batcher.local_sender.clone())
+                    .or_else(|| self.pool.handler_context.transaction_status_sender.clone()),
+                ..self.pool.handler_context.clone()
+            };
+
+            move || {
+                // idle time is measured from the later of the previous task's completion or the
+                // start of the current session, so that the time between sessions isn't counted.
+                let mut idle_since = Instant::now();
+                // completed tasks held back by CompletionBatching, along with when the first of
+                // them completed.
+                let mut held_tasks: Option<(FinishedTasks, Instant)> = None;
+                loop {
+                    if let Some((finished_tasks, held_since)) = &held_tasks {
+                        let CompletionBatching {
+                            max_count,
+                            max_delay,
+                        } = completion_batching.unwrap();
+                        // Must never block on the receiver while holding back completions;
+                        // otherwise, the session could never end.
+                        if finished_tasks.len() >= max_count
+                            || held_since.elapsed() >= max_delay
+                            || runnable_task_receiver.for_select().is_empty()
+                        {
+                            let (finished_tasks, _) = held_tasks.take().unwrap();
+                            if finished_task_sender.send(finished_tasks).is_err() {
+                                // the scheduler thread has exited
+                                break;
+                            }
                         }
-                    },
-                };
-                let mut task = ExecutedTask::new_boxed(task);
-                Self::execute_task_with_handler(
-                    runnable_task_receiver.context().bank(),
-                    &mut task,
-                    &pool.handler_context,
-                );
-                sender.send(task).unwrap();
+                    }
+
+                    let (task, sender) = select! {
+                        recv(runnable_task_receiver.for_select()) -> message => {
+                            let Ok(message) = message else {
+                                // the scheduler thread has exited
+                                break;
+                            };
+                            if let Some(task) = runnable_task_receiver.after_select(message) {
+                                (task, &finished_task_sender)
+                            } else {
+                                idle_since = Instant::now();
+                                continue;
+                            }
+                        },
+                    };
+                    let busy_since = Instant::now();
+                    if let Some(event_log) = &event_log {
+                        event_log.record(
+                            EventKind::TaskExecutionStarted,
+                            thread_id,
+                            Some(task.task_index()),
+                        );
+                    }
+                    let mut task = ExecutedTask::new_boxed(task, handler_index);
+                    if session_aborted.load(Relaxed) {
+                        // still reported back as completed, so that the session can end.
+                        task_counters.aborted.fetch_add(1, Relaxed);
+                    } else {
+                        Self::execute_task_with_handler(
+                            runnable_task_receiver.context().bank(),
+                            &mut task,
+                            &handler_context,
+                        );
+                        task_counters.executed.fetch_add(1, Relaxed);
+                    }
+                    if let Some(batcher) = &mut transaction_status_batcher {
+                        batcher.accumulate(!runnable_task_receiver.for_select().is_empty());
+                    }
+                    let busy_until = Instant::now();
+                    if let Some(event_log) = &event_log {
+                        event_log.record(
+                            EventKind::TaskExecutionFinished,
+                            thread_id,
+                            Some(task.task.task_index()),
+                        );
+                    }
+                    task.handler_timings = HandlerTimings {
+                        idle: busy_since.saturating_duration_since(idle_since),
+                        busy: busy_until.saturating_duration_since(busy_since),
+                    };
+                    task.committed_at = Some(busy_until);
+                    idle_since = busy_until;
+                    match (&mut held_tasks, completion_batching) {
+                        (None, None) => {
+                            if sender.send(FinishedTasks::new(task)).is_err() {
+                                // the scheduler thread has exited
+                                break;
+                            }
+                        }
+                        (None, Some(_)) => {
+                            held_tasks = Some((FinishedTasks::new(task), busy_until))
+                        }
+                        (Some((finished_tasks, _)), _) => finished_tasks.rest.push(task),
+                    }
+                }
             }
         };
 
-        self.scheduler_thread = Some(
-            thread::Builder::new()
-                .name("solScheduler".to_owned())
-                .spawn(scheduler_main_loop())
-                .unwrap(),
-        );
+        let scheduler_main_loop = scheduler_main_loop();
+        let niceness_adj = self.pool.config.scheduler_thread_niceness_adj;
+        let (scheduler_exit_sender, scheduler_exited) = bounded::<()>(0);
+        let scheduler_main_loop = self.with_panic_detection(move || {
+            if niceness_adj != 0 {
+                if let Err(error) = renice_this_thread(niceness_adj) {
+                    warn!("scheduler thread stays at the inherited priority: {error}");
+                }
+            }
+            scheduler_main_loop()
+        });
+        let scheduler_thread = thread::Builder::new()
+            .name("solScheduler".to_owned())
+            .spawn(move || {
+                // dropped only after a panic (if any) is recorded, so that its error is reported
+                // by do_end_session() instead of SchedulerError::Disconnected.
+                let _scheduler_exit_sender = scheduler_exit_sender;
+                scheduler_main_loop()
+            })
+            .unwrap();
 
-        self.handler_threads = (0..self.pool.handler_count)
+        let handler_threads = (0..self.pool.handler_count)
             .map({
                 |thx| {
-                    thread::Builder::new()
-                        .name(format!("solScHandler{:02}", thx))
-                        .spawn(handler_main_loop())
-                        .unwrap()
+                    let handler_main_loop = handler_main_loop(thx);
+                    let core_ids = self.pool.config.handler_core_ids.as_ref();
+                    let core_id = core_ids.map(|ids| CoreId {
+                        id: ids[thx % ids.len()],
+                    });
+                    let handler_main_loop = self.with_panic_detection(move || {
+                        if let Some(core_id) = core_id {
+                            core_affinity::set_for_current(core_id);
+                        }
+                        handler_main_loop()
+                    });
+                    // likewise, the scheduler thread mustn't see the disconnection until a panic
+                    // (if any) is recorded.
+                    let finished_task_sender = finished_task_sender.clone();
+                    self.pool.config.handler_executor.spawn(
+                        format!("solScHandler{:02}", thx),
+                        Box::new(move || {
+                            let _finished_task_sender = finished_task_sender;
+                            handler_main_loop()
+                        }),
+                    )
                 }
             })
             .collect();
+
+        SpawnedThreads {
+            scheduler_thread,
+            handler_threads,
+            scheduler_exited,
+        }
+    }
+
+    fn with_panic_detection<T>(
+        &self,
+        main_loop: impl FnOnce() -> T + Send + 'static,
+    ) -> impl FnOnce() + Send + 'static {
+        let pool = self.pool.clone();
+        let session_error = self.session_error.clone();
+        move || {
+            let _panic_detector = PanicDetector(&pool.panicked_thread_count);
+            if let Err(payload) = catch_unwind(AssertUnwindSafe(main_loop)) {
+                session_error.record(SchedulerError::ThreadPanicked(panic_message(&*payload)));
+                resume_unwind(payload);
+            }
+        }
     }
 
     fn send_task(&self, task: Task) {
         debug!("send_task()");
+        if self.session_result_with_timings.is_some() {
+            // paused; the session has been ended already. so, the task can't be executed.
+            self.session_error.record(SchedulerError::SessionEnded);
+            return;
+        }
+        if self.is_scheduler_exited() {
+            // the failure is reported when the session ends.
+            return;
+        }
+        // counted before sending, so that the scheduler thread sees it upon CloseSubchannel
+        self.task_counters.sent.fetch_add(1, Relaxed);
+        if let Some((slot_sender, _)) = &self.in_flight_task_slots {
+            if slot_sender.try_send(()).is_err() {
+                self.task_counters.throttled.fetch_add(1, Relaxed);
+                // the receiver is retained by self, so this only blocks until a slot is freed or
+                // the scheduler thread exits without freeing it.
+                let scheduler_exited = self.scheduler_exited();
+                select! {
+                    send(slot_sender, ()) -> result => result.unwrap(),
+                    recv(scheduler_exited) -> _ => return,
+                }
+            }
+        }
         self.new_task_sender
             .send(NewTaskPayload::Payload(task))
             .unwrap()
     }
 
+    fn checkpoint(&self) -> SchedulerResult<ResultWithTimings> {
+        // paused; the session has been ended already.
+        match &self.session_result_with_timings {
+            Some(Ok(result_with_timings)) => {
+                return Ok(snapshot_result_with_timings(result_with_timings))
+            }
+            Some(Err(error)) => return Err(error.clone()),
+            None => {}
+        }
+        if !self.are_threads_started() {
+            return Ok(initialized_result_with_timings());
+        }
+        let (reply_sender, reply_receiver) = bounded(1);
+        self.checkpoint_request_sender.send(reply_sender).unwrap();
+        let scheduler_exited = self.scheduler_exited();
+        select! {
+            recv(reply_receiver) -> snapshot => snapshot.map_err(|_| SchedulerError::Disconnected),
+            // the request would never be replied by the exited scheduler thread
+            recv(scheduler_exited) -> _ => {
+                Err(self.session_error.get().unwrap_or(SchedulerError::Disconnected))
+            }
+        }
+    }
+
+    /// Ends the session for pausing, retaining the result within the thread manager until it's
+    /// taken by `end_session_and_take_result()`.
     fn end_session(&mut self) {
         if self.session_result_with_timings.is_some() {
             debug!("end_session(): already result resides within thread manager..");
             return;
         }
+        let result_with_timings = self.do_end_session();
+        self.put_session_result_with_timings(result_with_timings);
+    }
+
+    /// Ends the session for termination. Unlike `end_session()`, the result is returned directly
+    /// without being buffered, unless the session has already been paused.
+    fn end_session_and_take_result(&mut self) -> SchedulerResult<ResultWithTimings> {
+        if self.session_result_with_timings.is_some() {
+            debug!("end_session_and_take_result(): taking the result of the paused session..");
+            let result_with_timings = self.take_session_result_with_timings();
+            // tasks may have been sent (and dropped) after pausing
+            return self.session_error.take().map_or(result_with_timings, Err);
+        }
+        self.do_end_session()
+    }
+
+    /// Ends the session for termination like `end_session_and_take_result()`, but without
+    /// executing the remaining tasks. So, the returned result only reflects the tasks which have
+    /// been executed before the abort.
+    fn abort_session_and_take_result(&mut self) -> SchedulerResult<ResultWithTimings> {
+        self.session_aborted.store(true, Relaxed);
+        let result_with_timings = self.end_session_and_take_result();
+        // all tasks have been completed, so the handler threads are done with this session.
+        self.session_aborted.store(false, Relaxed);
+        result_with_timings
+    }
+
+    fn do_end_session(&mut self) -> SchedulerResult<ResultWithTimings> {
         debug!("end_session(): will end session...");
 
+        if !self.are_threads_started() {
+            // no task has been sent ever, so there's nothing to wait for. just discard the queued
+            // session messages, which the lazily-spawned scheduler thread would otherwise process.
+            for message in self.new_task_receiver.try_iter() {
+                assert!(matches!(message, NewTaskPayload::OpenSubchannel(_)));
+            }
+            self.session_summary = SessionSummary::default();
+            return Ok(initialized_result_with_timings());
+        }
+
         self.new_task_sender
             .send(NewTaskPayload::CloseSubchannel)
             .unwrap();
 
-        if let Some(result_with_timings) = self.session_result_receiver.recv().unwrap() {
-            self.put_session_result_with_timings(result_with_timings);
-        }
+        let scheduler_exited = self.scheduler_exited();
+        let session_result = select! {
+            recv(self.session_result_receiver) -> session_result => Some(
+                session_result
+                    .unwrap()
+                    .expect("the scheduler thread always sends the session result"),
+            ),
+            // the session result would never be sent by the exited scheduler thread
+            recv(scheduler_exited) -> _ => None,
+        };
+        let Some((result_with_timings, summary)) = session_result else {
+            self.session_summary = SessionSummary::default();
+            return Err(self
+                .session_error
+                .take()
+                .unwrap_or(SchedulerError::Disconnected));
+        };
+        self.session_summary = summary;
+        self.session_error
+            .take()
+            .map_or(Ok(result_with_timings), Err)
     }
 
     fn start_session(&mut self, context: &SchedulingContext) {
@@ -692,10 +2350,25 @@ impl<S: SpawnableScheduler<TH>, TH: TaskHandler> ThreadManager<S, TH> {
     }
 }
 
+impl<S: SpawnableScheduler<TH>, TH: TaskHandler> Drop for ThreadManager<S, TH> {
+    // Stops the threads by disconnecting the scheduler thread, which then waits for the dispatched
+    // tasks (if any) to be completed.
+    fn drop(&mut self) {
+        drop(std::mem::replace(&mut self.new_task_sender, unbounded().0));
+        if let Some(threads) = self.threads.take() {
+            let thread_count = threads.thread_count();
+            threads.join();
+            self.pool
+                .spawned_thread_count
+                .fetch_sub(thread_count, Relaxed);
+        }
+    }
+}
+
 pub trait SpawnableScheduler<TH: TaskHandler>: InstalledScheduler {
     type Inner: Debug + Send + Sync;
 
-    fn into_inner(self) -> (ResultWithTimings, Self::Inner);
+    fn into_inner(self) -> (SchedulerResult<ResultWithTimings>, Self::Inner);
 
     fn from_inner(inner: Self::Inner, context: SchedulingContext) -> Self;
 
@@ -707,12 +2380,8 @@ pub trait SpawnableScheduler<TH: TaskHandler>: InstalledScheduler {
 impl<TH: TaskHandler> SpawnableScheduler<TH> for PooledScheduler<TH> {
     type Inner = PooledSchedulerInner<Self, TH>;
 
-    fn into_inner(mut self) -> (ResultWithTimings, Self::Inner) {
-        let result_with_timings = {
-            let manager = &mut self.inner.thread_manager;
-            manager.end_session();
-            manager.take_session_result_with_timings()
-        };
+    fn into_inner(mut self) -> (SchedulerResult<ResultWithTimings>, Self::Inner) {
+        let result_with_timings = self.inner.thread_manager.end_session_and_take_result();
         (result_with_timings, self.inner)
     }
 
@@ -722,11 +2391,16 @@ impl<TH: TaskHandler> SpawnableScheduler<TH> for PooledScheduler<TH> {
     }
 
     fn spawn(pool: Arc<SchedulerPool<Self, TH>>, initial_context: SchedulingContext) -> Self {
-        let mut scheduler = Self::do_spawn(pool, initial_context);
-        scheduler
-            .inner
-            .thread_manager
-            .start_threads(&scheduler.context);
+        let thread_start_policy = pool.config.thread_start_policy;
+        let scheduler = Self::do_spawn(pool, initial_context);
+        match thread_start_policy {
+            ThreadStartPolicy::Eager => scheduler
+                .inner
+                .thread_manager
+                .start_threads(&scheduler.context),
+            // deferred to schedule_execution()
+            ThreadStartPolicy::Lazy => {}
+        }
         scheduler
     }
 }
@@ -742,15 +2416,32 @@ impl<TH: TaskHandler> InstalledScheduler for PooledScheduler<TH> {
 
     fn schedule_execution(&self, &(transaction, index): &(&SanitizedTransaction, usize)) {
         let task = Task::create_task(transaction.clone(), index);
+        self.inner.thread_manager.start_threads(&self.context);
         self.inner.thread_manager.send_task(task);
     }
 
     fn wait_for_termination(
         self: Box<Self>,
-        _is_dropped: bool,
-    ) -> (ResultWithTimings, UninstalledSchedulerBox) {
-        let (result_with_timings, uninstalled_scheduler) = self.into_inner();
-        (result_with_timings, Box::new(uninstalled_scheduler))
+        is_dropped: bool,
+    ) -> (
+        SchedulerResult<ResultWithTimings>,
+        SessionSummary,
+        UninstalledSchedulerBox,
+    ) {
+        let (result_with_timings, uninstalled_scheduler) = if is_dropped {
+            // the result is going to be discarded; don't bother to execute the remaining tasks.
+            let mut inner = self.inner;
+            let result_with_timings = inner.thread_manager.abort_session_and_take_result();
+            (result_with_timings, inner)
+        } else {
+            self.into_inner()
+        };
+        let summary = uninstalled_scheduler.thread_manager.session_summary;
+        (
+            result_with_timings,
+            summary,
+            Box::new(uninstalled_scheduler),
+        )
     }
 
     fn pause_for_recent_blockhash(&mut self) {
@@ -764,12 +2455,17 @@ where
     TH: TaskHandler,
 {
     fn return_to_pool(self: Box<Self>) {
+        if self.thread_manager.is_scheduler_exited() {
+            // the failure has been reported as the session result already. a broken scheduler
+            // can't be reused, so just drop it to join the remaining threads.
+            return;
+        }
         self.thread_manager.pool.clone().return_scheduler(*self)
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use {
         super::*,
         assert_matches::assert_matches,
@@ -782,6 +2478,7 @@ mod tests {
         },
         solana_sdk::{
             clock::MAX_PROCESSING_AGE,
+            hash::Hash,
             pubkey::Pubkey,
             signer::keypair::Keypair,
             system_transaction,
@@ -806,118 +2503,1303 @@ mod tests {
     }
 
     #[test]
-    fn test_scheduler_spawn() {
-        solana_logger::setup();
-
-        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
-        let pool =
-            DefaultSchedulerPool::new_dyn(None, None, None, None, ignored_prioritization_fee_cache);
-        let bank = Arc::new(Bank::default_for_tests());
-        let context = SchedulingContext::new(bank);
-        let scheduler = pool.take_scheduler(context);
-
-        let debug = format!("{scheduler:#?}");
-        assert!(!debug.is_empty());
+    fn test_handler_timings_idle_ratio() {
+        let mut timings = HandlerTimings::default();
+        assert_eq!(timings.idle_ratio(), None);
+
+        timings.accumulate(&HandlerTimings {
+            idle: Duration::from_millis(30),
+            busy: Duration::from_millis(10),
+        });
+        timings.accumulate(&HandlerTimings {
+            idle: Duration::from_millis(10),
+            busy: Duration::from_millis(30),
+        });
+        assert_eq!(
+            timings,
+            HandlerTimings {
+                idle: Duration::from_millis(40),
+                busy: Duration::from_millis(40),
+            }
+        );
+        assert_eq!(timings.idle_ratio(), Some(0.5));
     }
 
     #[test]
-    fn test_scheduler_pool_filo() {
+    fn test_scheduler_pool_health() {
         solana_logger::setup();
 
         let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
         let pool =
             DefaultSchedulerPool::new(None, None, None, None, ignored_prioritization_fee_cache);
-        let bank = Arc::new(Bank::default_for_tests());
-        let context = &SchedulingContext::new(bank);
+        assert_eq!(pool.health(), SchedulerPoolHealth::Healthy);
 
-        let scheduler1 = pool.do_take_scheduler(context.clone());
-        let scheduler_id1 = scheduler1.id();
-        let scheduler2 = pool.do_take_scheduler(context.clone());
-        let scheduler_id2 = scheduler2.id();
-        assert_ne!(scheduler_id1, scheduler_id2);
+        let pool2 = pool.clone();
+        thread::spawn(move || {
+            let _panic_detector = PanicDetector(&pool2.panicked_thread_count);
+            panic!("handler panicked");
+        })
+        .join()
+        .unwrap_err();
+        assert_matches!(pool.health(), SchedulerPoolHealth::Degraded { .. });
+
+        let pool2 = pool.clone();
+        thread::spawn(move || {
+            let _guard = pool2.scheduler_inners.lock().unwrap();
+            panic!("poisoning");
+        })
+        .join()
+        .unwrap_err();
+        assert_matches!(pool.health(), SchedulerPoolHealth::Failed { .. });
+    }
 
-        let (result_with_timings, scheduler1) = scheduler1.into_inner();
-        assert_matches!(result_with_timings, (Ok(()), _));
-        pool.return_scheduler(scheduler1);
-        let (result_with_timings, scheduler2) = scheduler2.into_inner();
-        assert_matches!(result_with_timings, (Ok(()), _));
-        pool.return_scheduler(scheduler2);
+    #[test]
+    fn test_commit_order_verifier() {
+        let mut verifier = CommitOrderVerifier::default();
+        for index in [3, 5, 8] {
+            verifier.record_scheduled(index);
+        }
+        for index in [3, 5, 8] {
+            verifier.record_committed(index);
+        }
+        assert_eq!(verifier.take_divergences(), vec![]);
 
-        let scheduler3 = pool.do_take_scheduler(context.clone());
-        assert_eq!(scheduler_id2, scheduler3.id());
-        let scheduler4 = pool.do_take_scheduler(context.clone());
-        assert_eq!(scheduler_id1, scheduler4.id());
+        for index in [3, 5, 8] {
+            verifier.record_scheduled(index);
+        }
+        for index in [5, 3, 8] {
+            verifier.record_committed(index);
+        }
+        assert_eq!(
+            verifier.take_divergences(),
+            vec![CommitOrderDivergence {
+                position: 0,
+                expected_index: Some(3),
+                committed_index: 5,
+            }]
+        );
     }
 
     #[test]
-    fn test_scheduler_pool_context_drop_unless_reinitialized() {
+    fn test_scheduler_pool_verify_commit_order() {
         solana_logger::setup();
 
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let txs = (0..4)
+            .map(|_| {
+                SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+                    &mint_keypair,
+                    &solana_sdk::pubkey::new_rand(),
+                    2,
+                    genesis_config.hash(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let config = SchedulerPoolConfig {
+            verify_commit_order: true,
+            ..SchedulerPoolConfig::default()
+        };
         let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
-        let pool =
-            DefaultSchedulerPool::new(None, None, None, None, ignored_prioritization_fee_cache);
-        let bank = Arc::new(Bank::default_for_tests());
-        let context = &SchedulingContext::new(bank);
-        let mut scheduler = pool.do_take_scheduler(context.clone());
+        let pool = DefaultSchedulerPool::new_with_config(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+            config,
+        );
 
-        // should never panic.
-        scheduler.pause_for_recent_blockhash();
-        assert_matches!(
-            Box::new(scheduler).wait_for_termination(false),
-            ((Ok(()), _), _)
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank));
+        for (index, tx) in txs.iter().enumerate() {
+            scheduler.schedule_execution(&(tx, index));
+        }
+        let (result_with_timings, _inner) = scheduler.into_inner();
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        assert_eq!(pool.integrity_violation_count.load(Relaxed), 0);
+
+        // divergences are reported as integrity violations
+        pool.record_commit_order_divergences(
+            0,
+            0,
+            &[CommitOrderDivergence {
+                position: 0,
+                expected_index: Some(0),
+                committed_index: 1,
+            }],
         );
+        assert_eq!(pool.integrity_violation_count.load(Relaxed), 1);
+        assert_matches!(pool.health(), SchedulerPoolHealth::Degraded { .. });
     }
 
     #[test]
-    fn test_scheduler_pool_context_replace() {
+    fn test_scheduler_pool_task_counts() {
         solana_logger::setup();
 
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let tx0 = &SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            2,
+            genesis_config.hash(),
+        ));
         let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
         let pool =
             DefaultSchedulerPool::new(None, None, None, None, ignored_prioritization_fee_cache);
-        let old_bank = &Arc::new(Bank::default_for_tests());
-        let new_bank = &Arc::new(Bank::default_for_tests());
-        assert!(!Arc::ptr_eq(old_bank, new_bank));
-
-        let old_context = &SchedulingContext::new(old_bank.clone());
-        let new_context = &SchedulingContext::new(new_bank.clone());
-
-        let scheduler = pool.do_take_scheduler(old_context.clone());
-        let scheduler_id = scheduler.id();
-        pool.return_scheduler(scheduler.into_inner().1);
 
-        let scheduler = pool.take_scheduler(new_context.clone());
-        assert_eq!(scheduler_id, scheduler.id());
-        assert!(Arc::ptr_eq(scheduler.context().bank(), new_bank));
+        // a normal session has consistent counts
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank));
+        scheduler.schedule_execution(&(tx0, 0));
+        let (result_with_timings, _inner) = scheduler.into_inner();
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        assert_eq!(pool.integrity_violation_count.load(Relaxed), 0);
+        assert_eq!(pool.health(), SchedulerPoolHealth::Healthy);
+
+        // a lost task is detected
+        pool.record_task_counts(
+            0,
+            &TaskCounts {
+                sent: 2,
+                received: 2,
+                duplicate: 0,
+                dispatched: 2,
+                executed: 1,
+                aborted: 0,
+                completed: 1,
+            },
+        );
+        assert_eq!(pool.integrity_violation_count.load(Relaxed), 1);
+        assert_matches!(pool.health(), SchedulerPoolHealth::Degraded { .. });
     }
 
     #[test]
-    fn test_scheduler_pool_install_into_bank_forks() {
+    fn test_scheduler_pool_health_stall() {
         solana_logger::setup();
 
-        let bank = Bank::default_for_tests();
-        let bank_forks = BankForks::new_rw_arc(bank);
-        let mut bank_forks = bank_forks.write().unwrap();
+        #[derive(Debug)]
+        struct SlowTaskHandler;
+        impl TaskHandler for SlowTaskHandler {
+            fn handle(
+                result: &mut Result<()>,
+                timings: &mut ExecuteTimings,
+                bank: &Arc<Bank>,
+                transaction: &SanitizedTransaction,
+                index: usize,
+                handler_context: &HandlerContext,
+            ) {
+                thread::sleep(Duration::from_millis(500));
+                DefaultTaskHandler::handle(
+                    result,
+                    timings,
+                    bank,
+                    transaction,
+                    index,
+                    handler_context,
+                );
+            }
+        }
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let tx = SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            2,
+            genesis_config.hash(),
+        ));
+        let config = SchedulerPoolConfig {
+            stall_threshold: Duration::from_millis(100),
+            ..SchedulerPoolConfig::default()
+        };
         let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
-        let pool =
-            DefaultSchedulerPool::new_dyn(None, None, None, None, ignored_prioritization_fee_cache);
-        bank_forks.install_scheduler_pool(pool);
+        let pool = SchedulerPool::<PooledScheduler<SlowTaskHandler>, _>::new_with_config(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+            config,
+        );
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank));
+        assert_eq!(pool.health(), SchedulerPoolHealth::Healthy);
+
+        // the dispatched task isn't completed within the threshold
+        scheduler.schedule_execution(&(&tx, 0));
+        thread::sleep(Duration::from_millis(300));
+        assert_matches!(
+            pool.health(),
+            SchedulerPoolHealth::Degraded { reason } if reason.contains("completed none of 1")
+        );
+
+        // the stall recovers by itself once the task is completed
+        let (result_with_timings, _inner) = scheduler.into_inner();
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        assert_eq!(pool.health(), SchedulerPoolHealth::Healthy);
     }
 
     #[test]
-    fn test_scheduler_install_into_bank() {
+    fn test_scheduler_pool_max_spawns_per_second() {
         solana_logger::setup();
 
-        let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(10_000);
-        let bank = Arc::new(Bank::new_for_tests(&genesis_config));
-        let child_bank = Bank::new_from_parent(bank, &Pubkey::default(), 1);
-
+        let config = SchedulerPoolConfig {
+            max_spawns_per_second: Some(1),
+            ..SchedulerPoolConfig::default()
+        };
         let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
-        let pool =
-            DefaultSchedulerPool::new_dyn(None, None, None, None, ignored_prioritization_fee_cache);
+        let pool = DefaultSchedulerPool::new_with_config(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+            config,
+        );
+        let bank = Arc::new(Bank::default_for_tests());
+        let context = &SchedulingContext::new(bank);
 
-        let bank = Bank::default_for_tests();
-        let bank_forks = BankForks::new_rw_arc(bank);
+        let scheduler1 = pool.do_take_scheduler(context.clone());
+        let scheduler_id1 = scheduler1.id();
+
+        // the second take can't spawn within the same second, so waits for the first scheduler
+        let taker = thread::spawn({
+            let pool = pool.clone();
+            let context = context.clone();
+            move || pool.do_take_scheduler(context).id()
+        });
+        thread::sleep(Duration::from_millis(100));
+        assert!(!taker.is_finished());
+        assert_matches!(
+            pool.health(),
+            SchedulerPoolHealth::Degraded { reason } if reason.contains("waiting")
+        );
+        let (_result_with_timings, inner) = scheduler1.into_inner();
+        pool.return_scheduler(inner);
+        assert_eq!(taker.join().unwrap(), scheduler_id1);
+        assert_eq!(pool.throttled_take_count.load(Relaxed), 1);
+        assert_eq!(pool.health(), SchedulerPoolHealth::Healthy);
+    }
+
+    #[test]
+    fn test_scheduler_pool_thread_oversubscription() {
+        solana_logger::setup();
+
+        let oversubscription = ThreadOversubscription {
+            max_threads_per_core: 2,
+            auto_clamp: false,
+        };
+        assert_eq!(oversubscription.clamp_handler_count(8, 2), 3);
+        assert_eq!(oversubscription.clamp_handler_count(2, 2), 2);
+        assert_eq!(oversubscription.clamp_handler_count(8, 0), 1);
+
+        let config = SchedulerPoolConfig {
+            thread_oversubscription: Some(oversubscription),
+            ..SchedulerPoolConfig::default()
+        };
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = DefaultSchedulerPool::new_with_config(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+            config,
+        );
+        assert!(!pool.record_spawned_threads(2, Some(2)));
+        assert!(!pool.record_spawned_threads(2, Some(2)));
+        assert!(pool.record_spawned_threads(2, Some(2)));
+        // can't tell without the detected cpu cores
+        assert!(!pool.record_spawned_threads(2, None));
+        assert_eq!(pool.spawned_thread_count.load(Relaxed), 8);
+    }
+
+    #[test]
+    fn test_scheduler_pool_idle_scheduler_pruning() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let tx0 = &SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            2,
+            genesis_config.hash(),
+        ));
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let idle_scheduler_ttl = Duration::from_secs(1);
+        let config = SchedulerPoolConfig {
+            idle_scheduler_ttl: Some(idle_scheduler_ttl),
+            ..SchedulerPoolConfig::default()
+        };
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = DefaultSchedulerPool::new_with_config(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+            config,
+        );
+
+        let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank.clone()));
+        scheduler.schedule_execution(&(tx0, 0));
+        let (result_with_timings, inner) = scheduler.into_inner();
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        pool.return_scheduler(inner);
+        assert_eq!(pool.spawned_thread_count.load(Relaxed), 2);
+
+        // recently-returned schedulers are retained
+        assert_eq!(pool.prune_idle_schedulers(Duration::from_secs(60)), 0);
+
+        // the watchdog thread prunes the scheduler, stopping its threads cleanly
+        let started = Instant::now();
+        while pool.spawned_thread_count.load(Relaxed) > 0 {
+            assert!(started.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(pool.scheduler_inners.lock().unwrap().is_empty());
+        assert_eq!(pool.health(), SchedulerPoolHealth::Healthy);
+        assert_eq!(bank.transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_scheduler_pool_self_test() {
+        solana_logger::setup();
+
+        let (status_sender, status_receiver) = crossbeam_channel::unbounded();
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = DefaultSchedulerPool::new(
+            None,
+            None,
+            Some(TransactionStatusSender {
+                sender: status_sender,
+            }),
+            None,
+            ignored_prioritization_fee_cache,
+        );
+        assert!(pool.self_test().is_ok());
+        // the self-test is run by a separate pool, without leaking any transaction statuses
+        assert_eq!(pool.scheduler_inners.lock().unwrap().len(), 0);
+        assert_eq!(pool.spawned_thread_count.load(Relaxed), 0);
+        assert!(status_receiver.is_empty());
+        assert_eq!(pool.health(), SchedulerPoolHealth::Healthy);
+    }
+
+    #[test]
+    fn test_scheduler_spawn() {
+        solana_logger::setup();
+
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool =
+            DefaultSchedulerPool::new_dyn(None, None, None, None, ignored_prioritization_fee_cache);
+        let bank = Arc::new(Bank::default_for_tests());
+        let context = SchedulingContext::new(bank);
+        let scheduler = pool.take_scheduler(context);
+
+        let debug = format!("{scheduler:#?}");
+        assert!(!debug.is_empty());
+    }
+
+    #[test]
+    fn test_scheduler_lazy_thread_start() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let tx0 = &SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            2,
+            genesis_config.hash(),
+        ));
+        let bank = Bank::new_for_tests(&genesis_config);
+        let bank = setup_dummy_fork_graph(bank);
+        let config = SchedulerPoolConfig {
+            thread_start_policy: ThreadStartPolicy::Lazy,
+            ..SchedulerPoolConfig::default()
+        };
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = DefaultSchedulerPool::new_with_config(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+            config,
+        );
+
+        // a session without any transaction never spawns threads
+        let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank.clone()));
+        assert!(!scheduler.inner.thread_manager.are_threads_started());
+        let (result_with_timings, inner) = scheduler.into_inner();
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        assert!(!inner.thread_manager.are_threads_started());
+        pool.return_scheduler(inner);
+
+        // the returned scheduler spawns threads on the first transaction
+        let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank.clone()));
+        assert!(!scheduler.inner.thread_manager.are_threads_started());
+        scheduler.schedule_execution(&(tx0, 0));
+        assert!(scheduler.inner.thread_manager.are_threads_started());
+        let (result_with_timings, _inner) = scheduler.into_inner();
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        assert_eq!(bank.transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_scheduler_session_summary() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let txs: Vec<_> = (1..=2)
+            .map(|lamports| {
+                SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+                    &mint_keypair,
+                    &solana_sdk::pubkey::new_rand(),
+                    lamports,
+                    genesis_config.hash(),
+                ))
+            })
+            .collect();
+        let bank = Bank::new_for_tests(&genesis_config);
+        let bank = setup_dummy_fork_graph(bank);
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool =
+            DefaultSchedulerPool::new(None, None, None, None, ignored_prioritization_fee_cache);
+
+        let scheduler = pool.take_scheduler(SchedulingContext::new(bank.clone()));
+        for (index, tx) in txs.iter().enumerate() {
+            scheduler.schedule_execution(&(tx, index));
+        }
+        let (result_with_timings, summary, uninstalled_scheduler) =
+            scheduler.wait_for_termination(false);
+        uninstalled_scheduler.return_to_pool();
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        assert_eq!(bank.transaction_count(), 2);
+        let (first_dispatch, last_commit) = (
+            summary.first_dispatch.unwrap(),
+            summary.last_commit.unwrap(),
+        );
+        assert!(first_dispatch <= last_commit);
+
+        // the summary isn't carried over to the next session
+        let scheduler = pool.take_scheduler(SchedulingContext::new(bank));
+        let (result_with_timings, summary, uninstalled_scheduler) =
+            scheduler.wait_for_termination(false);
+        uninstalled_scheduler.return_to_pool();
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        assert_eq!(summary, SessionSummary::default());
+    }
+
+    #[test]
+    fn test_scheduler_pre_check_transaction_age() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let expired_tx =
+            &SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+                &mint_keypair,
+                &solana_sdk::pubkey::new_rand(),
+                2,
+                Hash::new_unique(),
+            ));
+        let valid_tx =
+            &SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+                &mint_keypair,
+                &solana_sdk::pubkey::new_rand(),
+                2,
+                genesis_config.hash(),
+            ));
+
+        for pre_check_transaction_age in [false, true] {
+            let config = SchedulerPoolConfig {
+                pre_check_transaction_age,
+                ..SchedulerPoolConfig::default()
+            };
+            let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+            let pool = DefaultSchedulerPool::new_with_config(
+                None,
+                None,
+                None,
+                None,
+                ignored_prioritization_fee_cache,
+                config,
+            );
+
+            // the results must be the same regardless of the pre-check
+            let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+            let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank.clone()));
+            scheduler.schedule_execution(&(expired_tx, 0));
+            let (result_with_timings, _inner) = scheduler.into_inner();
+            assert_matches!(
+                result_with_timings,
+                Ok((Err(TransactionError::BlockhashNotFound), _))
+            );
+            assert_eq!(bank.transaction_count(), 0);
+
+            let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+            let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank.clone()));
+            scheduler.schedule_execution(&(valid_tx, 0));
+            let (result_with_timings, _inner) = scheduler.into_inner();
+            assert_matches!(result_with_timings, Ok((Ok(()), _)));
+            assert_eq!(bank.transaction_count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_scheduler_completion_batching() {
+        solana_logger::setup();
+
+        // lets the following tasks queue up while the first one is being executed, so that their
+        // completions are held back.
+        #[derive(Debug)]
+        struct SlowFirstTaskHandler;
+        impl TaskHandler for SlowFirstTaskHandler {
+            fn handle(
+                result: &mut Result<()>,
+                timings: &mut ExecuteTimings,
+                bank: &Arc<Bank>,
+                transaction: &SanitizedTransaction,
+                index: usize,
+                handler_context: &HandlerContext,
+            ) {
+                if index == 0 {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                DefaultTaskHandler::handle(
+                    result,
+                    timings,
+                    bank,
+                    transaction,
+                    index,
+                    handler_context,
+                );
+            }
+        }
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let txs = (0..10)
+            .map(|_| {
+                SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+                    &mint_keypair,
+                    &solana_sdk::pubkey::new_rand(),
+                    2,
+                    genesis_config.hash(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let config = SchedulerPoolConfig {
+            completion_batching: Some(CompletionBatching {
+                max_count: 4,
+                max_delay: Duration::from_secs(1),
+            }),
+            ..SchedulerPoolConfig::default()
+        };
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = SchedulerPool::<PooledScheduler<SlowFirstTaskHandler>, _>::new_with_config(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+            config,
+        );
+
+        // every completion must be reported eventually, even if the handler runs out of work while
+        // holding some of them back.
+        for _ in 0..2 {
+            let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+            let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank.clone()));
+            for (index, tx) in txs.iter().enumerate() {
+                scheduler.schedule_execution(&(tx, index));
+            }
+            let (result_with_timings, inner) = scheduler.into_inner();
+            assert_matches!(result_with_timings, Ok((Ok(()), _)));
+            assert_eq!(bank.transaction_count(), txs.len() as u64);
+            pool.return_scheduler(inner);
+        }
+    }
+
+    #[test]
+    fn test_scheduler_handler_panic() {
+        solana_logger::setup();
+
+        #[derive(Debug)]
+        struct PanickingTaskHandler;
+        impl TaskHandler for PanickingTaskHandler {
+            fn handle(
+                _result: &mut Result<()>,
+                _timings: &mut ExecuteTimings,
+                _bank: &Arc<Bank>,
+                _transaction: &SanitizedTransaction,
+                index: usize,
+                _handler_context: &HandlerContext,
+            ) {
+                panic!("task {index} panicked");
+            }
+        }
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let tx = SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            2,
+            genesis_config.hash(),
+        ));
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = SchedulerPool::<PooledScheduler<PanickingTaskHandler>, _>::new(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+        );
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank));
+        scheduler.schedule_execution(&(&tx, 3));
+
+        // the panic ends the session with its payload, instead of hanging or panicking the caller
+        let (result_with_timings, summary, uninstalled_scheduler) =
+            Box::new(scheduler).wait_for_termination(false);
+        assert_matches!(
+            result_with_timings,
+            Err(SchedulerError::ThreadPanicked(message)) if message == "task 3 panicked"
+        );
+        assert_eq!(summary, SessionSummary::default());
+        assert_matches!(pool.health(), SchedulerPoolHealth::Degraded { .. });
+
+        // the broken scheduler is dropped along with its threads, instead of being pooled
+        uninstalled_scheduler.return_to_pool();
+        assert!(pool.scheduler_inners.lock().unwrap().is_empty());
+        assert_eq!(pool.spawned_thread_count.load(Relaxed), 0);
+    }
+
+    #[test]
+    fn test_scheduler_schedule_execution_after_pause() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let tx = SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            2,
+            genesis_config.hash(),
+        ));
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool =
+            DefaultSchedulerPool::new(None, None, None, None, ignored_prioritization_fee_cache);
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let mut scheduler = pool.do_take_scheduler(SchedulingContext::new(bank.clone()));
+        scheduler.pause_for_recent_blockhash();
+
+        // the task is dropped, as the session has been ended already
+        scheduler.schedule_execution(&(&tx, 0));
+        let (result_with_timings, inner) = scheduler.into_inner();
+        assert_matches!(result_with_timings, Err(SchedulerError::SessionEnded));
+        assert_eq!(bank.transaction_count(), 0);
+
+        // the scheduler itself isn't broken
+        pool.return_scheduler(inner);
+        let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank.clone()));
+        scheduler.schedule_execution(&(&tx, 0));
+        let (result_with_timings, _inner) = scheduler.into_inner();
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        assert_eq!(bank.transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_scheduler_abort() {
+        solana_logger::setup();
+
+        // keeps the first task executing, so that the following tasks are still queued when
+        // aborted.
+        #[derive(Debug)]
+        struct SlowFirstTaskHandler;
+        impl TaskHandler for SlowFirstTaskHandler {
+            fn handle(
+                result: &mut Result<()>,
+                timings: &mut ExecuteTimings,
+                bank: &Arc<Bank>,
+                transaction: &SanitizedTransaction,
+                index: usize,
+                handler_context: &HandlerContext,
+            ) {
+                if index == 0 {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                DefaultTaskHandler::handle(
+                    result,
+                    timings,
+                    bank,
+                    transaction,
+                    index,
+                    handler_context,
+                );
+            }
+        }
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let txs = (0..10)
+            .map(|_| {
+                SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+                    &mint_keypair,
+                    &solana_sdk::pubkey::new_rand(),
+                    2,
+                    genesis_config.hash(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = SchedulerPool::<PooledScheduler<SlowFirstTaskHandler>, _>::new(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+        );
+
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank.clone()));
+        for (index, tx) in txs.iter().enumerate() {
+            scheduler.schedule_execution(&(tx, index));
+        }
+        let (result_with_timings, _summary, uninstalled_scheduler) =
+            Box::new(scheduler).wait_for_termination(true);
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        assert!(bank.transaction_count() < txs.len() as u64);
+        uninstalled_scheduler.return_to_pool();
+        assert_eq!(pool.integrity_violation_count.load(Relaxed), 0);
+
+        // the abort doesn't carry over to the next session of the returned scheduler
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank.clone()));
+        for (index, tx) in txs.iter().enumerate() {
+            scheduler.schedule_execution(&(tx, index));
+        }
+        let (result_with_timings, _summary, _uninstalled_scheduler) =
+            Box::new(scheduler).wait_for_termination(false);
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        assert_eq!(bank.transaction_count(), txs.len() as u64);
+        assert_eq!(pool.integrity_violation_count.load(Relaxed), 0);
+    }
+
+    #[test]
+    fn test_scheduler_max_in_flight_task_count() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let txs = (0..10)
+            .map(|_| {
+                SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+                    &mint_keypair,
+                    &solana_sdk::pubkey::new_rand(),
+                    2,
+                    genesis_config.hash(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let config = SchedulerPoolConfig {
+            max_in_flight_task_count: Some(2),
+            ..SchedulerPoolConfig::default()
+        };
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = DefaultSchedulerPool::new_with_config(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+            config,
+        );
+
+        // scheduling more tasks than the limit blocks until earlier ones are completed, instead
+        // of deadlocking. The slots must be freed across sessions as well.
+        for _ in 0..2 {
+            let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+            let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank.clone()));
+            for (index, tx) in txs.iter().enumerate() {
+                scheduler.schedule_execution(&(tx, index));
+            }
+            let (result_with_timings, inner) = scheduler.into_inner();
+            assert_matches!(result_with_timings, Ok((Ok(()), _)));
+            assert_eq!(bank.transaction_count(), txs.len() as u64);
+            assert_matches!(
+                inner.thread_manager.in_flight_task_slots,
+                Some((_, ref receiver)) if receiver.is_empty()
+            );
+            pool.return_scheduler(inner);
+        }
+    }
+
+    #[test]
+    fn test_scheduler_reject_duplicate_transactions() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let tx0 = &SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            2,
+            genesis_config.hash(),
+        ));
+
+        // the results are the same regardless of the rejection by the scheduler thread
+        for reject_duplicate_transactions in [false, true] {
+            let config = SchedulerPoolConfig {
+                reject_duplicate_transactions,
+                ..SchedulerPoolConfig::default()
+            };
+            let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+            let pool = DefaultSchedulerPool::new_with_config(
+                None,
+                None,
+                None,
+                None,
+                ignored_prioritization_fee_cache,
+                config,
+            );
+
+            // duplicates are only detected within a session
+            for _ in 0..2 {
+                let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+                let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank.clone()));
+                scheduler.schedule_execution(&(tx0, 0));
+                scheduler.schedule_execution(&(tx0, 1));
+                let (result_with_timings, inner) = scheduler.into_inner();
+                assert_matches!(
+                    result_with_timings,
+                    Ok((Err(TransactionError::AlreadyProcessed), _))
+                );
+                assert_eq!(bank.transaction_count(), 1);
+                pool.return_scheduler(inner);
+            }
+            assert_eq!(pool.integrity_violation_count.load(Relaxed), 0);
+        }
+    }
+
+    #[test]
+    fn test_scheduler_pool_shutdown_all() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(10_000);
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool =
+            DefaultSchedulerPool::new(None, None, None, None, ignored_prioritization_fee_cache);
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let context = &SchedulingContext::new(bank);
+
+        let scheduler1 = pool.do_take_scheduler(context.clone());
+        let scheduler2 = pool.do_take_scheduler(context.clone());
+        let thread_count_per_scheduler = pool.spawned_thread_count.load(Relaxed) / 2;
+        Box::new(scheduler1)
+            .wait_for_termination(false)
+            .2
+            .return_to_pool();
+
+        // only the pooled scheduler is stopped
+        assert_eq!(pool.shutdown_all(), 1);
+        assert_eq!(
+            pool.spawned_thread_count.load(Relaxed),
+            thread_count_per_scheduler
+        );
+
+        // the scheduler in use is stopped once returned
+        Box::new(scheduler2)
+            .wait_for_termination(false)
+            .2
+            .return_to_pool();
+        assert_eq!(pool.spawned_thread_count.load(Relaxed), 0);
+        assert!(pool.scheduler_inners.lock().unwrap().is_empty());
+        assert_eq!(pool.health(), SchedulerPoolHealth::Healthy);
+        // the pool is no longer referred to by the schedulers
+        assert_eq!(Arc::strong_count(&pool), 1);
+    }
+
+    #[test]
+    fn test_scheduler_transaction_status_batching() {
+        solana_logger::setup();
+
+        // lets the following tasks queue up while the first one is being executed, so that their
+        // statuses are accumulated.
+        #[derive(Debug)]
+        struct SlowFirstTaskHandler;
+        impl TaskHandler for SlowFirstTaskHandler {
+            fn handle(
+                result: &mut Result<()>,
+                timings: &mut ExecuteTimings,
+                bank: &Arc<Bank>,
+                transaction: &SanitizedTransaction,
+                index: usize,
+                handler_context: &HandlerContext,
+            ) {
+                if index == 0 {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                DefaultTaskHandler::handle(
+                    result,
+                    timings,
+                    bank,
+                    transaction,
+                    index,
+                    handler_context,
+                );
+            }
+        }
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let txs = (0..10)
+            .map(|_| {
+                SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+                    &mint_keypair,
+                    &solana_sdk::pubkey::new_rand(),
+                    2,
+                    genesis_config.hash(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        let config = SchedulerPoolConfig {
+            transaction_status_batching: Some(TransactionStatusBatching {
+                max_count: 4,
+                max_delay: Duration::from_secs(1),
+            }),
+            ..SchedulerPoolConfig::default()
+        };
+        let (status_sender, status_receiver) = unbounded();
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = SchedulerPool::<PooledScheduler<SlowFirstTaskHandler>, _>::new_with_config(
+            None,
+            None,
+            Some(TransactionStatusSender {
+                sender: status_sender,
+            }),
+            None,
+            ignored_prioritization_fee_cache,
+            config,
+        );
+
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank));
+        for (index, tx) in txs.iter().enumerate() {
+            scheduler.schedule_execution(&(tx, index));
+        }
+        let (result_with_timings, _inner) = scheduler.into_inner();
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+
+        // every status has been sent by the end of the session, in order and merged into fewer
+        // batches, none of which exceeds the limit.
+        let batches = status_receiver
+            .try_iter()
+            .map(|message| match message {
+                TransactionStatusMessage::Batch(batch) => batch,
+                TransactionStatusMessage::Freeze(_) => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert!(batches.len() < txs.len());
+        assert!(batches.iter().all(|batch| batch.transactions.len() <= 4));
+        assert_eq!(
+            batches
+                .iter()
+                .flat_map(|batch| batch.transaction_indexes.iter().copied())
+                .collect::<Vec<_>>(),
+            (0..txs.len()).collect::<Vec<_>>()
+        );
+        assert!(batches.iter().all(|batch| {
+            [
+                batch.execution_results.len(),
+                batch.balances.pre_balances.len(),
+                batch.balances.post_balances.len(),
+                batch.token_balances.pre_token_balances.len(),
+                batch.token_balances.post_token_balances.len(),
+                batch.rent_debits.len(),
+            ]
+            .iter()
+            .all(|len| *len == batch.transactions.len())
+        }));
+    }
+
+    #[test]
+    fn test_scheduler_pool_max_pooled_schedulers() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(10_000);
+        let config = SchedulerPoolConfig {
+            max_pooled_schedulers: Some(1),
+            ..SchedulerPoolConfig::default()
+        };
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = DefaultSchedulerPool::new_with_config(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+            config,
+        );
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let context = &SchedulingContext::new(bank);
+
+        let scheduler1 = pool.do_take_scheduler(context.clone());
+        let scheduler2 = pool.do_take_scheduler(context.clone());
+        assert_eq!(pool.exhausted_take_count.load(Relaxed), 2);
+        let thread_count_per_scheduler = pool.spawned_thread_count.load(Relaxed) / 2;
+        for scheduler in [scheduler1, scheduler2] {
+            Box::new(scheduler)
+                .wait_for_termination(false)
+                .2
+                .return_to_pool();
+        }
+
+        // the second returned scheduler is dropped along with its threads
+        assert_eq!(pool.scheduler_inners.lock().unwrap().len(), 1);
+        assert_eq!(pool.spilled_scheduler_count.load(Relaxed), 1);
+        assert_eq!(
+            pool.spawned_thread_count.load(Relaxed),
+            thread_count_per_scheduler
+        );
+
+        // the pooled one is reused
+        let _scheduler = pool.do_take_scheduler(context.clone());
+        assert_eq!(pool.exhausted_take_count.load(Relaxed), 2);
+    }
+
+    #[test]
+    fn test_scheduler_checkpoint() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let expired_tx =
+            &SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+                &mint_keypair,
+                &solana_sdk::pubkey::new_rand(),
+                2,
+                Hash::default(),
+            ));
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool =
+            DefaultSchedulerPool::new(None, None, None, None, ignored_prioritization_fee_cache);
+
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let mut scheduler = pool.do_take_scheduler(SchedulingContext::new(bank));
+        assert_matches!(scheduler.checkpoint(), Ok((Ok(()), _)));
+
+        // the failure eventually shows up in a checkpoint, before the session ends
+        scheduler.schedule_execution(&(expired_tx, 0));
+        while scheduler.checkpoint().unwrap().0.is_ok() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_matches!(
+            scheduler.checkpoint(),
+            Ok((Err(TransactionError::BlockhashNotFound), _))
+        );
+
+        // checkpoints don't consume the result, even after paused
+        scheduler.pause_for_recent_blockhash();
+        assert_matches!(
+            scheduler.checkpoint(),
+            Ok((Err(TransactionError::BlockhashNotFound), _))
+        );
+        let (result_with_timings, _inner) = scheduler.into_inner();
+        assert_matches!(
+            result_with_timings,
+            Ok((Err(TransactionError::BlockhashNotFound), _))
+        );
+    }
+
+    #[test]
+    fn test_scheduler_thread_placement() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let config = SchedulerPoolConfig {
+            handler_core_ids: Some(vec![0]),
+            // lowering the priority is always permitted, unlike raising it.
+            scheduler_thread_niceness_adj: 1,
+            ..SchedulerPoolConfig::default()
+        };
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = DefaultSchedulerPool::new_with_config(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+            config,
+        );
+        let bank = setup_dummy_fork_graph(Bank::new_for_tests(&genesis_config));
+        let tx = &SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            2,
+            genesis_config.hash(),
+        ));
+
+        let scheduler = pool.do_take_scheduler(SchedulingContext::new(bank.clone()));
+        scheduler.schedule_execution(&(tx, 0));
+        let (result_with_timings, _inner) = scheduler.into_inner();
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        assert_eq!(bank.transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_scheduler_pool_filo() {
+        solana_logger::setup();
+
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool =
+            DefaultSchedulerPool::new(None, None, None, None, ignored_prioritization_fee_cache);
+        let bank = Arc::new(Bank::default_for_tests());
+        let context = &SchedulingContext::new(bank);
+
+        let scheduler1 = pool.do_take_scheduler(context.clone());
+        let scheduler_id1 = scheduler1.id();
+        let scheduler2 = pool.do_take_scheduler(context.clone());
+        let scheduler_id2 = scheduler2.id();
+        assert_ne!(scheduler_id1, scheduler_id2);
+
+        let (result_with_timings, scheduler1) = scheduler1.into_inner();
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        pool.return_scheduler(scheduler1);
+        let (result_with_timings, scheduler2) = scheduler2.into_inner();
+        assert_matches!(result_with_timings, Ok((Ok(()), _)));
+        pool.return_scheduler(scheduler2);
+
+        let scheduler3 = pool.do_take_scheduler(context.clone());
+        assert_eq!(scheduler_id2, scheduler3.id());
+        let scheduler4 = pool.do_take_scheduler(context.clone());
+        assert_eq!(scheduler_id1, scheduler4.id());
+    }
+
+    #[test]
+    fn test_scheduler_pool_context_drop_unless_reinitialized() {
+        solana_logger::setup();
+
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool =
+            DefaultSchedulerPool::new(None, None, None, None, ignored_prioritization_fee_cache);
+        let bank = Arc::new(Bank::default_for_tests());
+        let context = &SchedulingContext::new(bank);
+        let mut scheduler = pool.do_take_scheduler(context.clone());
+
+        // should never panic.
+        scheduler.pause_for_recent_blockhash();
+        assert_matches!(
+            Box::new(scheduler).wait_for_termination(false),
+            (Ok((Ok(()), _)), SessionSummary::default(), _)
+        );
+    }
+
+    #[test]
+    fn test_scheduler_pool_context_replace() {
+        solana_logger::setup();
+
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool =
+            DefaultSchedulerPool::new(None, None, None, None, ignored_prioritization_fee_cache);
+        let old_bank = &Arc::new(Bank::default_for_tests());
+        let new_bank = &Arc::new(Bank::default_for_tests());
+        assert!(!Arc::ptr_eq(old_bank, new_bank));
+
+        let old_context = &SchedulingContext::new(old_bank.clone());
+        let new_context = &SchedulingContext::new(new_bank.clone());
+
+        let scheduler = pool.do_take_scheduler(old_context.clone());
+        let scheduler_id = scheduler.id();
+        pool.return_scheduler(scheduler.into_inner().1);
+
+        let scheduler = pool.take_scheduler(new_context.clone());
+        assert_eq!(scheduler_id, scheduler.id());
+        assert!(Arc::ptr_eq(scheduler.context().bank(), new_bank));
+    }
+
+    #[test]
+    fn test_scheduler_pool_install_into_bank_forks() {
+        solana_logger::setup();
+
+        let bank = Bank::default_for_tests();
+        let bank_forks = BankForks::new_rw_arc(bank);
+        let mut bank_forks = bank_forks.write().unwrap();
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool =
+            DefaultSchedulerPool::new_dyn(None, None, None, None, ignored_prioritization_fee_cache);
+        bank_forks.install_scheduler_pool(pool);
+    }
+
+    #[test]
+    fn test_scheduler_install_into_bank() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo { genesis_config, .. } = create_genesis_config(10_000);
+        let bank = Arc::new(Bank::new_for_tests(&genesis_config));
+        let child_bank = Bank::new_from_parent(bank, &Pubkey::default(), 1);
+
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool =
+            DefaultSchedulerPool::new_dyn(None, None, None, None, ignored_prioritization_fee_cache);
+
+        let bank = Bank::default_for_tests();
+        let bank_forks = BankForks::new_rw_arc(bank);
         let mut bank_forks = bank_forks.write().unwrap();
 
         // existing banks in bank_forks shouldn't process transactions anymore in general, so
@@ -937,7 +3819,7 @@ mod tests {
         assert!(!child_bank.has_installed_scheduler());
     }
 
-    fn setup_dummy_fork_graph(bank: Bank) -> Arc<Bank> {
+    pub(crate) fn setup_dummy_fork_graph(bank: Bank) -> Arc<Bank> {
         let slot = bank.slot();
         let bank_fork = BankForks::new_rw_arc(bank);
         let bank = bank_fork.read().unwrap().get(slot).unwrap();
@@ -971,10 +3853,112 @@ mod tests {
         let scheduler = pool.take_scheduler(context);
         scheduler.schedule_execution(&(tx0, 0));
         let bank = BankWithScheduler::new(bank, Some(scheduler));
-        assert_matches!(bank.wait_for_completed_scheduler(), Some((Ok(()), _)));
+        assert_matches!(bank.wait_for_completed_scheduler(), Some(Ok((Ok(()), _))));
+        assert_eq!(bank.transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_scheduler_schedule_execution_with_rayon_handler_executor() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let tx0 = &SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            2,
+            genesis_config.hash(),
+        ));
+        let bank = Bank::new_for_tests(&genesis_config);
+        let bank = setup_dummy_fork_graph(bank);
+        let thread_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let config = SchedulerPoolConfig {
+            handler_executor: Arc::new(RayonHandlerExecutor(Arc::new(thread_pool))),
+            ..SchedulerPoolConfig::default()
+        };
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = DefaultSchedulerPool::new_dyn_with_config(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+            config,
+        );
+        let context = SchedulingContext::new(bank.clone());
+
+        assert_eq!(bank.transaction_count(), 0);
+        let scheduler = pool.take_scheduler(context);
+        scheduler.schedule_execution(&(tx0, 0));
+        let bank = BankWithScheduler::new(bank, Some(scheduler));
+        assert_matches!(bank.wait_for_completed_scheduler(), Some(Ok((Ok(()), _))));
         assert_eq!(bank.transaction_count(), 1);
     }
 
+    #[test]
+    fn test_scheduler_schedule_execution_with_event_log() {
+        solana_logger::setup();
+
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(10_000);
+        let tx0 = &SanitizedTransaction::from_transaction_for_tests(system_transaction::transfer(
+            &mint_keypair,
+            &solana_sdk::pubkey::new_rand(),
+            2,
+            genesis_config.hash(),
+        ));
+        let bank = Bank::new_for_tests(&genesis_config);
+        let bank = setup_dummy_fork_graph(bank);
+        let event_log = Arc::new(EventLog::new(16));
+        let config = SchedulerPoolConfig {
+            event_log: Some(event_log.clone()),
+            ..SchedulerPoolConfig::default()
+        };
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        let pool = DefaultSchedulerPool::new_dyn_with_config(
+            None,
+            None,
+            None,
+            None,
+            ignored_prioritization_fee_cache,
+            config,
+        );
+        let context = SchedulingContext::new(bank.clone());
+
+        let scheduler = pool.take_scheduler(context);
+        scheduler.schedule_execution(&(tx0, 0));
+        let bank = BankWithScheduler::new(bank, Some(scheduler));
+        assert_matches!(bank.wait_for_completed_scheduler(), Some(Ok((Ok(()), _))));
+
+        let kinds = event_log
+            .snapshot()
+            .iter()
+            .map(|record| record.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                EventKind::SessionStarted,
+                EventKind::TaskReceived,
+                EventKind::TaskExecutionStarted,
+                EventKind::TaskExecutionFinished,
+                EventKind::CompletionReceived,
+                EventKind::TaskCompleted,
+                EventKind::SessionEnded,
+            ]
+            .map(|kind| kind as u32)
+        );
+    }
+
     #[test]
     fn test_scheduler_schedule_execution_failure() {
         solana_logger::setup();
@@ -1034,10 +4018,10 @@ mod tests {
         let bank = BankWithScheduler::new(bank, Some(scheduler));
         assert_matches!(
             bank.wait_for_completed_scheduler(),
-            Some((
+            Some(Ok((
                 Err(solana_sdk::transaction::TransactionError::AccountNotFound),
                 _timings
-            ))
+            )))
         );
     }
 
@@ -1104,13 +4088,17 @@ mod tests {
         fn wait_for_termination(
             self: Box<Self>,
             _is_dropped: bool,
-        ) -> (ResultWithTimings, UninstalledSchedulerBox) {
+        ) -> (
+            SchedulerResult<ResultWithTimings>,
+            SessionSummary,
+            UninstalledSchedulerBox,
+        ) {
             self.do_wait();
             let result_with_timings = std::mem::replace(
                 &mut *self.0.lock().unwrap(),
                 initialized_result_with_timings(),
             );
-            (result_with_timings, self)
+            (Ok(result_with_timings), SessionSummary::default(), self)
         }
 
         fn pause_for_recent_blockhash(&mut self) {
@@ -1137,7 +4125,7 @@ mod tests {
         // well, i wish i can use ! (never type).....
         type Inner = Self;
 
-        fn into_inner(self) -> (ResultWithTimings, Self::Inner) {
+        fn into_inner(self) -> (SchedulerResult<ResultWithTimings>, Self::Inner) {
             unimplemented!();
         }
 
@@ -1212,11 +4200,11 @@ mod tests {
             // very_old_valid_tx is wrongly handled as expired!
             assert_matches!(
                 bank.wait_for_completed_scheduler(),
-                Some((Err(TransactionError::BlockhashNotFound), _))
+                Some(Ok((Err(TransactionError::BlockhashNotFound), _)))
             );
             assert_eq!(bank.transaction_count(), 0);
         } else {
-            assert_matches!(bank.wait_for_completed_scheduler(), Some((Ok(()), _)));
+            assert_matches!(bank.wait_for_completed_scheduler(), Some(Ok((Ok(()), _))));
             assert_eq!(bank.transaction_count(), 1);
         }
     }
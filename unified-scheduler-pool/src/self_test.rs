@@ -0,0 +1,124 @@
+//! A startup self-test of [`SchedulerPool`], which replays canned transfers against a throwaway
+//! bank.
+//!
+//! The transfers are run by a separate short-lived pool sharing the config and the handler count
+//! of the tested pool, but none of its senders and its prioritization fee cache. Otherwise, the
+//! throwaway bank's transaction statuses and votes would leak into the validator (e.g. the
+//! blockstore and RPC history).
+
+use {
+    crate::{SchedulerPool, SchedulerPoolHealth, SpawnableScheduler, TaskHandler},
+    solana_accounts_db::{
+        accounts_db::{AccountShrinkThreshold, ACCOUNTS_DB_CONFIG_FOR_BENCHMARKS},
+        accounts_index::AccountSecondaryIndexes,
+    },
+    solana_metrics::datapoint_info,
+    solana_program_runtime::runtime_config::RuntimeConfig,
+    solana_runtime::{
+        bank::Bank,
+        bank_forks::BankForks,
+        genesis_utils::{create_genesis_config, GenesisConfigInfo},
+        installed_scheduler_pool::{InstalledScheduler, SchedulingContext},
+        prioritization_fee_cache::PrioritizationFeeCache,
+    },
+    solana_sdk::{pubkey::Pubkey, system_transaction, transaction::SanitizedTransaction},
+    std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+};
+
+impl<S, TH> SchedulerPool<S, TH>
+where
+    S: SpawnableScheduler<TH>,
+    TH: TaskHandler,
+{
+    /// Runs a canned set of conflicting transfers through a newly-spawned scheduler against a
+    /// throwaway bank and returns how long it took, verifying the resulting balances and that the
+    /// scheduler's pool is still healthy after the scheduler is returned to it.
+    ///
+    /// This is intended to be called at startup, so that misconfigurations (e.g. thread limits)
+    /// surface before any real block is replayed. This pool itself is left untouched.
+    pub fn self_test(&self) -> std::result::Result<Duration, String> {
+        const TRANSFER_COUNT: u64 = 8;
+
+        let start = Instant::now();
+        let GenesisConfigInfo {
+            genesis_config,
+            mint_keypair,
+            ..
+        } = create_genesis_config(1_000_000_000);
+        let bank = Bank::new_with_paths(
+            &genesis_config,
+            Arc::<RuntimeConfig>::default(),
+            vec![],
+            None,
+            None,
+            AccountSecondaryIndexes::default(),
+            AccountShrinkThreshold::default(),
+            false,
+            Some(ACCOUNTS_DB_CONFIG_FOR_BENCHMARKS),
+            None,
+            None,
+            Arc::default(),
+        );
+        let bank_forks = BankForks::new_rw_arc(bank);
+        let bank = bank_forks.read().unwrap().root_bank();
+        bank.set_fork_graph_in_program_cache(bank_forks.clone());
+
+        // all of the transfers conflict with each other by write-locking the same accounts
+        let recipient = Pubkey::new_unique();
+        let transactions: Vec<_> = (1..=TRANSFER_COUNT)
+            .map(|lamports| {
+                SanitizedTransaction::try_from_legacy_transaction(system_transaction::transfer(
+                    &mint_keypair,
+                    &recipient,
+                    lamports,
+                    genesis_config.hash(),
+                ))
+                .unwrap()
+            })
+            .collect();
+
+        let pool = Self::new_with_config(
+            Some(self.handler_count),
+            self.handler_context.log_messages_bytes_limit,
+            None,
+            None,
+            Arc::new(PrioritizationFeeCache::new(0u64)),
+            self.config.clone(),
+        );
+        // bypass the pooled schedulers and max_spawns_per_second to exercise thread spawning
+        let scheduler = S::spawn(pool.clone(), SchedulingContext::new(bank.clone()));
+        for (index, transaction) in transactions.iter().enumerate() {
+            scheduler.schedule_execution(&(transaction, index));
+        }
+        let (result_with_timings, _summary, uninstalled_scheduler) =
+            Box::new(scheduler).wait_for_termination(false);
+        uninstalled_scheduler.return_to_pool();
+        let health = pool.health();
+        pool.shutdown_all();
+
+        let (result, _timings) =
+            result_with_timings.map_err(|err| format!("self-test scheduler has failed: {err}"))?;
+        result.map_err(|err| format!("self-test transfers have failed: {err}"))?;
+        let expected_balance = TRANSFER_COUNT * (TRANSFER_COUNT + 1) / 2;
+        let balance = bank.get_balance(&recipient);
+        if balance != expected_balance {
+            return Err(format!(
+                "self-test recipient has {balance} lamports, instead of {expected_balance}"
+            ));
+        }
+        match health {
+            SchedulerPoolHealth::Healthy => {}
+            health => return Err(format!("pool is unhealthy after self-test: {health:?}")),
+        }
+
+        let duration = start.elapsed();
+        datapoint_info!(
+            "unified_scheduler-self_test",
+            ("duration_us", duration.as_micros(), i64),
+        );
+        Ok(duration)
+    }
+}
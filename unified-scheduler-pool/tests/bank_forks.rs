@@ -0,0 +1,91 @@
+//! End-to-end wiring of the unified scheduler into `BankForks`, exercising only the public APIs
+//! across the solana-runtime boundary.
+
+use {
+    assert_matches::assert_matches,
+    solana_runtime::{
+        bank::Bank,
+        bank_forks::BankForks,
+        genesis_utils::{create_genesis_config, GenesisConfigInfo},
+        prioritization_fee_cache::PrioritizationFeeCache,
+    },
+    solana_sdk::{
+        account::AccountSharedData,
+        pubkey::Pubkey,
+        signer::{keypair::Keypair, Signer},
+        system_program, system_transaction,
+        transaction::SanitizedTransaction,
+    },
+    solana_unified_scheduler_pool::DefaultSchedulerPool,
+    std::sync::Arc,
+};
+
+#[test]
+fn test_scheduler_with_bank_forks() {
+    solana_logger::setup();
+
+    let GenesisConfigInfo {
+        mut genesis_config,
+        mint_keypair,
+        ..
+    } = create_genesis_config(1_000_000);
+    let payers: Vec<_> = (0..4).map(|_| Keypair::new()).collect();
+    for payer in &payers {
+        genesis_config.add_account(
+            payer.pubkey(),
+            AccountSharedData::new(100_000, 0, &system_program::id()),
+        );
+    }
+    let bank = Bank::new_for_tests(&genesis_config);
+    let bank_forks = BankForks::new_rw_arc(bank);
+    let root_bank = bank_forks.read().unwrap().root_bank();
+    root_bank.set_fork_graph_in_program_cache(bank_forks.clone());
+
+    let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+    let pool =
+        DefaultSchedulerPool::new_dyn(None, None, None, None, ignored_prioritization_fee_cache);
+    bank_forks.write().unwrap().install_scheduler_pool(pool);
+
+    // banks inserted after installing the pool are backed by a scheduler
+    let child_bank = Bank::new_from_parent(root_bank, &Pubkey::default(), 1);
+    let bank = bank_forks.write().unwrap().insert(child_bank);
+    assert!(bank.has_installed_scheduler());
+
+    let blockhash = genesis_config.hash();
+    let shared_recipient = Pubkey::new_unique();
+    let recipients: Vec<_> = payers.iter().map(|_| Pubkey::new_unique()).collect();
+    let mut transactions = vec![];
+    // conflicting: all of them write-lock the mint and the shared recipient
+    for lamports in 1..=3 {
+        transactions.push(system_transaction::transfer(
+            &mint_keypair,
+            &shared_recipient,
+            lamports,
+            blockhash,
+        ));
+    }
+    // non-conflicting: each of them touches disjoint accounts
+    for (payer, recipient) in payers.iter().zip(&recipients) {
+        transactions.push(system_transaction::transfer(
+            payer, recipient, 10, blockhash,
+        ));
+    }
+    let transactions: Vec<_> = transactions
+        .into_iter()
+        .map(SanitizedTransaction::from_transaction_for_tests)
+        .collect();
+    let indexes: Vec<_> = (0..transactions.len()).collect();
+
+    bank.schedule_transaction_executions(transactions.iter().zip(&indexes));
+    assert_matches!(bank.wait_for_completed_scheduler(), Some(Ok((Ok(()), _))));
+
+    assert_eq!(bank.transaction_count(), transactions.len() as u64);
+    assert_eq!(bank.get_balance(&shared_recipient), 1 + 2 + 3);
+    for recipient in &recipients {
+        assert_eq!(bank.get_balance(recipient), 10);
+    }
+    let fee = bank.get_lamports_per_signature();
+    for payer in &payers {
+        assert_eq!(bank.get_balance(&payer.pubkey()), 100_000 - 10 - fee);
+    }
+}
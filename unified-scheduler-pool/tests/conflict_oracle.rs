@@ -0,0 +1,177 @@
+//! Property-based cross-check of the unified scheduler against sequential execution, which serves
+//! as the oracle, over random transfers among a few accounts so that most of them conflict.
+//!
+//! Besides the final state of the accounts, the number of successful transactions writing to each
+//! account is compared, so that compensating divergences in the individual results are caught.
+
+use {
+    crossbeam_channel::unbounded,
+    proptest::prelude::*,
+    solana_ledger::blockstore_processor::{TransactionStatusMessage, TransactionStatusSender},
+    solana_runtime::{
+        bank::Bank,
+        bank_forks::BankForks,
+        genesis_utils::{create_genesis_config, GenesisConfigInfo},
+        installed_scheduler_pool::BankWithScheduler,
+        prioritization_fee_cache::PrioritizationFeeCache,
+    },
+    solana_sdk::{
+        account::AccountSharedData,
+        genesis_config::GenesisConfig,
+        pubkey::Pubkey,
+        signer::{keypair::Keypair, Signer},
+        system_program, system_transaction,
+        transaction::SanitizedTransaction,
+    },
+    solana_unified_scheduler_pool::DefaultSchedulerPool,
+    std::{
+        collections::HashMap,
+        sync::{Arc, RwLock},
+    },
+};
+
+const ACCOUNT_COUNT: usize = 4;
+const INITIAL_LAMPORTS: u64 = 100_000;
+
+fn setup_bank_forks(genesis_config: &GenesisConfig) -> Arc<RwLock<BankForks>> {
+    let bank_forks = BankForks::new_rw_arc(Bank::new_for_tests(genesis_config));
+    let root_bank = bank_forks.read().unwrap().root_bank();
+    root_bank.set_fork_graph_in_program_cache(bank_forks.clone());
+    bank_forks
+}
+
+fn insert_child_bank(bank_forks: &RwLock<BankForks>) -> BankWithScheduler {
+    let root_bank = bank_forks.read().unwrap().root_bank();
+    let child_bank = Bank::new_from_parent(root_bank, &Pubkey::default(), 1);
+    bank_forks.write().unwrap().insert(child_bank)
+}
+
+// Counts the successful transactions writing to each account, given the indexes of the successful
+// ones.
+fn count_writes(
+    transactions: &[SanitizedTransaction],
+    succeeded_indexes: impl IntoIterator<Item = usize>,
+) -> HashMap<Pubkey, usize> {
+    let mut write_counts = HashMap::new();
+    for index in succeeded_indexes {
+        for pubkey in transactions[index].get_account_locks_unchecked().writable {
+            *write_counts.entry(*pubkey).or_default() += 1;
+        }
+    }
+    write_counts
+}
+
+// (payer, recipient, lamports) with the accounts as indexes. Large enough lamports are included
+// so that some transfers fail, or even can't pay their fees, depending on the preceding ones.
+fn transfers() -> impl Strategy<Value = Vec<(usize, usize, u64)>> {
+    prop::collection::vec(
+        (0..ACCOUNT_COUNT, 0..ACCOUNT_COUNT, 1..=INITIAL_LAMPORTS),
+        1..32,
+    )
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn test_scheduler_matches_sequential_execution(transfers in transfers()) {
+        let GenesisConfigInfo {
+            mut genesis_config,
+            ..
+        } = create_genesis_config(1_000_000);
+        let accounts: Vec<_> = (0..ACCOUNT_COUNT).map(|_| Keypair::new()).collect();
+        for account in &accounts {
+            genesis_config.add_account(
+                account.pubkey(),
+                AccountSharedData::new(INITIAL_LAMPORTS, 0, &system_program::id()),
+            );
+        }
+        let blockhash = genesis_config.hash();
+        let transactions: Vec<_> = transfers
+            .iter()
+            .map(|&(payer, recipient, lamports)| {
+                system_transaction::transfer(
+                    &accounts[payer],
+                    &accounts[recipient].pubkey(),
+                    lamports,
+                    blockhash,
+                )
+            })
+            .collect();
+
+        let sanitized_transactions: Vec<_> = transactions
+            .iter()
+            .cloned()
+            .map(SanitizedTransaction::from_transaction_for_tests)
+            .collect();
+
+        // the oracle, whose individual results (including AlreadyProcessed for duplicates) are
+        // reflected in its final state.
+        let oracle_bank_forks = setup_bank_forks(&genesis_config);
+        let oracle_bank = insert_child_bank(&oracle_bank_forks);
+        let oracle_succeeded_indexes: Vec<_> = transactions
+            .iter()
+            .enumerate()
+            .filter(|(_, transaction)| oracle_bank.process_transaction(transaction).is_ok())
+            .map(|(index, _)| index)
+            .collect();
+
+        let bank_forks = setup_bank_forks(&genesis_config);
+        let ignored_prioritization_fee_cache = Arc::new(PrioritizationFeeCache::new(0u64));
+        // the individual results of the scheduler are only observable as transaction statuses
+        let (status_sender, status_receiver) = unbounded();
+        let pool = DefaultSchedulerPool::new(
+            None,
+            None,
+            Some(TransactionStatusSender {
+                sender: status_sender,
+            }),
+            None,
+            ignored_prioritization_fee_cache,
+        );
+        bank_forks
+            .write()
+            .unwrap()
+            .install_scheduler_pool(pool.clone());
+        let bank = insert_child_bank(&bank_forks);
+        prop_assert!(bank.has_installed_scheduler());
+        let indexes: Vec<_> = (0..sanitized_transactions.len()).collect();
+        bank.schedule_transaction_executions(sanitized_transactions.iter().zip(&indexes));
+        prop_assert!(matches!(
+            bank.wait_for_completed_scheduler(),
+            Some(Ok(_))
+        ));
+        // otherwise, the threads of every case would be left running
+        pool.shutdown_all();
+        // all of the statuses have been sent by the time the tasks are completed
+        let succeeded_indexes: Vec<_> = status_receiver
+            .try_iter()
+            .filter_map(|message| match message {
+                TransactionStatusMessage::Batch(batch) => Some(batch),
+                TransactionStatusMessage::Freeze(_) => None,
+            })
+            .flat_map(|batch| {
+                batch
+                    .transaction_indexes
+                    .into_iter()
+                    .zip(batch.execution_results)
+            })
+            .filter(|(_, details)| details.as_ref().is_some_and(|details| details.status.is_ok()))
+            .map(|(index, _)| index)
+            .collect();
+
+        prop_assert_eq!(bank.transaction_count(), oracle_bank.transaction_count());
+        prop_assert_eq!(bank.signature_count(), oracle_bank.signature_count());
+        for account in &accounts {
+            // not only the balances, but also the data, owner and rent epoch
+            prop_assert_eq!(
+                bank.get_account(&account.pubkey()),
+                oracle_bank.get_account(&account.pubkey())
+            );
+        }
+        prop_assert_eq!(
+            count_writes(&sanitized_transactions, succeeded_indexes),
+            count_writes(&sanitized_transactions, oracle_succeeded_indexes)
+        );
+    }
+}
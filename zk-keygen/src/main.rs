@@ -1,8 +1,10 @@
 #![allow(deprecated)]
 
 use {
-    bip39::{Mnemonic, MnemonicType, Seed},
-    clap::{crate_description, crate_name, Arg, ArgMatches, Command},
+    bip39::{Language, Mnemonic, MnemonicType, Seed},
+    clap::{crate_description, crate_name, value_parser, Arg, ArgMatches, Command},
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
     solana_clap_v3_utils::{
         input_parsers::{value_of, STDOUT_OUTFILE_TOKEN},
         input_validators::is_prompt_signer_source,
@@ -18,11 +20,26 @@ use {
         DisplayError,
     },
     solana_sdk::signer::{EncodableKey, SeedDerivable},
-    solana_zk_token_sdk::encryption::{auth_encryption::AeKey, elgamal::ElGamalKeypair},
-    std::{error, str::FromStr},
+    solana_zk_token_sdk::encryption::{
+        auth_encryption::AeKey,
+        elgamal::{ElGamalKeypair, ElGamalPubkey},
+    },
+    std::{
+        error, io,
+        process::exit,
+        str::FromStr,
+        sync::{
+            atomic::{AtomicBool, AtomicU64, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+        time::Instant,
+    },
     thiserror::Error,
 };
 
+const JSON_ARG: &str = "json";
+
 fn output_encodable_key<K: EncodableKey>(
     key: &K,
     outfile: &str,
@@ -38,12 +55,228 @@ fn output_encodable_key<K: EncodableKey>(
     Ok(())
 }
 
-fn app(crate_version: &str) -> Command {
+/// Like `output_encodable_key`, but returns the encoded key instead of writing it to stdout, so
+/// that it can be embedded into the JSON output
+fn output_encodable_key_json<K: EncodableKey>(
+    key: &K,
+    outfile: &str,
+) -> Result<Option<Value>, Box<dyn error::Error>> {
+    if outfile == STDOUT_OUTFILE_TOKEN {
+        let mut encoded = vec![];
+        key.write(&mut encoded)?;
+        Ok(Some(serde_json::from_slice(&encoded)?))
+    } else {
+        key.write_to_file(outfile)?;
+        Ok(None)
+    }
+}
+
+/// Output of all subcommands in `--json` mode. Fields which don't apply to a subcommand are
+/// omitted.
+#[derive(Debug, Default, Serialize)]
+struct JsonOutput {
+    #[serde(rename = "type")]
+    key_type: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pubkey: Option<String>,
+    /// Path of the key file which was read or written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    /// The encoded key, if it was written to stdout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    key: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed_phrase: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verified: Option<bool>,
+    /// The keypairs found by `grind`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keypairs: Option<Vec<JsonGrindKeypair>>,
+}
+
+/// A keypair found by the `grind` subcommand in `--json` mode
+#[derive(Debug, Serialize)]
+struct JsonGrindKeypair {
+    pubkey: String,
+    /// The encoding which the prefixes are matched against
+    pubkey_base58: String,
+    path: String,
+}
+
+#[derive(Debug, Serialize)]
+struct JsonError {
+    error: String,
+}
+
+/// Input of the `recover` subcommand in `--json` mode, read from stdin
+#[derive(Debug, Deserialize)]
+struct JsonRecoverInput {
+    seed_phrase: String,
+    #[serde(default)]
+    passphrase: String,
+}
+
+/// Derives a key from the seed phrase and passphrase given as `JsonRecoverInput`, the same way as
+/// `elgamal_keypair_from_seed_phrase` and `ae_key_from_seed_phrase` do from the prompted ones
+fn key_from_json_recover_input<K: EncodableKey + SeedDerivable>(
+    input: &JsonRecoverInput,
+    skip_validation: bool,
+) -> Result<K, Box<dyn error::Error>> {
+    let seed_phrase = input
+        .seed_phrase
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if skip_validation {
+        return K::from_seed_phrase_and_passphrase(&seed_phrase, &input.passphrase);
+    }
+    let mnemonic = [
+        Language::English,
+        Language::ChineseSimplified,
+        Language::ChineseTraditional,
+        Language::Japanese,
+        Language::Spanish,
+        Language::Korean,
+        Language::French,
+        Language::Italian,
+    ]
+    .into_iter()
+    .find_map(|language| Mnemonic::from_phrase(&seed_phrase, language).ok())
+    .ok_or("Can't get mnemonic from seed phrases")?;
+    let seed = Seed::new(&mnemonic, &input.passphrase);
+    K::from_seed(seed.as_bytes())
+}
+
+fn grind_validator_starts_with(v: &str) -> Result<(), String> {
+    if v.matches(':').count() != 1 || (v.starts_with(':') || v.ends_with(':')) {
+        return Err(String::from("Expected : between PREFIX and COUNT"));
+    }
+    let args: Vec<&str> = v.split(':').collect();
+    bs58::decode(&args[0])
+        .into_vec()
+        .map_err(|err| format!("{}: {:?}", args[0], err))?;
+    let count = args[1].parse::<u64>();
+    if count.is_err() || count.unwrap() == 0 {
+        return Err(String::from("Expected COUNT to be of type u64"));
+    }
+    Ok(())
+}
+
+struct GrindMatch {
+    starts: String,
+    count: AtomicU64,
+}
+
+fn grind_parse_args(ignore_case: bool, starts_with_args: &[String]) -> Vec<GrindMatch> {
+    starts_with_args
+        .iter()
+        .map(|sw| {
+            let args: Vec<&str> = sw.split(':').collect();
+            GrindMatch {
+                starts: if ignore_case {
+                    args[0].to_lowercase()
+                } else {
+                    args[0].to_string()
+                },
+                count: AtomicU64::new(args[1].parse::<u64>().unwrap()),
+            }
+        })
+        .collect()
+}
+
+/// Base58 encoding of an ElGamal pubkey, which is what `grind` matches against. Note that the
+/// pubkey is displayed in base64 elsewhere.
+fn elgamal_pubkey_base58(pubkey: &ElGamalPubkey) -> String {
+    bs58::encode(pubkey.to_bytes()).into_string()
+}
+
+/// Generates random ElGamal keypairs on `num_threads` threads until the requested number of
+/// pubkeys starting with each prefix have been found, and returns the matching keypairs
+fn grind_elgamal_keypairs(
+    grind_matches: Vec<GrindMatch>,
+    ignore_case: bool,
+    num_threads: usize,
+    report_progress: bool,
+) -> Vec<ElGamalKeypair> {
+    let grind_matches = Arc::new(grind_matches);
+    let attempts = Arc::new(AtomicU64::new(1));
+    let found = Arc::new(Mutex::new(Vec::new()));
+    let start = Instant::now();
+    let done = Arc::new(AtomicBool::new(false));
+
+    let thread_handles: Vec<_> = (0..num_threads)
+        .map(|_| {
+            let grind_matches = grind_matches.clone();
+            let attempts = attempts.clone();
+            let found = found.clone();
+            let done = done.clone();
+
+            thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    let attempts = attempts.fetch_add(1, Ordering::Relaxed);
+                    if report_progress && attempts % 100_000 == 0 {
+                        let elapsed = start.elapsed().as_secs_f64();
+                        println!(
+                            "Searched {} keypairs in {}s ({:.0} keypairs/s). {} matches found.",
+                            attempts,
+                            elapsed as u64,
+                            attempts as f64 / elapsed,
+                            found.lock().unwrap().len(),
+                        );
+                    }
+                    let keypair = ElGamalKeypair::new_rand();
+                    let mut pubkey = elgamal_pubkey_base58(keypair.pubkey());
+                    if ignore_case {
+                        pubkey = pubkey.to_lowercase();
+                    }
+                    let mut total_matches_found = 0;
+                    for grind_match in grind_matches.iter() {
+                        if grind_match.count.load(Ordering::Relaxed) == 0 {
+                            total_matches_found += 1;
+                            continue;
+                        }
+                        // claim one of the remaining matches, so that other threads can't
+                        // overshoot the requested count. a keypair only counts towards the first
+                        // prefix it claims, even if it matches several of them.
+                        if pubkey.starts_with(&grind_match.starts)
+                            && grind_match
+                                .count
+                                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |count| {
+                                    count.checked_sub(1)
+                                })
+                                .is_ok()
+                        {
+                            found.lock().unwrap().push(keypair);
+                            break;
+                        }
+                    }
+                    if total_matches_found == grind_matches.len() {
+                        done.store(true, Ordering::Relaxed);
+                    }
+                }
+            })
+        })
+        .collect();
+
+    for thread_handle in thread_handles {
+        thread_handle.join().unwrap();
+    }
+    Arc::try_unwrap(found).unwrap().into_inner().unwrap()
+}
+
+fn app<'a>(num_threads: &'a str, crate_version: &'a str) -> Command<'a> {
     Command::new(crate_name!())
         .about(crate_description!())
         .version(crate_version)
         .subcommand_required(true)
         .arg_required_else_help(true)
+        .arg(
+            Arg::new(JSON_ARG)
+                .long(JSON_ARG)
+                .global(true)
+                .help("Read input from stdin and write output to stdout as JSON, without prompts or banners. \
+                       `new` uses no BIP39 passphrase and `recover` reads {\"seed_phrase\", \"passphrase\"} from stdin"),
+        )
         .subcommand(
             Command::new("new")
                 .about("Generate a new encryption key/keypair file from a random seed phrase and optional BIP39 passphrase")
@@ -104,6 +337,39 @@ fn app(crate_version: &str) -> Command {
                         .help(SKIP_SEED_PHRASE_VALIDATION_ARG.help),
                 )
         )
+        .subcommand(
+            Command::new("verify")
+                .about("Verify the internal consistency of a key/keypair file")
+                .disable_version_flag(true)
+                .arg(
+                    Arg::new("type")
+                        .index(1)
+                        .takes_value(true)
+                        .possible_values(["elgamal", "aes128"])
+                        .value_name("TYPE")
+                        .required(true)
+                        .help("The type of encryption key")
+                )
+                .arg(
+                    Arg::new("keypair")
+                        .index(2)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .help("Filepath or URL to a key/keypair"),
+                )
+                .arg(
+                    Arg::new("expected_pubkey")
+                        .long("expected-pubkey")
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .help("Fail unless the keypair's pubkey matches this base64-encoded ElGamal pubkey"),
+                )
+                .arg(
+                    Arg::new(SKIP_SEED_PHRASE_VALIDATION_ARG.name)
+                        .long(SKIP_SEED_PHRASE_VALIDATION_ARG.long)
+                        .help(SKIP_SEED_PHRASE_VALIDATION_ARG.help),
+                )
+        )
         .subcommand(
             Command::new("recover")
                 .about("Recover keypair from seed phrase and optional BIP39 passphrase")
@@ -144,20 +410,71 @@ fn app(crate_version: &str) -> Command {
                         .help(SKIP_SEED_PHRASE_VALIDATION_ARG.help),
                 ),
         )
+        .subcommand(
+            Command::new("grind")
+                .about("Grind for vanity ElGamal keypairs")
+                .disable_version_flag(true)
+                .arg(
+                    Arg::new("ignore_case")
+                        .long("ignore-case")
+                        .help("Performs case insensitive matches"),
+                )
+                .arg(
+                    Arg::new("starts_with")
+                        .long("starts-with")
+                        .value_name("PREFIX:COUNT")
+                        .number_of_values(1)
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .multiple_values(true)
+                        .required(true)
+                        .validator(grind_validator_starts_with)
+                        .help("Saves specified number of keypairs whose base58-encoded public key starts with the indicated prefix\nExample: --starts-with sol:4\nPREFIX type is Base58\nCOUNT type is u64"),
+                )
+                .arg(
+                    Arg::new("num_threads")
+                        .long("num-threads")
+                        .value_name("NUMBER")
+                        .takes_value(true)
+                        .value_parser(value_parser!(usize))
+                        .default_value(num_threads)
+                        .help("Specify the number of grind threads"),
+                )
+        )
 }
 
 fn main() -> Result<(), Box<dyn error::Error>> {
-    let matches = app(solana_version::version!())
+    let default_num_threads = num_cpus::get().to_string();
+    let matches = app(&default_num_threads, solana_version::version!())
         .try_get_matches()
         .unwrap_or_else(|e| e.exit());
-    do_main(&matches).map_err(|err| DisplayError::new_as_boxed(err).into())
+    if matches.is_present(JSON_ARG) {
+        match do_main(&matches) {
+            Ok(output) => println!("{}", serde_json::to_string(&output.unwrap())?),
+            Err(err) => {
+                let error = JsonError {
+                    error: err.to_string(),
+                };
+                println!("{}", serde_json::to_string(&error)?);
+                exit(1);
+            }
+        }
+        return Ok(());
+    }
+    do_main(&matches)
+        .map(|_| ())
+        .map_err(|err| DisplayError::new_as_boxed(err).into())
 }
 
-fn do_main(matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
+/// Returns the output to be printed in `--json` mode, or `None` otherwise
+fn do_main(matches: &ArgMatches) -> Result<Option<JsonOutput>, Box<dyn error::Error>> {
+    let json = matches.is_present(JSON_ARG);
+    let mut json_output = JsonOutput::default();
     let subcommand = matches.subcommand().unwrap();
     match subcommand {
         ("new", matches) => {
             let key_type: KeyType = value_of(matches, "type").unwrap();
+            json_output.key_type = key_type.name();
 
             let mut path = dirs_next::home_dir().expect("home directory");
             let outfile = if matches.is_present("outfile") {
@@ -180,24 +497,40 @@ fn do_main(matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
             let language = acquire_language(matches);
 
             let mnemonic = Mnemonic::new(mnemonic_type, language);
-            let (passphrase, passphrase_message) = acquire_passphrase_and_message(matches).unwrap();
+            let (passphrase, passphrase_message) = if json {
+                (String::new(), String::new())
+            } else {
+                acquire_passphrase_and_message(matches).unwrap()
+            };
             let seed = Seed::new(&mnemonic, &passphrase);
 
             let silent = matches.is_present("silent");
+            if json {
+                json_output.path = outfile
+                    .filter(|outfile| *outfile != STDOUT_OUTFILE_TOKEN)
+                    .map(str::to_string);
+                json_output.seed_phrase = (!silent).then(|| mnemonic.phrase().to_string());
+            }
 
             match key_type {
                 KeyType::ElGamal => {
-                    if !silent {
+                    if !silent && !json {
                         eprintln!("Generating a new ElGamal keypair");
                     }
 
                     let elgamal_keypair = ElGamalKeypair::from_seed(seed.as_bytes())?;
                     if let Some(outfile) = outfile {
-                        output_encodable_key(&elgamal_keypair, outfile, "new ElGamal keypair")
-                            .map_err(|err| format!("Unable to write {outfile}: {err}"))?;
+                        if json {
+                            json_output.key = output_encodable_key_json(&elgamal_keypair, outfile)
+                                .map_err(|err| format!("Unable to write {outfile}: {err}"))?;
+                        } else {
+                            output_encodable_key(&elgamal_keypair, outfile, "new ElGamal keypair")
+                                .map_err(|err| format!("Unable to write {outfile}: {err}"))?;
+                        }
                     }
+                    json_output.pubkey = Some(elgamal_keypair.pubkey().to_string());
 
-                    if !silent {
+                    if !silent && !json {
                         let phrase: &str = mnemonic.phrase();
                         let divider = String::from_utf8(vec![b'='; phrase.len()]).unwrap();
                         println!(
@@ -207,17 +540,22 @@ fn do_main(matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
                     }
                 }
                 KeyType::Aes128 => {
-                    if !silent {
+                    if !silent && !json {
                         eprintln!("Generating a new AES128 encryption key");
                     }
 
                     let aes_key = AeKey::from_seed(seed.as_bytes())?;
                     if let Some(outfile) = outfile {
-                        output_encodable_key(&aes_key, outfile, "new AES128 key")
-                            .map_err(|err| format!("Unable to write {outfile}: {err}"))?;
+                        if json {
+                            json_output.key = output_encodable_key_json(&aes_key, outfile)
+                                .map_err(|err| format!("Unable to write {outfile}: {err}"))?;
+                        } else {
+                            output_encodable_key(&aes_key, outfile, "new AES128 key")
+                                .map_err(|err| format!("Unable to write {outfile}: {err}"))?;
+                        }
                     }
 
-                    if !silent {
+                    if !silent && !json {
                         let phrase: &str = mnemonic.phrase();
                         let divider = String::from_utf8(vec![b'='; phrase.len()]).unwrap();
                         println!(
@@ -238,6 +576,8 @@ fn do_main(matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
                 path.extend([".config", "solana", key_type.default_file_name()]);
                 path.to_str().unwrap()
             };
+            json_output.key_type = key_type.name();
+            json_output.path = Some(path.to_string());
 
             // wrap the logic inside a match statement in case more keys are supported in the
             // future
@@ -246,11 +586,54 @@ fn do_main(matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
                     let elgamal_keypair =
                         elgamal_keypair_from_path(matches, path, "pubkey recovery", false)?;
                     let elgamal_pubkey = elgamal_keypair.pubkey();
-                    println!("{elgamal_pubkey}");
+                    if json {
+                        json_output.pubkey = Some(elgamal_pubkey.to_string());
+                    } else {
+                        println!("{elgamal_pubkey}");
+                    }
                 }
                 _ => unreachable!(),
             }
         }
+        ("verify", matches) => {
+            let key_type: KeyType = value_of(matches, "type").unwrap();
+
+            let mut path = dirs_next::home_dir().expect("home directory");
+            let path = if matches.is_present("keypair") {
+                matches.value_of("keypair").unwrap()
+            } else {
+                path.extend([".config", "solana", key_type.default_file_name()]);
+                path.to_str().unwrap()
+            };
+            json_output.key_type = key_type.name();
+            json_output.path = Some(path.to_string());
+
+            match key_type {
+                KeyType::ElGamal => {
+                    let elgamal_keypair =
+                        elgamal_keypair_from_path(matches, path, "verification", false)?;
+                    verify_elgamal_keypair(&elgamal_keypair, matches.value_of("expected_pubkey"))?;
+                    if json {
+                        json_output.pubkey = Some(elgamal_keypair.pubkey().to_string());
+                    } else {
+                        println!("Verified ElGamal keypair: {}", elgamal_keypair.pubkey());
+                    }
+                }
+                KeyType::Aes128 => {
+                    if matches.is_present("expected_pubkey") {
+                        return Err(
+                            "--expected-pubkey is only supported for ElGamal keypairs".into()
+                        );
+                    }
+                    let aes_key = ae_key_from_path(matches, path, "verification")?;
+                    verify_ae_key(&aes_key)?;
+                    if !json {
+                        println!("Verified AES128 key");
+                    }
+                }
+            }
+            json_output.verified = Some(true);
+        }
         ("recover", matches) => {
             let key_type: KeyType = value_of(matches, "type").unwrap();
 
@@ -265,34 +648,154 @@ fn do_main(matches: &ArgMatches) -> Result<(), Box<dyn error::Error>> {
             if outfile != STDOUT_OUTFILE_TOKEN {
                 check_for_overwrite(outfile, matches)?;
             }
+            json_output.key_type = key_type.name();
+            json_output.path = (outfile != STDOUT_OUTFILE_TOKEN).then(|| outfile.to_string());
+
+            let skip_validation = matches.is_present(SKIP_SEED_PHRASE_VALIDATION_ARG.name);
+            let json_input = if json {
+                if matches.is_present("prompt_signer") {
+                    return Err(
+                        "--json reads the seed phrase from stdin and doesn't accept KEYPAIR".into(),
+                    );
+                }
+                let json_input: JsonRecoverInput = serde_json::from_reader(io::stdin())
+                    .map_err(|err| format!("Unable to parse JSON input: {err}"))?;
+                Some(json_input)
+            } else {
+                None
+            };
 
             let name = "recover";
             match key_type {
                 KeyType::ElGamal => {
-                    let keypair = if let Some(path) = matches.value_of("prompt_signer") {
+                    let keypair = if let Some(json_input) = &json_input {
+                        key_from_json_recover_input(json_input, skip_validation)?
+                    } else if let Some(path) = matches.value_of("prompt_signer") {
                         elgamal_keypair_from_path(matches, path, name, true)?
                     } else {
-                        let skip_validation =
-                            matches.is_present(SKIP_SEED_PHRASE_VALIDATION_ARG.name);
                         elgamal_keypair_from_seed_phrase(name, skip_validation, true, None, true)?
                     };
-                    output_encodable_key(&keypair, outfile, "recovered ElGamal keypair")?;
+                    if json {
+                        json_output.key = output_encodable_key_json(&keypair, outfile)?;
+                        json_output.pubkey = Some(keypair.pubkey().to_string());
+                    } else {
+                        output_encodable_key(&keypair, outfile, "recovered ElGamal keypair")?;
+                    }
                 }
                 KeyType::Aes128 => {
-                    let key = if let Some(path) = matches.value_of("prompt_signer") {
+                    let key = if let Some(json_input) = &json_input {
+                        key_from_json_recover_input(json_input, skip_validation)?
+                    } else if let Some(path) = matches.value_of("prompt_signer") {
                         ae_key_from_path(matches, path, name)?
                     } else {
-                        let skip_validation =
-                            matches.is_present(SKIP_SEED_PHRASE_VALIDATION_ARG.name);
                         ae_key_from_seed_phrase(name, skip_validation, None, true)?
                     };
-                    output_encodable_key(&key, outfile, "recovered AES128 key")?;
+                    if json {
+                        json_output.key = output_encodable_key_json(&key, outfile)?;
+                    } else {
+                        output_encodable_key(&key, outfile, "recovered AES128 key")?;
+                    }
                 }
             }
         }
+        ("grind", matches) => {
+            let ignore_case = matches.is_present("ignore_case");
+            let starts_with_args: Vec<String> = matches.values_of_t_or_exit("starts_with");
+            let num_threads = *matches.get_one::<usize>("num_threads").unwrap();
+
+            let grind_matches = grind_parse_args(ignore_case, &starts_with_args);
+            if !json {
+                println!("Searching with {num_threads} threads for:");
+                for grind_match in &grind_matches {
+                    let count = grind_match.count.load(Ordering::Relaxed);
+                    println!(
+                        "\t{} {} that {} with '{}'",
+                        count,
+                        if count > 1 { "pubkeys" } else { "pubkey" },
+                        if count > 1 { "start" } else { "starts" },
+                        grind_match.starts,
+                    );
+                }
+            }
+
+            json_output.key_type = KeyType::ElGamal.name();
+            let mut found = vec![];
+            for keypair in grind_elgamal_keypairs(grind_matches, ignore_case, num_threads, !json) {
+                let pubkey_base58 = elgamal_pubkey_base58(keypair.pubkey());
+                let outfile = format!("{pubkey_base58}.json");
+                if json {
+                    output_encodable_key_json(&keypair, &outfile)
+                        .map_err(|err| format!("Unable to write {outfile}: {err}"))?;
+                    found.push(JsonGrindKeypair {
+                        pubkey: keypair.pubkey().to_string(),
+                        pubkey_base58,
+                        path: outfile,
+                    });
+                } else {
+                    output_encodable_key(&keypair, &outfile, "ElGamal keypair")
+                        .map_err(|err| format!("Unable to write {outfile}: {err}"))?;
+                    println!(
+                        "ElGamal pubkey: {} (base58: {pubkey_base58})",
+                        keypair.pubkey()
+                    );
+                }
+            }
+            json_output.keypairs = json.then_some(found);
+        }
         _ => unreachable!(),
     }
 
+    Ok(json.then_some(json_output))
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum VerifyError {
+    #[error("secret key is zero")]
+    ZeroSecretKey,
+    #[error("pubkey {stored} is not derived from the secret key (expected {derived})")]
+    PubkeyMismatch { stored: String, derived: String },
+    #[error("pubkey {actual} doesn't match the expected pubkey {expected}")]
+    UnexpectedPubkey { actual: String, expected: String },
+    #[error("key failed to decrypt its own ciphertext")]
+    RoundTripFailure,
+}
+
+fn verify_elgamal_keypair(
+    keypair: &ElGamalKeypair,
+    expected_pubkey: Option<&str>,
+) -> Result<(), VerifyError> {
+    // ElGamalPubkey::new() asserts a non-zero secret; reject it gracefully beforehand
+    if keypair.secret().as_bytes().iter().all(|byte| *byte == 0) {
+        return Err(VerifyError::ZeroSecretKey);
+    }
+
+    let derived = ElGamalPubkey::new(keypair.secret());
+    if &derived != keypair.pubkey() {
+        return Err(VerifyError::PubkeyMismatch {
+            stored: keypair.pubkey().to_string(),
+            derived: derived.to_string(),
+        });
+    }
+
+    if let Some(expected) = expected_pubkey {
+        let actual = keypair.pubkey().to_string();
+        if actual != expected {
+            return Err(VerifyError::UnexpectedPubkey {
+                actual,
+                expected: expected.to_string(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn verify_ae_key(key: &AeKey) -> Result<(), VerifyError> {
+    const PROBE_AMOUNT: u64 = 55;
+
+    if key.decrypt(&key.encrypt(PROBE_AMOUNT)) != Some(PROBE_AMOUNT) {
+        return Err(VerifyError::RoundTripFailure);
+    }
     Ok(())
 }
 
@@ -302,6 +805,13 @@ enum KeyType {
 }
 
 impl KeyType {
+    fn name(&self) -> &'static str {
+        match self {
+            KeyType::ElGamal => "elgamal",
+            KeyType::Aes128 => "aes128",
+        }
+    }
+
     fn default_file_name(&self) -> &str {
         match self {
             KeyType::ElGamal => "elgamal.json",
@@ -334,9 +844,9 @@ mod tests {
         tempfile::{tempdir, TempDir},
     };
 
-    fn process_test_command(args: &[&str]) -> Result<(), Box<dyn error::Error>> {
+    fn process_test_command(args: &[&str]) -> Result<Option<JsonOutput>, Box<dyn error::Error>> {
         let solana_version = solana_version::version!();
-        let app_matches = app(solana_version).get_matches_from(args);
+        let app_matches = app("1", solana_version).get_matches_from(args);
         do_main(&app_matches)
     }
 
@@ -350,7 +860,7 @@ mod tests {
         let solana_version = solana_version::version!();
 
         // run clap internal assert statements
-        app(solana_version).debug_assert();
+        app("1", solana_version).debug_assert();
     }
 
     #[test]
@@ -450,4 +960,204 @@ mod tests {
 
         process_test_command(&["solana-keygen", "pubkey", "elgamal", &keypair_path]).unwrap();
     }
+
+    #[test]
+    fn test_verify() {
+        let keypair_out_dir = tempdir().unwrap();
+        // use `Pubkey::new_unique()` to generate names for temporary key files
+        let keypair_path = tmp_outfile_path(&keypair_out_dir, &Pubkey::new_unique().to_string());
+        let aes_key_path = tmp_outfile_path(&keypair_out_dir, &Pubkey::new_unique().to_string());
+
+        let keypair = ElGamalKeypair::new_rand();
+        keypair.write_to_file(&keypair_path).unwrap();
+        AeKey::new_rand().write_to_file(&aes_key_path).unwrap();
+
+        process_test_command(&["solana-zk-keygen", "verify", "elgamal", &keypair_path]).unwrap();
+        process_test_command(&[
+            "solana-zk-keygen",
+            "verify",
+            "elgamal",
+            &keypair_path,
+            "--expected-pubkey",
+            &keypair.pubkey().to_string(),
+        ])
+        .unwrap();
+        process_test_command(&["solana-zk-keygen", "verify", "aes128", &aes_key_path]).unwrap();
+
+        // unexpected pubkey
+        let other_pubkey = ElGamalKeypair::new_rand().pubkey().to_string();
+        let result = process_test_command(&[
+            "solana-zk-keygen",
+            "verify",
+            "elgamal",
+            &keypair_path,
+            "--expected-pubkey",
+            &other_pubkey,
+        ])
+        .unwrap_err()
+        .to_string();
+        let expected = format!(
+            "pubkey {} doesn't match the expected pubkey {other_pubkey}",
+            keypair.pubkey()
+        );
+        assert_eq!(result, expected);
+
+        // inconsistent keypair: the stored pubkey isn't derived from the secret
+        let other_keypair = ElGamalKeypair::new_rand();
+        let corrupted =
+            ElGamalKeypair::new_for_tests(*other_keypair.pubkey(), keypair.secret().clone());
+        assert_eq!(
+            verify_elgamal_keypair(&corrupted, None),
+            Err(VerifyError::PubkeyMismatch {
+                stored: other_keypair.pubkey().to_string(),
+                derived: keypair.pubkey().to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let out_dir = tempdir().unwrap();
+        // use `Pubkey::new_unique()` to generate names for temporary key files
+        let keypair_path = tmp_outfile_path(&out_dir, &Pubkey::new_unique().to_string());
+
+        let output = process_test_command(&[
+            "solana-zk-keygen",
+            "new",
+            "elgamal",
+            "--outfile",
+            &keypair_path,
+            "--json",
+        ])
+        .unwrap()
+        .unwrap();
+        let keypair = ElGamalKeypair::read_from_file(&keypair_path).unwrap();
+        assert_eq!(output.key_type, "elgamal");
+        assert_eq!(output.pubkey, Some(keypair.pubkey().to_string()));
+        assert_eq!(output.path, Some(keypair_path.clone()));
+        assert!(output.key.is_none());
+
+        // the key derived from the printed seed phrase without passphrase is the generated one
+        let recovered: ElGamalKeypair = key_from_json_recover_input(
+            &JsonRecoverInput {
+                seed_phrase: output.seed_phrase.unwrap(),
+                passphrase: String::new(),
+            },
+            false,
+        )
+        .unwrap();
+        assert_eq!(recovered.pubkey(), keypair.pubkey());
+
+        // the key is embedded into the output if written to stdout
+        let output = process_test_command(&[
+            "solana-zk-keygen",
+            "new",
+            "aes128",
+            "--outfile",
+            STDOUT_OUTFILE_TOKEN,
+            "--silent",
+            "--json",
+        ])
+        .unwrap()
+        .unwrap();
+        assert!(output.key.is_some());
+        assert!(output.path.is_none());
+        assert!(output.seed_phrase.is_none());
+
+        let output = process_test_command(&[
+            "solana-zk-keygen",
+            "pubkey",
+            "elgamal",
+            &keypair_path,
+            "--json",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(output.pubkey, Some(keypair.pubkey().to_string()));
+
+        let output = process_test_command(&[
+            "solana-zk-keygen",
+            "verify",
+            "elgamal",
+            &keypair_path,
+            "--json",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(output.verified, Some(true));
+
+        // no JSON output without --json
+        assert!(
+            process_test_command(&["solana-zk-keygen", "pubkey", "elgamal", &keypair_path])
+                .unwrap()
+                .is_none()
+        );
+    }
+    #[test]
+    fn test_grind() {
+        let ignore_case = true;
+        let grind_matches = grind_parse_args(ignore_case, &["A:2".to_string(), "b:1".to_string()]);
+        let keypairs = grind_elgamal_keypairs(grind_matches, ignore_case, 2, false);
+        assert_eq!(keypairs.len(), 3);
+        let prefixes: Vec<_> = keypairs
+            .iter()
+            .map(|keypair| elgamal_pubkey_base58(keypair.pubkey())[..1].to_lowercase())
+            .collect();
+        assert_eq!(prefixes.iter().filter(|prefix| *prefix == "a").count(), 2);
+        assert_eq!(prefixes.iter().filter(|prefix| *prefix == "b").count(), 1);
+        for keypair in &keypairs {
+            verify_elgamal_keypair(keypair, None).unwrap();
+        }
+
+        assert!(grind_validator_starts_with("A:2").is_ok());
+        assert!(grind_validator_starts_with("A:0").is_err());
+        assert!(grind_validator_starts_with("0:1").is_err());
+        assert!(grind_validator_starts_with("A").is_err());
+    }
+
+    #[test]
+    fn test_grind_overlapping_prefixes() {
+        let ignore_case = true;
+
+        // every pubkey starting with "ab" also starts with "a"
+        let grind_matches = grind_parse_args(ignore_case, &["A:1".to_string(), "AB:1".to_string()]);
+        let keypairs = grind_elgamal_keypairs(grind_matches, ignore_case, 2, false);
+        assert_eq!(keypairs.len(), 2);
+        let pubkeys: Vec<_> = keypairs
+            .iter()
+            .map(|keypair| elgamal_pubkey_base58(keypair.pubkey()).to_lowercase())
+            .collect();
+        assert!(pubkeys.iter().all(|pubkey| pubkey.starts_with('a')));
+        assert!(pubkeys.iter().any(|pubkey| pubkey.starts_with("ab")));
+
+        // identical prefixes always overlap
+        let grind_matches = grind_parse_args(ignore_case, &["a:1".to_string(), "A:1".to_string()]);
+        let keypairs = grind_elgamal_keypairs(grind_matches, ignore_case, 2, false);
+        assert_eq!(keypairs.len(), 2);
+    }
+
+    #[test]
+    fn test_grind_json() {
+        let output = process_test_command(&[
+            "solana-zk-keygen",
+            "grind",
+            "--starts-with",
+            "A:1",
+            "--ignore-case",
+            "--json",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(output.key_type, "elgamal");
+        let found = output.keypairs.unwrap();
+        assert_eq!(found.len(), 1);
+        let found = &found[0];
+        assert!(found.pubkey_base58.to_lowercase().starts_with('a'));
+        assert_eq!(found.path, format!("{}.json", found.pubkey_base58));
+
+        let keypair = ElGamalKeypair::read_from_file(&found.path).unwrap();
+        std::fs::remove_file(&found.path).unwrap();
+        assert_eq!(found.pubkey, keypair.pubkey().to_string());
+        assert_eq!(found.pubkey_base58, elgamal_pubkey_base58(keypair.pubkey()));
+    }
 }